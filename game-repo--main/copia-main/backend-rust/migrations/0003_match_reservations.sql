@@ -0,0 +1,14 @@
+-- Backend-issued match ids: a reservation sits in waiting_create_tx until the
+-- create_game transaction is seen on-chain or create_expires_at passes.
+alter table matches add column if not exists create_expires_at timestamptz;
+
+create index if not exists idx_matches_waiting_create
+  on matches (create_expires_at)
+  where match_status = 'waiting_create_tx';
+
+-- Client-chosen ids were inserted explicitly; start allocating above them.
+select setval(
+  pg_get_serial_sequence('matches', 'match_id'),
+  (select coalesce(max(match_id), 0) + 1 from matches),
+  false
+);
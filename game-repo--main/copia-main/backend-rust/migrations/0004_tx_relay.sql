@@ -0,0 +1,8 @@
+-- Relayed create_game/join_game transactions: when the signature was submitted,
+-- so the relay watcher can give up on transactions that never land.
+alter table matches add column if not exists create_tx_sent_at timestamptz;
+alter table matches add column if not exists join_tx_sent_at timestamptz;
+
+create index if not exists idx_matches_pending_join_tx
+  on matches (join_tx_sent_at)
+  where match_status = 'created_on_chain' and join_tx_sig is not null;
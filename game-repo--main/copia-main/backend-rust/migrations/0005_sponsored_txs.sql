@@ -0,0 +1,12 @@
+-- Player transactions whose fee the backend sponsor paid; counted for quotas.
+create table if not exists sponsored_txs (
+  signature text primary key,
+  wallet text not null,
+  match_id bigint not null,
+  instruction text not null,
+  created_at timestamptz not null default now()
+);
+
+create index if not exists idx_sponsored_txs_wallet_created_at
+  on sponsored_txs (wallet, created_at);
+create index if not exists idx_sponsored_txs_created_at on sponsored_txs (created_at);
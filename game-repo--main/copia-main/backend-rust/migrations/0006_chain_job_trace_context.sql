@@ -0,0 +1,3 @@
+-- W3C `traceparent` of the request that enqueued the job, so the finalizer's spans
+-- can link back to it.
+alter table chain_jobs add column if not exists trace_parent text;
@@ -0,0 +1,16 @@
+-- Per-server HMAC keys for internal requests, picked by the `X-Key-Id` header.
+-- A server may hold several active keys while it rotates. `secret` is the raw
+-- HMAC key: verifying a signature needs it, so it cannot be stored hashed.
+create table if not exists server_credentials (
+  key_id text primary key,
+  server_id text not null,
+  secret text not null,
+  created_at timestamptz not null default now(),
+  expires_at timestamptz,
+  revoked_at timestamptz
+);
+
+create index if not exists idx_server_credentials_server_id on server_credentials (server_id);
+
+-- The authenticated server that reported the result; null for the shared secret.
+alter table matches add column if not exists result_reported_by text;
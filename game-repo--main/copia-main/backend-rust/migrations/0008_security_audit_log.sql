@@ -0,0 +1,17 @@
+-- Denied or overridden internal requests, e.g. a server finalizing a match it
+-- does not host. Append-only; nothing in the backend updates or deletes rows.
+create table if not exists security_audit_log (
+  id bigserial primary key,
+  occurred_at timestamptz not null default now(),
+  event text not null,
+  -- `server`, `shared` or `admin`: which kind of key signed the request.
+  caller text not null,
+  caller_server_id text,
+  caller_key_id text,
+  -- The server the request tried to act as, or the match's assigned server.
+  target_server_id text,
+  match_id bigint,
+  detail text not null
+);
+
+create index if not exists idx_security_audit_log_occurred_at on security_audit_log (occurred_at);
@@ -0,0 +1,4 @@
+-- Reported by the assigned game server once both players have connected; the
+-- match moves to `in_progress`. Null for matches that never started.
+alter table matches add column if not exists kicked_off_at timestamptz;
+alter table matches add column if not exists kickoff_wallets text[];
@@ -0,0 +1,22 @@
+-- `broken` results whose reason_code maps to hold_for_review wait here for an
+-- operator, who resolves them with an admin-override finalize.
+alter table matches drop constraint if exists matches_match_status_check;
+alter table matches add constraint matches_match_status_check check (
+  match_status in (
+    'waiting_create_tx',
+    'created_on_chain',
+    'joined_on_chain',
+    'in_progress',
+    'result_pending_finalize',
+    'held_for_review',
+    'finalizing',
+    'settled',
+    'refunded'
+  )
+);
+
+alter table matches add column if not exists held_at timestamptz;
+alter table matches add column if not exists held_by text;
+alter table matches add column if not exists hold_reason_code text;
+alter table matches add column if not exists hold_reason_detail text;
+alter table matches add column if not exists hold_idempotency_key text;
@@ -0,0 +1,5 @@
+-- When the assigned game server first reported each player connected. A match
+-- kicks off once both have been; a `broken` result settles only to a player
+-- seen here, even if the other never connected.
+alter table matches add column if not exists player1_connected_at timestamptz;
+alter table matches add column if not exists player2_connected_at timestamptz;
@@ -0,0 +1,154 @@
+//! Operator endpoints under `/v1/admin`, signed with `ADMIN_HMAC_SECRET` and
+//! `X-Key-Id: admin`.
+
+use axum::{
+    body::Bytes,
+    extract::{OriginalUri, Path, State},
+    http::{HeaderMap, Method},
+    routing::post,
+    Json, Router,
+};
+use chrono::{Duration, Utc};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::{
+    api::internal_auth::verify_internal_hmac,
+    app_state::AppState,
+    db::server_credentials::{self, ServerCredential},
+    error::AppError,
+    models::dto::{
+        IssueCredentialRequest, IssuedCredentialResponse, ServerCredentialResponse,
+        ServerCredentialsResponse,
+    },
+};
+
+const KEY_ID_BYTES: usize = 8;
+const SECRET_BYTES: usize = 32;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/admin/servers/:server_id/credentials",
+            post(issue_credential).get(list_credentials),
+        )
+        .route("/admin/credentials/:key_id/revoke", post(revoke_credential))
+}
+
+/// POST /v1/admin/servers/{server_id}/credentials — issues a new key for the
+/// server. Earlier keys stay valid until revoked, so servers can rotate.
+async fn issue_credential(
+    State(state): State<AppState>,
+    Path(server_id): Path<String>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<IssuedCredentialResponse>, AppError> {
+    verify_internal_hmac(&state, &method, &uri, &headers, body.as_ref())
+        .await?
+        .require_admin()?;
+
+    let req: IssueCredentialRequest = if body.is_empty() {
+        IssueCredentialRequest::default()
+    } else {
+        serde_json::from_slice(body.as_ref())
+            .map_err(|e| AppError::BadRequest(format!("invalid JSON: {e}")))?
+    };
+    let server_id = server_id.trim();
+    if server_id.is_empty() {
+        return Err(AppError::BadRequest("server_id is required".into()));
+    }
+    let expires_at = match req.expires_in_seconds {
+        Some(seconds) if seconds <= 0 => {
+            return Err(AppError::BadRequest(
+                "expires_in_seconds must be positive".into(),
+            ))
+        }
+        Some(seconds) => Some(
+            Duration::try_seconds(seconds)
+                .and_then(|lifetime| Utc::now().checked_add_signed(lifetime))
+                .ok_or_else(|| AppError::BadRequest("expires_in_seconds is too large".into()))?,
+        ),
+        None => None,
+    };
+
+    let key_id = format!("sk_{}", random_hex(KEY_ID_BYTES)?);
+    let secret = random_hex(SECRET_BYTES)?;
+    let credential =
+        server_credentials::insert_credential(&state.pool, &key_id, server_id, &secret, expires_at)
+            .await?;
+
+    tracing::info!(server_id, key_id = %credential.key_id, "server credential issued");
+    Ok(Json(IssuedCredentialResponse {
+        key_id: credential.key_id,
+        server_id: credential.server_id,
+        secret: credential.secret,
+        created_at: credential.created_at.timestamp(),
+        expires_at: credential.expires_at.map(|at| at.timestamp()),
+    }))
+}
+
+/// GET /v1/admin/servers/{server_id}/credentials — the server's keys, without secrets.
+async fn list_credentials(
+    State(state): State<AppState>,
+    Path(server_id): Path<String>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Result<Json<ServerCredentialsResponse>, AppError> {
+    verify_internal_hmac(&state, &method, &uri, &headers, b"")
+        .await?
+        .require_admin()?;
+
+    let credentials = server_credentials::list_credentials(&state.pool, &server_id)
+        .await?
+        .into_iter()
+        .map(credential_response)
+        .collect();
+    Ok(Json(ServerCredentialsResponse { credentials }))
+}
+
+/// POST /v1/admin/credentials/{key_id}/revoke — the key stops working immediately.
+async fn revoke_credential(
+    State(state): State<AppState>,
+    Path(key_id): Path<String>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ServerCredentialResponse>, AppError> {
+    verify_internal_hmac(&state, &method, &uri, &headers, body.as_ref())
+        .await?
+        .require_admin()?;
+
+    let credential = server_credentials::revoke_credential(&state.pool, &key_id)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("credential not found".into()))?;
+
+    tracing::warn!(
+        server_id = %credential.server_id,
+        key_id = %credential.key_id,
+        "server credential revoked"
+    );
+    Ok(Json(credential_response(credential)))
+}
+
+fn credential_response(credential: ServerCredential) -> ServerCredentialResponse {
+    let active = credential.revoked_at.is_none() && !credential.is_expired(Utc::now());
+    ServerCredentialResponse {
+        key_id: credential.key_id,
+        server_id: credential.server_id,
+        created_at: credential.created_at.timestamp(),
+        expires_at: credential.expires_at.map(|at| at.timestamp()),
+        revoked_at: credential.revoked_at.map(|at| at.timestamp()),
+        active,
+    }
+}
+
+fn random_hex(len: usize) -> Result<String, AppError> {
+    let mut bytes = vec![0u8; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| AppError::Internal("failed to generate random bytes".into()))?;
+    Ok(hex::encode(bytes))
+}
@@ -5,28 +5,36 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
+use chrono::{DateTime, TimeZone, Utc};
+use solana_sdk::pubkey::Pubkey;
 use sqlx::Row;
 
 use crate::{
+    api::join_tickets::verify_optional_wallet_proof,
     app_state::AppState,
+    db::matches as matches_db,
     error::AppError,
-    models::dto::{
-        AcceptChallengeRequest, AcceptChallengeResponse, ChallengeInfo, ChallengeListResponse,
-        ChallengeStatusResponse, RegisterChallengeRequest, RegisterChallengeResponse,
+    models::{
+        dto::{
+            AcceptChallengeRequest, AcceptChallengeResponse, ChallengeInfo, ChallengeListResponse,
+            ChallengeStatusResponse, RegisterChallengeRequest, RegisterChallengeResponse,
+            ReserveMatchRequest, ReserveMatchResponse,
+        },
+        enums::MatchStatus,
     },
+    solana::client::fetch_and_decode_game_account,
 };
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/challenges", get(list_challenges).post(register_challenge))
-        .route("/challenges/{game_pda}/accept", post(accept_challenge))
-        .route("/challenges/{game_pda}/status", get(challenge_status))
+        .route("/challenges/reserve", post(reserve_match))
+        .route("/challenges/:game_pda/accept", post(accept_challenge))
+        .route("/challenges/:game_pda/status", get(challenge_status))
 }
 
 /// GET /v1/challenges — list open challenges (status = created_on_chain)
-async fn list_challenges(
-    State(state): State<AppState>,
-) -> Result<impl IntoResponse, AppError> {
+async fn list_challenges(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
     let rows = sqlx::query(
         r#"
         select game_pda, player1_pubkey, entry_lamports, match_id,
@@ -57,6 +65,49 @@ async fn list_challenges(
     Ok(Json(ChallengeListResponse { challenges }))
 }
 
+/// POST /v1/challenges/reserve — allocate a match_id before the on-chain create_game.
+/// The client must create the game at the returned PDAs before `expires_at`.
+async fn reserve_match(
+    State(state): State<AppState>,
+    Json(body): Json<ReserveMatchRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    if body.creator_pubkey.parse::<Pubkey>().is_err() {
+        return Err(AppError::BadRequest(
+            "creator_pubkey is not a valid pubkey".into(),
+        ));
+    }
+    if body.entry_amount == 0 {
+        return Err(AppError::BadRequest("entry_amount must be > 0".into()));
+    }
+    let entry_lamports = i64::try_from(body.entry_amount)
+        .map_err(|_| AppError::BadRequest("entry_amount is too large".into()))?;
+    let env = state.requested_env(body.program_id.as_deref())?;
+
+    let reserved = matches_db::reserve_match(
+        &state.pool,
+        &env.config.program_id,
+        &env.config.authority_pubkey,
+        &body.creator_pubkey,
+        entry_lamports,
+        state.config.match_reservation_ttl_seconds,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ReserveMatchResponse {
+            match_id: reserved.match_id,
+            join_code: reserved.join_code,
+            program_id: env.config.program_id.clone(),
+            authority_pubkey: env.config.authority_pubkey.clone(),
+            game_pda: reserved.game_pda,
+            vault_pda: reserved.vault_pda,
+            match_status: MatchStatus::WaitingCreateTx,
+            expires_at: reserved.create_expires_at.timestamp(),
+        }),
+    ))
+}
+
 /// POST /v1/challenges — register a new challenge after on-chain create_game.
 /// Assigns a server immediately so the creator can connect right away.
 async fn register_challenge(
@@ -75,6 +126,16 @@ async fn register_challenge(
     if body.match_id == 0 {
         return Err(AppError::BadRequest("match_id must be > 0".into()));
     }
+    // The creator's ticket goes only to whoever holds the creator's wallet.
+    let proven = verify_optional_wallet_proof(
+        &state.pool,
+        &body.creator_pubkey,
+        &body.game_pda,
+        body.timestamp,
+        body.nonce.as_deref(),
+        body.signature.as_deref(),
+    )
+    .await?;
 
     let match_id = body.match_id as i64;
     let entry_lamports = body.entry_amount as i64;
@@ -82,6 +143,66 @@ async fn register_challenge(
     // Deterministic join code
     let join_code = crate::db::matches::join_code_from_match_id(match_id)?;
 
+    // A reserved match_id may only be registered by the creator it was issued to.
+    let reservation = sqlx::query(
+        r#"
+        select program_id, player1_pubkey, game_pda, match_status, assigned_server_id
+        from matches
+        where match_id = $1
+        "#,
+    )
+    .bind(match_id)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to lookup match: {e}")))?;
+    // When the game was created on-chain, if this request is the first to see it.
+    let mut created_onchain_at: Option<DateTime<Utc>> = None;
+    let reserved = match reservation {
+        Some(row) => {
+            let status: String = row.get("match_status");
+            let server: Option<String> = row.get("assigned_server_id");
+            let reservable = matches!(status.as_str(), "waiting_create_tx" | "created_on_chain");
+            if !reservable || server.is_some() {
+                return Err(AppError::Conflict("match_id is already registered".into()));
+            }
+            if row.get::<String, _>("player1_pubkey") != body.creator_pubkey
+                || row.get::<String, _>("game_pda") != body.game_pda
+            {
+                return Err(AppError::Conflict(
+                    "match_id is reserved for a different creator or game_pda".into(),
+                ));
+            }
+            if status == "waiting_create_tx" {
+                let env = state.env(row.get("program_id"))?;
+                let game = fetch_and_decode_game_account(
+                    env.chain.as_ref(),
+                    &env.idl,
+                    &env.config.program_id,
+                    &body.game_pda,
+                )
+                .await
+                .map_err(|e| {
+                    AppError::BadRequest(format!("create_game not seen on-chain yet: {e:#}"))
+                })?;
+                created_onchain_at = Some(
+                    Utc.timestamp_opt(game.created_at, 0)
+                        .single()
+                        .ok_or_else(|| {
+                            AppError::Internal("invalid on-chain created_at timestamp".into())
+                        })?,
+                );
+            }
+            true
+        }
+        None => {
+            tracing::warn!(
+                match_id,
+                "challenge registered with a client-chosen match_id"
+            );
+            false
+        }
+    };
+
     // Find an idle server from the pool so the creator can connect immediately
     let server_row = sqlx::query(
         r#"
@@ -97,9 +218,7 @@ async fn register_challenge(
     .fetch_optional(&state.pool)
     .await
     .map_err(|e| AppError::Internal(format!("failed to find idle server: {e}")))?
-    .ok_or_else(|| {
-        AppError::Internal("no idle servers available — try again shortly".into())
-    })?;
+    .ok_or_else(|| AppError::Internal("no idle servers available — try again shortly".into()))?;
 
     let server_id: String = server_row.get("server_id");
     let server_ip: String = server_row.get("ip");
@@ -115,7 +234,41 @@ async fn register_challenge(
     .await
     .map_err(|e| AppError::Internal(format!("failed to assign server: {e}")))?;
 
+    if reserved {
+        sqlx::query(
+            r#"
+            update matches
+            set match_status = 'created_on_chain',
+                assigned_server_id = $2,
+                created_onchain_at = coalesce($3, created_onchain_at, now()),
+                create_expires_at = null,
+                updated_at = now()
+            where match_id = $1
+            "#,
+        )
+        .bind(match_id)
+        .bind(&server_id)
+        .bind(created_onchain_at)
+        .execute(&state.pool)
+        .await
+        .map_err(|e| AppError::Internal(format!("failed to register challenge: {e}")))?;
+
+        let (join_ticket, join_ticket_expires_at) =
+            ticket_if_proven(&state, proven, &body.creator_pubkey, match_id, &server_id);
+        return Ok((
+            StatusCode::CREATED,
+            Json(RegisterChallengeResponse {
+                ok: true,
+                server_ip,
+                server_port,
+                join_ticket,
+                join_ticket_expires_at,
+            }),
+        ));
+    }
+
     // Insert match with assigned server
+    let env = state.requested_env(body.program_id.as_deref())?;
     sqlx::query(
         r#"
         insert into matches (
@@ -129,8 +282,8 @@ async fn register_challenge(
     )
     .bind(match_id)
     .bind(&join_code)
-    .bind(&state.config.program_id)
-    .bind(&state.config.authority_pubkey)
+    .bind(&env.config.program_id)
+    .bind(&env.config.authority_pubkey)
     .bind(&body.game_pda)
     .bind(&body.creator_pubkey)
     .bind(entry_lamports)
@@ -139,14 +292,37 @@ async fn register_challenge(
     .await
     .map_err(|e| AppError::Internal(format!("failed to register challenge: {e}")))?;
 
-    Ok((StatusCode::CREATED, Json(RegisterChallengeResponse {
-        ok: true,
-        server_ip,
-        server_port,
-    })))
+    let (join_ticket, join_ticket_expires_at) =
+        ticket_if_proven(&state, proven, &body.creator_pubkey, match_id, &server_id);
+    Ok((
+        StatusCode::CREATED,
+        Json(RegisterChallengeResponse {
+            ok: true,
+            server_ip,
+            server_port,
+            join_ticket,
+            join_ticket_expires_at,
+        }),
+    ))
 }
 
-/// POST /v1/challenges/{game_pda}/accept — accept a challenge.
+/// A join ticket for `wallet` and its expiry, if the request proved it holds
+/// the wallet.
+fn ticket_if_proven(
+    state: &AppState,
+    proven: bool,
+    wallet: &str,
+    match_id: i64,
+    server_id: &str,
+) -> (Option<String>, Option<i64>) {
+    if !proven {
+        return (None, None);
+    }
+    let ticket = state.join_tickets.issue(wallet, match_id, server_id);
+    (Some(ticket.ticket), Some(ticket.expires_at))
+}
+
+/// POST /v1/challenges/:game_pda/accept — accept a challenge.
 /// Server was already assigned on creation; returns the same server info.
 async fn accept_challenge(
     State(state): State<AppState>,
@@ -156,26 +332,66 @@ async fn accept_challenge(
     if body.acceptor_pubkey.is_empty() {
         return Err(AppError::BadRequest("acceptor_pubkey is required".into()));
     }
+    // Only the acceptor's own wallet may become player2 and get its ticket.
+    let proven = verify_optional_wallet_proof(
+        &state.pool,
+        &body.acceptor_pubkey,
+        &game_pda,
+        body.timestamp,
+        body.nonce.as_deref(),
+        body.signature.as_deref(),
+    )
+    .await?;
 
     // Lookup the challenge and its already-assigned server
     let match_row = sqlx::query(
         r#"
-        select m.match_id, sp.ip as server_ip, sp.port as server_port
+        select m.match_id, m.program_id,
+               sp.server_id, sp.ip as server_ip, sp.port as server_port
         from matches m
         join server_pool sp on sp.server_id = m.assigned_server_id
-        where m.game_pda = $1 and m.match_status = 'created_on_chain'
+        where m.game_pda = $1
+          and (
+            m.match_status = 'created_on_chain'
+            -- already moved forward by a relayed join_game from this player
+            or (m.match_status = 'joined_on_chain' and m.player2_pubkey = $2)
+          )
         "#,
     )
     .bind(&game_pda)
+    .bind(&body.acceptor_pubkey)
     .fetch_optional(&state.pool)
     .await
     .map_err(|e| AppError::Internal(format!("failed to lookup challenge: {e}")))?
     .ok_or_else(|| AppError::BadRequest("challenge not found or already accepted".into()))?;
 
     let match_id: i64 = match_row.get("match_id");
+    let server_id: String = match_row.get("server_id");
     let server_ip: String = match_row.get("server_ip");
     let server_port: i32 = match_row.get("server_port");
 
+    // Without a proof only the chain vouches for the acceptor: it must already
+    // hold the join_game the acceptor's wallet signed.
+    if !proven {
+        let env = state.env(match_row.get("program_id"))?;
+        let game = fetch_and_decode_game_account(
+            env.chain.as_ref(),
+            &env.idl,
+            &env.config.program_id,
+            &game_pda,
+        )
+        .await
+        .map_err(|e| AppError::BadRequest(format!("game not found on-chain: {e:#}")))?;
+        if game.player2 == Pubkey::default() {
+            return Err(AppError::BadRequest(
+                "join_game not seen on-chain yet".into(),
+            ));
+        }
+        if game.player2.to_string() != body.acceptor_pubkey {
+            return Err(AppError::Unauthorized);
+        }
+    }
+
     // Update match: set acceptor, status → joined_on_chain
     sqlx::query(
         r#"
@@ -183,7 +399,7 @@ async fn accept_challenge(
         set acceptor_pubkey = $1,
             player2_pubkey = $1,
             match_status = 'joined_on_chain',
-            joined_onchain_at = now(),
+            joined_onchain_at = coalesce(joined_onchain_at, now()),
             updated_at = now()
         where match_id = $2
         "#,
@@ -194,21 +410,26 @@ async fn accept_challenge(
     .await
     .map_err(|e| AppError::Internal(format!("failed to update match: {e}")))?;
 
+    let (join_ticket, join_ticket_expires_at) =
+        ticket_if_proven(&state, proven, &body.acceptor_pubkey, match_id, &server_id);
     Ok(Json(AcceptChallengeResponse {
         server_ip,
         server_port,
         status: "matched".to_string(),
+        join_ticket,
+        join_ticket_expires_at,
     }))
 }
 
-/// GET /v1/challenges/{game_pda}/status — poll challenge status (for creator)
+/// GET /v1/challenges/:game_pda/status — poll challenge status (for creator)
 async fn challenge_status(
     State(state): State<AppState>,
     Path(game_pda): Path<String>,
 ) -> Result<impl IntoResponse, AppError> {
     let row = sqlx::query(
         r#"
-        select m.match_status, sp.ip as server_ip, sp.port as server_port
+        select m.match_status, sp.ip as server_ip, sp.port as server_port,
+               floor(extract(epoch from m.kicked_off_at))::bigint as kicked_off_at_epoch
         from matches m
         left join server_pool sp on sp.server_id = m.assigned_server_id
         where m.game_pda = $1
@@ -224,5 +445,6 @@ async fn challenge_status(
         status: row.get("match_status"),
         server_ip: row.get("server_ip"),
         server_port: row.get("server_port"),
+        kicked_off_at: row.get("kicked_off_at_epoch"),
     }))
 }
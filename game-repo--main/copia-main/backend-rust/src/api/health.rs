@@ -0,0 +1,146 @@
+use axum::{
+    extract::{OriginalUri, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
+
+use crate::{
+    api::internal_auth::verify_internal_hmac,
+    app_state::AppState,
+    db::chain_jobs as chain_jobs_db,
+    error::AppError,
+    metrics::metrics,
+    models::dto::{FinalizerEnvStatus, FinalizerStatusResponse, HealthCheck, HealthResponse},
+};
+
+/// Probes for orchestrators and the Prometheus scrape; mounted at the root, outside `/v1`.
+pub fn probe_router() -> Router<AppState> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(prometheus_metrics))
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/finalizer/status", get(finalizer_status))
+}
+
+/// GET /healthz — liveness: fails while a running finalizer has stopped ticking,
+/// so a restart can bring settlement back.
+async fn healthz(State(state): State<AppState>) -> impl IntoResponse {
+    let checks = state
+        .envs
+        .iter()
+        .map(|env| {
+            let stalled = env.finalizer.is_stalled(state.config.finalizer_poll_ms);
+            HealthCheck {
+                name: format!("{}: finalizer", env.config.name),
+                ok: !stalled,
+                detail: stalled.then(|| "no finalizer loop pass recently".into()),
+            }
+        })
+        .collect();
+    health_response(checks)
+}
+
+/// GET /readyz — readiness: the database and every environment's RPC answer.
+async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let mut checks = Vec::new();
+
+    let db = sqlx::query("select 1").execute(&state.pool).await;
+    if let Err(e) = &db {
+        tracing::warn!("readiness: database check failed: {e}");
+    }
+    checks.push(HealthCheck {
+        name: "database".into(),
+        ok: db.is_ok(),
+        detail: db.is_err().then(|| "unreachable".into()),
+    });
+
+    for env in state.envs.iter() {
+        // Errors may carry RPC URLs with API keys; only log them.
+        let rpc = env.chain.get_latest_blockhash().await;
+        if let Err(e) = &rpc {
+            tracing::warn!(env = %env.config.name, "readiness: rpc check failed: {e:#}");
+        }
+        checks.push(HealthCheck {
+            name: format!("{}: rpc", env.config.name),
+            ok: rpc.is_ok(),
+            detail: rpc.is_err().then(|| "unreachable".into()),
+        });
+    }
+    health_response(checks)
+}
+
+/// GET /metrics — Prometheus text format. Server pool sizes are read at scrape time.
+async fn prometheus_metrics(State(state): State<AppState>) -> Result<impl IntoResponse, AppError> {
+    let pool_sizes: Vec<(String, i64)> =
+        sqlx::query_as("select status, count(*) from server_pool group by status")
+            .fetch_all(&state.pool)
+            .await
+            .map_err(|e| AppError::Internal(format!("failed to count servers: {e}")))?;
+
+    let m = metrics();
+    m.server_pool_servers.reset();
+    for (status, servers) in pool_sizes {
+        m.server_pool_servers
+            .with_label_values(&[&status])
+            .set(servers);
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        m.render(),
+    ))
+}
+
+fn health_response(checks: Vec<HealthCheck>) -> (StatusCode, Json<HealthResponse>) {
+    let ok = checks.iter().all(|check| check.ok);
+    let status = if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(HealthResponse {
+            status: if ok { "ok" } else { "unhealthy" },
+            checks,
+        }),
+    )
+}
+
+/// GET /v1/finalizer/status — finalizer liveness and queue backlog per environment
+/// (HMAC-protected: error texts are internal).
+async fn finalizer_status(
+    State(state): State<AppState>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, AppError> {
+    verify_internal_hmac(&state, &method, &uri, &headers, b"").await?;
+
+    let mut environments = Vec::new();
+    for env in state.envs.iter() {
+        let liveness = env.finalizer.snapshot();
+        let queue =
+            chain_jobs_db::finalizer_queue_stats(&state.pool, &env.config.program_id).await?;
+        environments.push(FinalizerEnvStatus {
+            env: env.config.name.clone(),
+            program_id: env.config.program_id.clone(),
+            state: liveness.state,
+            stalled: env.finalizer.is_stalled(state.config.finalizer_poll_ms),
+            last_tick_at: liveness.last_tick_at.map(|at| at.timestamp()),
+            queue: queue.by_status.into_iter().collect(),
+            oldest_due_age_seconds: queue.oldest_due_age_seconds,
+            last_error: liveness.last_error,
+            last_error_at: liveness.last_error_at.map(|at| at.timestamp()),
+            last_job_error: queue.last_job_error,
+            last_job_error_at: queue.last_job_error_at.map(|at| at.timestamp()),
+        });
+    }
+
+    Ok(Json(FinalizerStatusResponse { environments }))
+}
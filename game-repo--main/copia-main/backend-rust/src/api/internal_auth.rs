@@ -1,70 +1,275 @@
-use axum::http::HeaderMap;
+use axum::http::{HeaderMap, Method, Uri};
 use chrono::Utc;
 use hmac::{Hmac, Mac};
 use sha2::Sha256;
+use sqlx::PgPool;
 
-use crate::{app_state::AppState, db::used_nonces, error::AppError};
+use crate::{
+    api::request_signing::{
+        canonical_v1, canonical_v2, HEADER_KEY_ID, HEADER_NONCE, HEADER_SIGNATURE,
+        HEADER_SIGNATURE_VERSION, HEADER_TIMESTAMP,
+    },
+    app_state::AppState,
+    db::{security_audit, server_credentials, used_nonces},
+    error::AppError,
+    metrics::metrics,
+};
 
-const HEADER_TIMESTAMP: &str = "X-Timestamp";
-const HEADER_NONCE: &str = "X-Nonce";
-const HEADER_SIGNATURE: &str = "X-Signature";
-const MAX_CLOCK_SKEW_SECONDS: i64 = 300;
-const MAX_NONCE_LEN: usize = 128;
+/// How far a request's `X-Timestamp` may be from now, either way.
+pub const MAX_CLOCK_SKEW_SECONDS: i64 = 300;
+pub const MAX_NONCE_LEN: usize = 128;
+/// The `X-Key-Id` of requests signed with `ADMIN_HMAC_SECRET`.
+pub const ADMIN_KEY_ID: &str = "admin";
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Whether a holder of the shared secret may still act for `server_id`: only while
+/// the server has never been issued a credential. Revoking or letting its keys
+/// expire does not hand the server back to the shared secret.
+pub async fn shared_secret_may_act_for(pool: &PgPool, server_id: &str) -> Result<bool, AppError> {
+    Ok(!server_credentials::has_any_credential(pool, server_id).await?)
+}
+
+/// Who signed an internal request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InternalCaller {
+    /// A game server, with one of its `server_credentials`.
+    Server { server_id: String, key_id: String },
+    /// A holder of the shared `INTERNAL_HMAC_SECRET`: any server, or none.
+    Shared,
+    /// An operator, with `ADMIN_HMAC_SECRET`.
+    Admin,
+}
+
+impl InternalCaller {
+    /// The authenticated server, if the caller is one.
+    pub fn server_id(&self) -> Option<&str> {
+        match self {
+            InternalCaller::Server { server_id, .. } => Some(server_id),
+            InternalCaller::Shared | InternalCaller::Admin => None,
+        }
+    }
+
+    /// Checks the caller may act as `server_id`: it is that server, or it holds the
+    /// shared secret and [`shared_secret_may_act_for`] the server.
+    /// Refusals are written to the security audit log.
+    pub async fn authorize_server(&self, pool: &PgPool, server_id: &str) -> Result<(), AppError> {
+        match self {
+            InternalCaller::Server { server_id: own, .. } if own == server_id => Ok(()),
+            InternalCaller::Server { server_id: own, .. } => {
+                let detail = format!("credential belongs to server {own}, not {server_id}");
+                self.audit(pool, "server_impersonation", Some(server_id), None, &detail)
+                    .await?;
+                Err(AppError::Forbidden(detail))
+            }
+            InternalCaller::Shared => {
+                if !shared_secret_may_act_for(pool, server_id).await? {
+                    let detail =
+                        format!("server {server_id} has its own credentials; sign with them");
+                    self.audit(pool, "shared_secret_denied", Some(server_id), None, &detail)
+                        .await?;
+                    return Err(AppError::Forbidden(detail));
+                }
+                Ok(())
+            }
+            InternalCaller::Admin => Err(AppError::Forbidden(
+                "the admin key cannot act as a server".into(),
+            )),
+        }
+    }
+
+    /// Records a denied or overridden request in `security_audit_log`, with a
+    /// warning in the log (inside the request's span) and a metric.
+    pub async fn audit(
+        &self,
+        pool: &PgPool,
+        event: &str,
+        target_server_id: Option<&str>,
+        match_id: Option<i64>,
+        detail: &str,
+    ) -> Result<(), AppError> {
+        let (caller, caller_server_id, caller_key_id) = match self {
+            InternalCaller::Server { server_id, key_id } => {
+                ("server", Some(server_id.as_str()), Some(key_id.as_str()))
+            }
+            InternalCaller::Shared => ("shared", None, None),
+            InternalCaller::Admin => ("admin", None, Some(ADMIN_KEY_ID)),
+        };
+        tracing::warn!(
+            event,
+            caller,
+            caller_server_id,
+            target_server_id,
+            match_id,
+            detail,
+            "security audit"
+        );
+        metrics()
+            .security_audit_events
+            .with_label_values(&[event])
+            .inc();
+        security_audit::insert_audit_record(
+            pool,
+            &security_audit::AuditRecord {
+                event,
+                caller,
+                caller_server_id,
+                caller_key_id,
+                target_server_id,
+                match_id,
+                detail,
+            },
+        )
+        .await
+    }
+
+    pub fn require_admin(&self) -> Result<(), AppError> {
+        match self {
+            InternalCaller::Admin => Ok(()),
+            _ => Err(AppError::Forbidden("admin key required".into())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SignatureVersion {
+    /// `timestamp.nonce.body`; accepted while `HMAC_V1_SIGNATURES_ENABLED` is on.
+    V1,
+    /// The canonical request, bound to method, path and query.
+    V2,
+}
+
+impl SignatureVersion {
+    fn as_str(self) -> &'static str {
+        match self {
+            SignatureVersion::V1 => "1",
+            SignatureVersion::V2 => "2",
+        }
+    }
+}
+
+/// Checks the HMAC headers of an internal request against `method`, `uri` (the
+/// original one, before any `nest` stripped its prefix) and the raw body, and
+/// returns who signed it.
 pub async fn verify_internal_hmac(
     state: &AppState,
+    method: &Method,
+    uri: &Uri,
     headers: &HeaderMap,
     raw_body: &[u8],
-) -> Result<(), AppError> {
-    if state.config.internal_hmac_secret.is_empty() {
-        return Err(AppError::Unauthorized);
-    }
-
-    let timestamp_raw = header_value(headers, HEADER_TIMESTAMP)?;
+) -> Result<InternalCaller, AppError> {
+    let timestamp_raw = header_value(headers, HEADER_TIMESTAMP)?.trim();
     let nonce = header_value(headers, HEADER_NONCE)?.trim();
     let signature_raw = header_value(headers, HEADER_SIGNATURE)?;
+    let key_id = headers
+        .get(HEADER_KEY_ID)
+        .map(|value| value.to_str().map(str::trim))
+        .transpose()
+        .map_err(|_| reject("missing_header"))?
+        .filter(|key_id| !key_id.is_empty());
+    let version = match headers
+        .get(HEADER_SIGNATURE_VERSION)
+        .map(|value| value.to_str().map(str::trim))
+    {
+        None | Some(Ok("1")) => SignatureVersion::V1,
+        Some(Ok("2")) => SignatureVersion::V2,
+        Some(_) => return Err(reject("bad_version")),
+    };
+    if version == SignatureVersion::V1 && !state.config.hmac_v1_signatures_enabled {
+        return Err(reject("legacy_signature"));
+    }
 
     if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
-        return Err(AppError::Unauthorized);
+        return Err(reject("bad_nonce"));
     }
 
     let timestamp = parse_timestamp(timestamp_raw)?;
     let now = Utc::now().timestamp();
     if (now - timestamp).abs() > MAX_CLOCK_SKEW_SECONDS {
-        return Err(AppError::Unauthorized);
+        return Err(reject("clock_skew"));
     }
 
     let provided_sig = parse_signature_hex(signature_raw)?;
+    let (caller, secret) = signing_key(state, key_id).await?;
 
-    let mut mac = HmacSha256::new_from_slice(state.config.internal_hmac_secret.as_bytes())
+    let message = match version {
+        SignatureVersion::V1 => canonical_v1(timestamp_raw, nonce, raw_body),
+        SignatureVersion::V2 => canonical_v2(
+            timestamp_raw,
+            nonce,
+            key_id,
+            method,
+            uri.path(),
+            uri.query(),
+            raw_body,
+        ),
+    };
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
         .map_err(|_| AppError::Internal("failed to initialize HMAC".into()))?;
-    mac.update(timestamp_raw.trim().as_bytes());
-    mac.update(b".");
-    mac.update(nonce.as_bytes());
-    mac.update(b".");
-    mac.update(raw_body);
+    mac.update(&message);
     mac.verify_slice(&provided_sig)
-        .map_err(|_| AppError::Unauthorized)?;
+        .map_err(|_| reject("bad_signature"))?;
 
     let inserted = used_nonces::insert_nonce_if_unused(&state.pool, nonce).await?;
     if !inserted {
-        return Err(AppError::Unauthorized);
+        return Err(reject("replayed_nonce"));
     }
 
-    Ok(())
+    metrics()
+        .hmac_requests
+        .with_label_values(&[version.as_str()])
+        .inc();
+    Ok(caller)
+}
+
+/// The caller a key id stands for, and the secret its requests are signed with.
+async fn signing_key(
+    state: &AppState,
+    key_id: Option<&str>,
+) -> Result<(InternalCaller, String), AppError> {
+    let config = &state.config;
+    match key_id {
+        None => {
+            if !config.shared_hmac_secret_enabled {
+                return Err(reject("shared_secret_disabled"));
+            }
+            if config.internal_hmac_secret.is_empty() {
+                return Err(reject("no_secret"));
+            }
+            Ok((InternalCaller::Shared, config.internal_hmac_secret.clone()))
+        }
+        Some(ADMIN_KEY_ID) => match config.admin_hmac_secret.as_deref() {
+            Some(secret) => Ok((InternalCaller::Admin, secret.to_string())),
+            None => Err(reject("unknown_key")),
+        },
+        Some(key_id) => {
+            let credential = server_credentials::find_credential(&state.pool, key_id)
+                .await?
+                .ok_or_else(|| reject("unknown_key"))?;
+            if credential.revoked_at.is_some() {
+                return Err(reject("revoked_key"));
+            }
+            if credential.is_expired(Utc::now()) {
+                return Err(reject("expired_key"));
+            }
+            let caller = InternalCaller::Server {
+                server_id: credential.server_id,
+                key_id: credential.key_id,
+            };
+            Ok((caller, credential.secret))
+        }
+    }
 }
 
 fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> Result<&'a str, AppError> {
-    let value = headers.get(name).ok_or(AppError::Unauthorized)?;
-    value.to_str().map_err(|_| AppError::Unauthorized)
+    let value = headers.get(name).ok_or_else(|| reject("missing_header"))?;
+    value.to_str().map_err(|_| reject("missing_header"))
 }
 
 fn parse_timestamp(raw: &str) -> Result<i64, AppError> {
     raw.trim()
         .parse::<i64>()
-        .map_err(|_| AppError::Unauthorized)
+        .map_err(|_| reject("bad_timestamp"))
 }
 
 fn parse_signature_hex(raw: &str) -> Result<Vec<u8>, AppError> {
@@ -75,8 +280,14 @@ fn parse_signature_hex(raw: &str) -> Result<Vec<u8>, AppError> {
         .unwrap_or(trimmed);
 
     if hex_str.is_empty() {
-        return Err(AppError::Unauthorized);
+        return Err(reject("bad_signature"));
     }
 
-    hex::decode(hex_str).map_err(|_| AppError::Unauthorized)
+    hex::decode(hex_str).map_err(|_| reject("bad_signature"))
+}
+
+/// Every rejection is a plain 401 to the caller; the reason only goes to metrics.
+fn reject(reason: &'static str) -> AppError {
+    metrics().hmac_rejections.with_label_values(&[reason]).inc();
+    AppError::Unauthorized
 }
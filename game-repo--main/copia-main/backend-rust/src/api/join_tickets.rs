@@ -0,0 +1,168 @@
+//! Join tickets: the public key game servers verify them with, and reissuing one
+//! to a player who reconnects.
+
+use std::str::FromStr;
+
+use axum::{
+    extract::{Path, State},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use sqlx::{PgPool, Row};
+
+use crate::{
+    api::internal_auth::{MAX_CLOCK_SKEW_SECONDS, MAX_NONCE_LEN},
+    app_state::AppState,
+    db::used_nonces,
+    error::AppError,
+    models::dto::{JoinTicketPublicKeyResponse, JoinTicketResponse, RefreshJoinTicketRequest},
+};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/join-tickets/public-key", get(public_key))
+        .route(
+            "/challenges/:game_pda/join-ticket",
+            post(refresh_join_ticket),
+        )
+}
+
+/// The message a wallet signs to get a ticket for `game_pda`, when registering,
+/// accepting or reconnecting. `nonce` is the client's, fresh for every request.
+pub fn join_ticket_message(game_pda: &str, timestamp: i64, nonce: &str) -> String {
+    format!("join-ticket:{game_pda}:{timestamp}:{nonce}")
+}
+
+/// Checks `signature` is `wallet`'s base58 signature of [`join_ticket_message`]
+/// for `game_pda`, with `timestamp` close to now, then records `nonce` in
+/// `used_nonces` so the proof is good for one request. Returns the wallet.
+pub async fn verify_wallet_proof(
+    pool: &PgPool,
+    wallet: &str,
+    game_pda: &str,
+    timestamp: i64,
+    nonce: &str,
+    signature: &str,
+) -> Result<Pubkey, AppError> {
+    let wallet = Pubkey::from_str(wallet.trim())
+        .map_err(|_| AppError::BadRequest("wallet is not a valid pubkey".into()))?;
+    if (Utc::now().timestamp() - timestamp).abs() > MAX_CLOCK_SKEW_SECONDS {
+        return Err(AppError::BadRequest(
+            "timestamp is too far from the server's clock".into(),
+        ));
+    }
+    if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+        return Err(AppError::BadRequest(format!(
+            "nonce must be 1 to {MAX_NONCE_LEN} bytes"
+        )));
+    }
+    let signature = Signature::from_str(signature.trim())
+        .map_err(|_| AppError::BadRequest("signature is not a base58 signature".into()))?;
+    let message = join_ticket_message(game_pda, timestamp, nonce);
+    if !signature.verify(wallet.as_ref(), message.as_bytes()) {
+        return Err(AppError::Unauthorized);
+    }
+    // Kept apart from the internal HMAC nonces sharing the table.
+    if !used_nonces::insert_nonce_if_unused(pool, &format!("join-ticket:{nonce}")).await? {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(wallet)
+}
+
+/// [`verify_wallet_proof`] where the proof may be left out, as by clients that
+/// predate join tickets: `false` when none of `timestamp`, `nonce` and
+/// `signature` is sent, `true` once they verify. Some but not all is a 400.
+pub async fn verify_optional_wallet_proof(
+    pool: &PgPool,
+    wallet: &str,
+    game_pda: &str,
+    timestamp: Option<i64>,
+    nonce: Option<&str>,
+    signature: Option<&str>,
+) -> Result<bool, AppError> {
+    match (timestamp, nonce, signature) {
+        (None, None, None) => Ok(false),
+        (Some(timestamp), Some(nonce), Some(signature)) => {
+            verify_wallet_proof(pool, wallet, game_pda, timestamp, nonce, signature).await?;
+            Ok(true)
+        }
+        _ => Err(AppError::BadRequest(
+            "timestamp, nonce and signature must be sent together".into(),
+        )),
+    }
+}
+
+/// GET /v1/join-tickets/public-key — for game servers to verify tickets offline.
+async fn public_key(State(state): State<AppState>) -> Json<JoinTicketPublicKeyResponse> {
+    Json(JoinTicketPublicKeyResponse {
+        algorithm: "ed25519",
+        public_key: state.join_tickets.public_key().to_string(),
+        ttl_seconds: state.join_tickets.ttl_seconds(),
+    })
+}
+
+/// POST /v1/challenges/:game_pda/join-ticket — a fresh ticket for one of the
+/// match's players, who proves it holds the wallet by signing
+/// [`join_ticket_message`]. Only while the match can still be played.
+async fn refresh_join_ticket(
+    State(state): State<AppState>,
+    Path(game_pda): Path<String>,
+    Json(body): Json<RefreshJoinTicketRequest>,
+) -> Result<Json<JoinTicketResponse>, AppError> {
+    let wallet = verify_wallet_proof(
+        &state.pool,
+        &body.wallet,
+        &game_pda,
+        body.timestamp,
+        &body.nonce,
+        &body.signature,
+    )
+    .await?;
+
+    let row = sqlx::query(
+        r#"
+        select m.match_id, m.match_status, m.player1_pubkey, m.player2_pubkey,
+               sp.server_id, sp.ip as server_ip, sp.port as server_port
+        from matches m
+        join server_pool sp on sp.server_id = m.assigned_server_id
+        where m.game_pda = $1
+        "#,
+    )
+    .bind(&game_pda)
+    .fetch_optional(&state.pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to lookup match: {e}")))?
+    .ok_or_else(|| AppError::BadRequest("match not found or has no server".into()))?;
+
+    let wallet = wallet.to_string();
+    let player1: String = row.get("player1_pubkey");
+    let player2: Option<String> = row.get("player2_pubkey");
+    if wallet != player1 && player2.as_deref() != Some(wallet.as_str()) {
+        return Err(AppError::Forbidden(
+            "wallet is not a player of this match".into(),
+        ));
+    }
+    let status: String = row.get("match_status");
+    if !matches!(
+        status.as_str(),
+        "created_on_chain" | "joined_on_chain" | "in_progress"
+    ) {
+        return Err(AppError::Conflict(format!(
+            "match is {status}; it can no longer be joined"
+        )));
+    }
+
+    let match_id: i64 = row.get("match_id");
+    let server_id: String = row.get("server_id");
+    let ticket = state.join_tickets.issue(&wallet, match_id, &server_id);
+    Ok(Json(JoinTicketResponse {
+        join_ticket: ticket.ticket,
+        expires_at: ticket.expires_at,
+        match_id,
+        server_id,
+        server_ip: row.get("server_ip"),
+        server_port: row.get("server_port"),
+    }))
+}
@@ -1,32 +1,46 @@
-use axum::{body::Bytes, extract::State, http::HeaderMap, routing::post, Json, Router};
+use axum::{
+    body::Bytes,
+    extract::{OriginalUri, Path, State},
+    http::{HeaderMap, Method},
+    routing::post,
+    Json, Router,
+};
 use chrono::{TimeZone, Utc};
 use solana_sdk::pubkey::Pubkey;
 
 use crate::{
+    api::internal_auth::{self, InternalCaller},
     app_state::AppState,
     db::chain_jobs as chain_jobs_db,
     db::matches as matches_db,
     error::AppError,
     models::{
-        dto::{FinalizeRequest, FinalizeResponse},
+        dto::{FinalizeRequest, FinalizeResponse, KickoffRequest, KickoffResponse},
         enums::{ChainJobType, MatchStatus, ResultOutcome},
     },
+    reason_policy::ReasonAction,
     solana::{
         client::fetch_and_decode_game_account, game_account::DecodedGameState,
         pda::derive_match_pdas,
     },
+    telemetry,
 };
 
 pub fn router() -> Router<AppState> {
-    Router::new().route("/finalize", post(finalize))
+    Router::new()
+        .route("/finalize", post(finalize))
+        .route("/matches/:match_id/kickoff", post(kickoff))
 }
 
 async fn finalize(
     State(state): State<AppState>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
     headers: HeaderMap,
     body: Bytes,
 ) -> Result<Json<FinalizeResponse>, AppError> {
-    crate::api::internal_auth::verify_internal_hmac(&state, &headers, body.as_ref()).await?;
+    let caller =
+        internal_auth::verify_internal_hmac(&state, &method, &uri, &headers, body.as_ref()).await?;
     let payload: FinalizeRequest = serde_json::from_slice(body.as_ref())
         .map_err(|e| AppError::BadRequest(format!("invalid JSON body: {e}")))?;
 
@@ -43,6 +57,19 @@ async fn finalize(
     if reason_code.is_empty() {
         return Err(AppError::BadRequest("reason_code is required".into()));
     }
+    // Admin overrides decide the outcome themselves: broken always refunds.
+    let reason_action = match payload.outcome {
+        ResultOutcome::Broken if caller != InternalCaller::Admin => {
+            let policy = &state.config.reason_policy;
+            if !policy.is_registered(reason_code) {
+                // Servers do not retry a rejected result, which would leave the pot
+                // locked; unknown reasons get the fallback instead.
+                tracing::warn!(reason_code, "unregistered reason_code for outcome=broken");
+            }
+            Some(policy.action(reason_code))
+        }
+        _ => None,
+    };
     let reason_detail = payload
         .reason_detail
         .as_deref()
@@ -50,18 +77,22 @@ async fn finalize(
         .filter(|s| !s.is_empty())
         .map(ToOwned::to_owned);
 
+    let env = state
+        .env_for_game(payload.program_id.as_deref(), game_pda)
+        .await?;
     let decoded = fetch_and_decode_game_account(
-        &state.config.solana_rpc_url,
-        &state.config.program_id,
+        env.chain.as_ref(),
+        &env.idl,
+        &env.config.program_id,
         game_pda,
     )
     .await
     .map_err(|e| AppError::BadRequest(format!("failed to verify on-chain game account: {e}")))?;
 
     let authority_pubkey = decoded.authority.to_string();
-    if authority_pubkey != state.config.authority_pubkey {
+    if env.config.authority(&authority_pubkey).is_none() {
         return Err(AppError::Conflict(
-            "game.authority does not match backend AUTHORITY_PUBKEY".into(),
+            "game.authority is not a configured authority".into(),
         ));
     }
 
@@ -79,7 +110,7 @@ async fn finalize(
 
     let player1_pubkey = decoded.player1.to_string();
     let expected_pdas = derive_match_pdas(
-        &state.config.program_id,
+        &env.config.program_id,
         &authority_pubkey,
         &player1_pubkey,
         match_id_i64,
@@ -180,11 +211,19 @@ async fn finalize(
         }
     };
 
+    authorize_finalize(
+        &state,
+        &caller,
+        match_id_i64,
+        payload.override_reason.as_deref(),
+    )
+    .await?;
+
     matches_db::upsert_match_from_chain(
         &state.pool,
         &matches_db::UpsertMatchFromChainParams {
             match_id: match_id_i64,
-            program_id: &state.config.program_id,
+            program_id: &env.config.program_id,
             authority_pubkey: &authority_pubkey,
             game_pda,
             vault_pda: &expected_pdas.vault_pda,
@@ -198,9 +237,70 @@ async fn finalize(
     )
     .await?;
 
-    let finalization_action = match payload.outcome {
-        ResultOutcome::Winner => ChainJobType::Settle,
-        ResultOutcome::Broken => ChainJobType::ForceRefund,
+    if let Some(mint) = decoded.token_mint {
+        // The finalizer cannot settle token games yet. Rather than enqueue a job
+        // that cannot land, the match waits for an operator with its pot intact.
+        let detail =
+            format!("game is staked in token mint {mint}; token games cannot be finalized yet");
+        if caller == InternalCaller::Admin {
+            return Err(AppError::Conflict(detail));
+        }
+        return hold_result(
+            &state,
+            &matches_db::HoldForReviewParams {
+                match_id: match_id_i64,
+                reason_code,
+                reason_detail: Some(&detail),
+                idempotency_key,
+                held_by: caller.server_id(),
+            },
+        )
+        .await;
+    }
+
+    let (finalization_action, winner_pubkey) = match (payload.outcome, reason_action) {
+        (ResultOutcome::Winner, _) => (ChainJobType::Settle, winner_pubkey),
+        (ResultOutcome::Broken, None | Some(ReasonAction::Refund)) => {
+            (ChainJobType::ForceRefund, None)
+        }
+        (ResultOutcome::Broken, Some(ReasonAction::SettleToPresent)) => {
+            let players = [Some(player1_pubkey.as_str()), player2_pubkey.as_deref()];
+            let present =
+                present_player(&players, payload.present_wallets.as_deref(), reason_code)?;
+            // Only both players paid in once the game is joined, and the server's
+            // word on who stayed counts only for a player it reported connected.
+            let kickoff = matches_db::find_match_kickoff(&state.pool, match_id_i64).await?;
+            let seen_connected =
+                |wallet: &str| kickoff.as_ref().is_some_and(|k| k.seen_connected(wallet));
+            match present {
+                Some(present) if player2_pubkey.is_some() && seen_connected(&present) => {
+                    (ChainJobType::Settle, Some(present))
+                }
+                Some(present) => {
+                    tracing::warn!(
+                        match_id = match_id_i64,
+                        reason_code,
+                        present,
+                        "present player was never reported connected; refunding"
+                    );
+                    (ChainJobType::ForceRefund, None)
+                }
+                None => (ChainJobType::ForceRefund, None),
+            }
+        }
+        (ResultOutcome::Broken, Some(ReasonAction::HoldForReview)) => {
+            return hold_result(
+                &state,
+                &matches_db::HoldForReviewParams {
+                    match_id: match_id_i64,
+                    reason_code,
+                    reason_detail: reason_detail.as_deref(),
+                    idempotency_key,
+                    held_by: caller.server_id(),
+                },
+            )
+            .await;
+        }
     };
     let persisted = chain_jobs_db::persist_result_and_enqueue(
         &state.pool,
@@ -211,6 +311,9 @@ async fn finalize(
             reason_code: reason_code.to_string(),
             reason_detail,
             idempotency_key: idempotency_key.to_string(),
+            trace_parent: telemetry::current_traceparent(),
+            reported_by: caller.server_id().map(ToOwned::to_owned),
+            resolve_review: caller == InternalCaller::Admin,
         },
     )
     .await?;
@@ -218,7 +321,218 @@ async fn finalize(
     Ok(Json(FinalizeResponse {
         match_id: match_id_i64.to_string(),
         match_status: persisted.match_status,
-        finalization_action: persisted.chain_job_type,
-        chain_job_status: persisted.chain_job_status,
+        finalization_action: Some(persisted.chain_job_type),
+        chain_job_status: Some(persisted.chain_job_status),
+    }))
+}
+
+/// Parks the result as `held_for_review`: no chain job until an operator decides.
+async fn hold_result(
+    state: &AppState,
+    params: &matches_db::HoldForReviewParams<'_>,
+) -> Result<Json<FinalizeResponse>, AppError> {
+    matches_db::hold_for_review(&state.pool, params).await?;
+    tracing::warn!(
+        match_id = params.match_id,
+        reason_code = params.reason_code,
+        reason_detail = params.reason_detail,
+        "match held for review"
+    );
+    Ok(Json(FinalizeResponse {
+        match_id: params.match_id.to_string(),
+        match_status: MatchStatus::HeldForReview,
+        finalization_action: None,
+        chain_job_status: None,
+    }))
+}
+
+/// The one player reported present, for a `settle_to_present` reason; `None`
+/// when neither is. Both present contradicts the reason.
+fn present_player(
+    players: &[Option<&str>],
+    present_wallets: Option<&[String]>,
+    reason_code: &str,
+) -> Result<Option<String>, AppError> {
+    let mut present: Vec<&str> = Vec::with_capacity(2);
+    for wallet in present_wallets.unwrap_or_default().iter().map(|w| w.trim()) {
+        if !players.contains(&Some(wallet)) {
+            return Err(AppError::BadRequest(format!(
+                "present wallet {wallet} is not a player of this match"
+            )));
+        }
+        if !present.contains(&wallet) {
+            present.push(wallet);
+        }
+    }
+    match present.as_slice() {
+        [] => Ok(None),
+        [wallet] => Ok(Some(wallet.to_string())),
+        _ => Err(AppError::BadRequest(format!(
+            "reason_code {reason_code} pays the one present player, but both are present"
+        ))),
+    }
+}
+
+/// Only the server hosting a match may report its result. The shared secret
+/// still may while that server has no credentials of its own (or the match has
+/// no assigned server); the admin key may with an `override_reason`. Refusals
+/// and overrides go to the security audit log.
+async fn authorize_finalize(
+    state: &AppState,
+    caller: &InternalCaller,
+    match_id: i64,
+    override_reason: Option<&str>,
+) -> Result<(), AppError> {
+    let pool = &state.pool;
+    let assigned = matches_db::find_assigned_server(pool, match_id).await?;
+    let override_reason = override_reason.map(str::trim).filter(|s| !s.is_empty());
+
+    if let Some(reason) = override_reason {
+        if *caller != InternalCaller::Admin {
+            let detail = format!("override_reason without the admin key: {reason}");
+            caller
+                .audit(
+                    pool,
+                    "finalize_override_denied",
+                    assigned.as_deref(),
+                    Some(match_id),
+                    &detail,
+                )
+                .await?;
+            return Err(AppError::Forbidden(
+                "override_reason requires the admin key".into(),
+            ));
+        }
+    }
+
+    match caller {
+        InternalCaller::Admin => {
+            let Some(reason) = override_reason else {
+                return Err(AppError::Forbidden(
+                    "finalizing with the admin key requires an override_reason".into(),
+                ));
+            };
+            caller
+                .audit(
+                    pool,
+                    "finalize_admin_override",
+                    assigned.as_deref(),
+                    Some(match_id),
+                    reason,
+                )
+                .await
+        }
+        InternalCaller::Server { server_id, .. } => {
+            if assigned.as_deref() == Some(server_id.as_str()) {
+                return Ok(());
+            }
+            let detail = match &assigned {
+                Some(assigned) => {
+                    format!("match {match_id} is hosted by {assigned}, not {server_id}")
+                }
+                None => format!("match {match_id} has no assigned server"),
+            };
+            caller
+                .audit(
+                    pool,
+                    "finalize_not_assigned_server",
+                    assigned.as_deref(),
+                    Some(match_id),
+                    &detail,
+                )
+                .await?;
+            Err(AppError::Forbidden(detail))
+        }
+        InternalCaller::Shared => {
+            let Some(assigned) = assigned else {
+                return Ok(());
+            };
+            if internal_auth::shared_secret_may_act_for(pool, &assigned).await? {
+                return Ok(());
+            }
+            let detail =
+                format!("match {match_id} is hosted by {assigned}, which has its own credentials");
+            caller
+                .audit(
+                    pool,
+                    "finalize_shared_secret_denied",
+                    Some(&assigned),
+                    Some(match_id),
+                    &detail,
+                )
+                .await?;
+            Err(AppError::Forbidden(detail))
+        }
+    }
+}
+
+/// POST /v1/matches/:match_id/kickoff — the assigned server reports which
+/// players have connected. Once both have, in this report or earlier ones, the
+/// match moves from `joined_on_chain` to `in_progress`. Repeating the report
+/// returns the first kickoff.
+async fn kickoff(
+    State(state): State<AppState>,
+    Path(match_id): Path<i64>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<KickoffResponse>, AppError> {
+    let caller =
+        internal_auth::verify_internal_hmac(&state, &method, &uri, &headers, body.as_ref()).await?;
+    let payload: KickoffRequest = serde_json::from_slice(body.as_ref())
+        .map_err(|e| AppError::BadRequest(format!("invalid JSON body: {e}")))?;
+
+    let current = matches_db::find_match_kickoff(&state.pool, match_id)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("match not found".into()))?;
+    let assigned = current
+        .assigned_server_id
+        .as_deref()
+        .ok_or_else(|| AppError::Conflict("match has no assigned server".into()))?;
+    caller.authorize_server(&state.pool, assigned).await?;
+
+    let players = [
+        Some(current.player1_pubkey.as_str()),
+        current.player2_pubkey.as_deref(),
+    ];
+    for wallet in payload.present_wallets.iter().map(|w| w.trim()) {
+        if !players.contains(&Some(wallet)) {
+            return Err(AppError::BadRequest(format!(
+                "{wallet} is not a player of this match"
+            )));
+        }
+    }
+    let reported = |player: Option<&str>| {
+        player.is_some_and(|p| payload.present_wallets.iter().any(|w| w.trim() == p))
+    };
+    let (player1, player2) = (reported(players[0]), reported(players[1]));
+    if !player1 && !player2 {
+        return Err(AppError::BadRequest(
+            "present_wallets must name a player".into(),
+        ));
+    }
+    if !matches_db::record_player_connections(&state.pool, match_id, player1, player2).await? {
+        return Err(AppError::Conflict(format!(
+            "match is {}; players can no longer connect",
+            current.match_status
+        )));
+    }
+    if matches_db::mark_in_progress(&state.pool, match_id)
+        .await?
+        .is_some()
+    {
+        tracing::info!(match_id, "match kicked off");
+    }
+
+    // Kicked off by this report, an earlier one or a concurrent one, or not yet.
+    let current = matches_db::find_match_kickoff(&state.pool, match_id)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("match not found".into()))?;
+    Ok(Json(KickoffResponse {
+        match_id: match_id.to_string(),
+        match_status: chain_jobs_db::parse_match_status(&current.match_status)?,
+        kicked_off_at: current.kicked_off_at.map(|at| at.timestamp()),
+        present_wallets: current.connected_wallets(),
     }))
 }
@@ -1,7 +1,12 @@
+pub mod admin;
+pub mod challenges;
+pub mod health;
 pub mod internal_auth;
+pub mod join_tickets;
 pub mod matches;
-pub mod challenges;
+pub mod request_signing;
 pub mod servers;
+pub mod transactions;
 
 use axum::Router;
 
@@ -11,5 +16,9 @@ pub fn router() -> Router<AppState> {
     Router::new()
         .merge(matches::router())
         .merge(challenges::router())
+        .merge(join_tickets::router())
         .merge(servers::router())
+        .merge(transactions::router())
+        .merge(health::router())
+        .merge(admin::router())
 }
@@ -1,7 +1,7 @@
 use axum::{
     body::Bytes,
-    extract::{Path, State},
-    http::{HeaderMap, StatusCode},
+    extract::{OriginalUri, Path, State},
+    http::{HeaderMap, Method, StatusCode},
     response::IntoResponse,
     routing::{post, put},
     Json, Router,
@@ -17,16 +17,18 @@ use crate::{
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/servers/register", post(register_server))
-        .route("/servers/{server_id}/heartbeat", put(heartbeat))
+        .route("/servers/:server_id/heartbeat", put(heartbeat))
 }
 
 /// POST /v1/servers/register — server instance registers in pool (HMAC-protected)
 async fn register_server(
     State(state): State<AppState>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
     headers: HeaderMap,
     body: Bytes,
 ) -> Result<impl IntoResponse, AppError> {
-    verify_internal_hmac(&state, &headers, body.as_ref()).await?;
+    let caller = verify_internal_hmac(&state, &method, &uri, &headers, body.as_ref()).await?;
 
     let req: RegisterServerRequest = serde_json::from_slice(body.as_ref())
         .map_err(|e| AppError::BadRequest(format!("invalid JSON: {e}")))?;
@@ -36,6 +38,7 @@ async fn register_server(
             "server_id, ip, and port are required".into(),
         ));
     }
+    caller.authorize_server(&state.pool, &req.server_id).await?;
 
     sqlx::query(
         r#"
@@ -70,10 +73,13 @@ async fn register_server(
 async fn heartbeat(
     State(state): State<AppState>,
     Path(server_id): Path<String>,
+    method: Method,
+    OriginalUri(uri): OriginalUri,
     headers: HeaderMap,
     body: Bytes,
 ) -> Result<impl IntoResponse, AppError> {
-    verify_internal_hmac(&state, &headers, body.as_ref()).await?;
+    let caller = verify_internal_hmac(&state, &method, &uri, &headers, body.as_ref()).await?;
+    caller.authorize_server(&state.pool, &server_id).await?;
 
     let req: HeartbeatRequest = serde_json::from_slice(body.as_ref())
         .map_err(|e| AppError::BadRequest(format!("invalid JSON: {e}")))?;
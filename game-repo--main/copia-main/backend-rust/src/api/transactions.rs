@@ -0,0 +1,487 @@
+//! Unsigned `create_game`/`join_game` transactions for client wallets to sign, so
+//! instruction layout lives only in the backend, and a relay that submits the
+//! signed transaction and records its signature on the match.
+//!
+//! With sponsorship enabled, a player may ask for the backend sponsor as fee
+//! payer; the relay then co-signs, but only for a bare game instruction and within
+//! the sponsor quotas.
+
+use std::str::FromStr;
+
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use solana_sdk::{
+    hash::Hash,
+    instruction::{CompiledInstruction, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signature::Signer,
+    transaction::Transaction,
+};
+use solana_sdk_ids::compute_budget;
+
+use crate::{
+    app_state::{AppState, ProgramEnv},
+    config::AuthorityStatus,
+    db::{
+        matches as matches_db,
+        sponsorships::{self, NewSponsorship, SponsorshipGrant},
+    },
+    error::AppError,
+    models::dto::{
+        CreateGameTxRequest, JoinGameTxRequest, RelayTxRequest, RelayTxResponse, UnsignedTxResponse,
+    },
+    solana::{
+        client::fetch_and_decode_game_account,
+        game_account::DecodedGameState,
+        idl::DecodedInstruction,
+        instructions::{create_game_ix, join_game_ix},
+        pda::vault_address,
+    },
+    worker::relay_watcher::RELAYED_TX_TIMEOUT_SECONDS,
+};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/transactions/create-game", post(create_game_tx))
+        .route("/transactions/join-game", post(join_game_tx))
+        .route("/transactions/relay", post(relay_tx))
+}
+
+/// POST /v1/transactions/create-game — `create_game` for a reserved match, signed
+/// by the creator and paid by them or the sponsor.
+async fn create_game_tx(
+    State(state): State<AppState>,
+    Json(body): Json<CreateGameTxRequest>,
+) -> Result<Json<UnsignedTxResponse>, AppError> {
+    let reservation = matches_db::find_open_reservation(&state.pool, body.match_id)
+        .await?
+        .ok_or_else(|| {
+            AppError::BadRequest("match_id is not an open reservation; reserve a new one".into())
+        })?;
+
+    let env = state.env(&reservation.program_id)?;
+    let authority = active_authority(env, &reservation.authority_pubkey)?;
+    let creator = Pubkey::from_str(&reservation.player1_pubkey)
+        .map_err(|e| AppError::Internal(format!("invalid player1_pubkey in DB: {e}")))?;
+
+    let ix = create_game_ix(
+        &env.idl,
+        env.program_id,
+        creator,
+        authority,
+        reservation.entry_lamports as u64,
+        reservation.match_id as u64,
+    )?;
+    let game_pda = Pubkey::from_str(&reservation.game_pda)
+        .map_err(|e| AppError::Internal(format!("invalid game_pda in DB: {e}")))?;
+
+    let fee_payer = fee_payer(&state, creator, body.sponsored)?;
+    unsigned_tx_response(env, ix, fee_payer, reservation.match_id, game_pda).await
+}
+
+/// POST /v1/transactions/join-game — `join_game` for an open on-chain game, signed
+/// by the joining player and paid by them or the sponsor.
+async fn join_game_tx(
+    State(state): State<AppState>,
+    Json(body): Json<JoinGameTxRequest>,
+) -> Result<Json<UnsignedTxResponse>, AppError> {
+    let player = Pubkey::from_str(body.player_pubkey.trim())
+        .map_err(|_| AppError::BadRequest("player_pubkey is not a valid pubkey".into()))?;
+    let game_pda = Pubkey::from_str(body.game_pda.trim())
+        .map_err(|_| AppError::BadRequest("game_pda is not a valid pubkey".into()))?;
+
+    let env = state
+        .env_for_game(body.program_id.as_deref(), &game_pda.to_string())
+        .await?;
+    let game = fetch_and_decode_game_account(
+        env.chain.as_ref(),
+        &env.idl,
+        &env.config.program_id,
+        &game_pda.to_string(),
+    )
+    .await
+    .map_err(|e| AppError::BadRequest(format!("failed to load on-chain game: {e:#}")))?;
+    if game.state != DecodedGameState::Created {
+        return Err(AppError::Conflict(format!(
+            "game is not open to join (state {:?})",
+            game.state
+        )));
+    }
+    if game.player1 == player {
+        return Err(AppError::BadRequest(
+            "creator cannot join their own game".into(),
+        ));
+    }
+    active_authority(env, &game.authority.to_string())?;
+
+    let ix = join_game_ix(&env.idl, env.program_id, player, game_pda)?;
+    let fee_payer = fee_payer(&state, player, body.sponsored)?;
+    unsigned_tx_response(env, ix, fee_payer, game.match_id as i64, game_pda).await
+}
+
+fn fee_payer(state: &AppState, player: Pubkey, sponsored: bool) -> Result<Pubkey, AppError> {
+    if !sponsored {
+        return Ok(player);
+    }
+    state
+        .sponsor
+        .as_ref()
+        .map(|sponsor| sponsor.pubkey())
+        .ok_or_else(|| AppError::BadRequest("transaction sponsorship is not enabled".into()))
+}
+
+async fn unsigned_tx_response(
+    env: &ProgramEnv,
+    ix: Instruction,
+    fee_payer: Pubkey,
+    match_id: i64,
+    game_pda: Pubkey,
+) -> Result<Json<UnsignedTxResponse>, AppError> {
+    let blockhash: Hash = env
+        .chain
+        .get_latest_blockhash()
+        .await
+        .map_err(|e| AppError::Internal(format!("failed to fetch latest blockhash: {e:#}")))?;
+
+    let message = Message::new_with_blockhash(&[ix], Some(&fee_payer), &blockhash);
+    let tx = Transaction::new_unsigned(message);
+    let bytes = bincode::serialize(&tx)
+        .map_err(|e| AppError::Internal(format!("failed to serialize transaction: {e}")))?;
+
+    let (vault_pda, _) = vault_address(&env.program_id, &game_pda);
+    Ok(Json(UnsignedTxResponse {
+        transaction: BASE64.encode(bytes),
+        recent_blockhash: blockhash.to_string(),
+        fee_payer: fee_payer.to_string(),
+        match_id,
+        game_pda: game_pda.to_string(),
+        vault_pda: vault_pda.to_string(),
+    }))
+}
+
+/// POST /v1/transactions/relay — submit a wallet-signed `create_game` or
+/// `join_game` and record its signature; the relay watcher follows it from there.
+async fn relay_tx(
+    State(state): State<AppState>,
+    Json(body): Json<RelayTxRequest>,
+) -> Result<(StatusCode, Json<RelayTxResponse>), AppError> {
+    let bytes = BASE64
+        .decode(body.transaction.trim())
+        .map_err(|_| AppError::BadRequest("transaction is not valid base64".into()))?;
+    let mut tx: Transaction = bincode::deserialize(&bytes)
+        .map_err(|_| AppError::BadRequest("transaction is not a legacy transaction".into()))?;
+
+    let sponsor = state
+        .sponsor
+        .clone()
+        .filter(|sponsor| tx.message.account_keys.first() == Some(&sponsor.pubkey()));
+    if let Some(sponsor) = &sponsor {
+        check_sponsorable(&tx, &sponsor.pubkey())?;
+        let blockhash = tx.message.recent_blockhash;
+        tx.try_partial_sign(&[sponsor.as_ref()], blockhash)
+            .map_err(|e| AppError::Internal(format!("sponsor failed to sign: {e}")))?;
+    }
+    tx.verify()
+        .map_err(|e| AppError::BadRequest(format!("transaction is not fully signed: {e}")))?;
+    let signature = tx.signatures[0].to_string();
+
+    let env = relay_env(&state, &tx)?;
+    let compiled = program_instruction(&tx, &env.program_id)?;
+    let metas = instruction_metas(&tx, compiled)?;
+    let decoded = env
+        .idl
+        .decode_instruction(&compiled.data, &metas)
+        .map_err(|e| AppError::BadRequest(format!("unrecognized program instruction: {e:#}")))?;
+
+    let (expected, match_id, game_pda) = match decoded.name.as_str() {
+        "create_game" => expected_create(&state, env, &decoded).await?,
+        "join_game" => expected_join(&state, env, &decoded, &signature).await?,
+        other => return Err(AppError::BadRequest(format!("{other} cannot be relayed"))),
+    };
+    let expected_metas: Vec<(Pubkey, bool)> = expected
+        .accounts
+        .iter()
+        .map(|m| (m.pubkey, m.is_signer))
+        .collect();
+    if expected_metas != metas || expected.data != compiled.data {
+        return Err(AppError::BadRequest(format!(
+            "{} accounts do not match the expected accounts",
+            decoded.name
+        )));
+    }
+
+    if sponsor.is_some() {
+        let player = if decoded.name == "create_game" {
+            "player1"
+        } else {
+            "player2"
+        };
+        let wallet = named_account(&decoded, player)?.to_string();
+        let grant = sponsorships::try_record_sponsorship(
+            &state.pool,
+            &NewSponsorship {
+                signature: &signature,
+                wallet: &wallet,
+                match_id,
+                instruction: &decoded.name,
+                wallet_daily_limit: state.config.sponsor_wallet_daily_limit,
+                daily_limit: state.config.sponsor_daily_limit,
+            },
+        )
+        .await?;
+        match grant {
+            SponsorshipGrant::Granted => {}
+            SponsorshipGrant::WalletQuotaExceeded => {
+                return Err(AppError::TooManyRequests(
+                    "sponsored transaction quota for this wallet is used up; pay the fee yourself"
+                        .into(),
+                ))
+            }
+            SponsorshipGrant::DailyQuotaExceeded => {
+                tracing::warn!("daily sponsored transaction quota reached");
+                return Err(AppError::TooManyRequests(
+                    "sponsored transactions are unavailable right now; pay the fee yourself".into(),
+                ));
+            }
+        }
+    }
+
+    if let Err(e) = env.chain.send_transaction(&tx).await {
+        if sponsor.is_some() {
+            sponsorships::release_sponsorship(&state.pool, &signature).await?;
+        }
+        return Err(AppError::BadRequest(format!(
+            "transaction was rejected: {e:#}"
+        )));
+    }
+
+    let recorded = if decoded.name == "create_game" {
+        matches_db::record_create_tx(
+            &state.pool,
+            match_id,
+            &signature,
+            RELAYED_TX_TIMEOUT_SECONDS,
+        )
+        .await?
+    } else {
+        matches_db::record_join_tx(&state.pool, match_id, &signature).await?
+    };
+    if !recorded {
+        tracing::warn!(
+            match_id,
+            %signature,
+            "relayed transaction sent but the match moved on before it was recorded"
+        );
+    }
+    tracing::info!(match_id, %signature, instruction = %decoded.name, "relayed transaction");
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(RelayTxResponse {
+            signature,
+            instruction: decoded.name,
+            match_id,
+            game_pda,
+            sponsored: sponsor.is_some(),
+        }),
+    ))
+}
+
+/// A sponsored transaction may only carry the game instruction (no compute
+/// budget, so no priority fee), must not use the sponsor as an instruction
+/// account, and must already be signed by everyone but the sponsor.
+fn check_sponsorable(tx: &Transaction, sponsor: &Pubkey) -> Result<(), AppError> {
+    if tx.message.instructions.len() != 1 {
+        return Err(AppError::BadRequest(
+            "sponsored transactions must contain only the game instruction".into(),
+        ));
+    }
+    let keys = &tx.message.account_keys;
+    let uses_sponsor = tx.message.instructions.iter().any(|ix| {
+        ix.accounts
+            .iter()
+            .any(|&i| keys.get(i as usize) == Some(sponsor))
+    });
+    if uses_sponsor {
+        return Err(AppError::BadRequest(
+            "sponsor may only be the fee payer".into(),
+        ));
+    }
+    if !tx.verify_with_results().iter().skip(1).all(|ok| *ok) {
+        return Err(AppError::BadRequest(
+            "transaction is missing a player signature".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// The environment whose program `tx` calls.
+fn relay_env<'a>(state: &'a AppState, tx: &Transaction) -> Result<&'a ProgramEnv, AppError> {
+    let keys = &tx.message.account_keys;
+    let called = |env: &ProgramEnv| {
+        tx.message
+            .instructions
+            .iter()
+            .any(|ix| keys.get(ix.program_id_index as usize) == Some(&env.program_id))
+    };
+    state
+        .envs
+        .iter()
+        .find(|env| called(env))
+        .ok_or_else(|| AppError::BadRequest("transaction calls no game program".into()))
+}
+
+/// The single game program instruction in `tx`. Compute budget instructions
+/// added by wallets are allowed alongside it.
+fn program_instruction<'a>(
+    tx: &'a Transaction,
+    program_id: &Pubkey,
+) -> Result<&'a CompiledInstruction, AppError> {
+    let mut found = None;
+    for ix in &tx.message.instructions {
+        let program = tx
+            .message
+            .account_keys
+            .get(ix.program_id_index as usize)
+            .ok_or_else(|| AppError::BadRequest("malformed transaction".into()))?;
+        if program == program_id {
+            if found.replace(ix).is_some() {
+                return Err(AppError::BadRequest(
+                    "transaction must contain exactly one game instruction".into(),
+                ));
+            }
+        } else if *program != compute_budget::id() {
+            return Err(AppError::BadRequest(format!(
+                "transaction calls unexpected program {program}"
+            )));
+        }
+    }
+    found.ok_or_else(|| {
+        AppError::BadRequest(format!("transaction does not call program {program_id}"))
+    })
+}
+
+/// `(key, is_signer)` for each account of `ix`, in instruction order.
+fn instruction_metas(
+    tx: &Transaction,
+    ix: &CompiledInstruction,
+) -> Result<Vec<(Pubkey, bool)>, AppError> {
+    ix.accounts
+        .iter()
+        .map(|&i| {
+            let i = i as usize;
+            tx.message
+                .account_keys
+                .get(i)
+                .map(|k| (*k, tx.message.is_signer(i)))
+                .ok_or_else(|| AppError::BadRequest("malformed transaction".into()))
+        })
+        .collect()
+}
+
+/// The `create_game` the backend would have built for the reservation named by
+/// the relayed instruction.
+async fn expected_create(
+    state: &AppState,
+    env: &ProgramEnv,
+    decoded: &DecodedInstruction,
+) -> Result<(Instruction, i64, String), AppError> {
+    let match_id = decoded.args.field("match_id")?.as_u64()? as i64;
+    let entry_amount = decoded.args.field("entry_amount")?.as_u64()?;
+    let creator = named_account(decoded, "player1")?;
+
+    let reservation = matches_db::find_open_reservation(&state.pool, match_id)
+        .await?
+        .ok_or_else(|| {
+            AppError::BadRequest("match_id is not an open reservation; reserve a new one".into())
+        })?;
+    if reservation.player1_pubkey != creator.to_string() {
+        return Err(AppError::Conflict(
+            "match_id is reserved for a different creator".into(),
+        ));
+    }
+    if reservation.program_id != env.config.program_id {
+        return Err(AppError::Conflict(
+            "match_id is reserved on a different program".into(),
+        ));
+    }
+    if reservation.entry_lamports as u64 != entry_amount {
+        return Err(AppError::BadRequest(
+            "entry_amount differs from the reservation".into(),
+        ));
+    }
+
+    let authority = active_authority(env, &reservation.authority_pubkey)?;
+    let ix = create_game_ix(
+        &env.idl,
+        env.program_id,
+        creator,
+        authority,
+        entry_amount,
+        match_id as u64,
+    )?;
+    Ok((ix, match_id, reservation.game_pda))
+}
+
+/// The `join_game` for the open match the relayed instruction targets.
+async fn expected_join(
+    state: &AppState,
+    env: &ProgramEnv,
+    decoded: &DecodedInstruction,
+    signature: &str,
+) -> Result<(Instruction, i64, String), AppError> {
+    let player = named_account(decoded, "player2")?;
+    let game_pda = named_account(decoded, "game")?;
+
+    let open = matches_db::find_joinable_match(&state.pool, &game_pda.to_string())
+        .await?
+        .filter(|open| open.program_id == env.config.program_id)
+        .ok_or_else(|| AppError::BadRequest("game is not open to join".into()))?;
+    if open.player1_pubkey == player.to_string() {
+        return Err(AppError::BadRequest(
+            "creator cannot join their own game".into(),
+        ));
+    }
+    active_authority(env, &open.authority_pubkey)?;
+    if open
+        .join_tx_sig
+        .as_deref()
+        .is_some_and(|pending| pending != signature)
+    {
+        return Err(AppError::Conflict(
+            "another join transaction is pending for this game".into(),
+        ));
+    }
+
+    let ix = join_game_ix(&env.idl, env.program_id, player, game_pda)?;
+    Ok((ix, open.match_id, game_pda.to_string()))
+}
+
+fn named_account(decoded: &DecodedInstruction, name: &str) -> Result<Pubkey, AppError> {
+    decoded
+        .accounts
+        .iter()
+        .find(|(account, _, _)| account == name)
+        .map(|(_, key, _)| *key)
+        .ok_or_else(|| AppError::BadRequest(format!("{}: missing {name} account", decoded.name)))
+}
+
+/// Games under a settle-only (or unknown) authority take no new players.
+fn active_authority(env: &ProgramEnv, authority: &str) -> Result<Pubkey, AppError> {
+    match env.config.authority(authority) {
+        Some(config) if config.status == AuthorityStatus::Active => {}
+        Some(_) => {
+            return Err(AppError::Conflict(format!(
+                "authority {authority} is settle-only; its games take no new players"
+            )))
+        }
+        None => {
+            return Err(AppError::Conflict(format!(
+                "authority {authority} is not configured on this backend"
+            )))
+        }
+    }
+    Pubkey::from_str(authority)
+        .map_err(|e| AppError::Internal(format!("invalid authority pubkey {authority}: {e}")))
+}
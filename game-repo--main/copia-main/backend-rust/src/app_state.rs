@@ -1,15 +1,178 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+};
 use sqlx::PgPool;
 
-use crate::config::Config;
+use crate::{
+    config::{Config, ProgramEnvConfig},
+    db::matches as matches_db,
+    error::AppError,
+    join_ticket::JoinTicketIssuer,
+    shutdown::Shutdown,
+    solana::{
+        gateway::ChainGateway,
+        idl::Idl,
+        rpc_pool::{RpcPool, RpcPoolOptions},
+    },
+    worker::liveness::FinalizerLiveness,
+};
 
 #[derive(Clone)]
 pub struct AppState {
     pub config: Config,
     pub pool: PgPool,
+    /// One per configured program, in config order; the first is the default.
+    pub envs: Arc<Vec<ProgramEnv>>,
+    /// Pays fees for sponsored player transactions; `None` when sponsorship is off.
+    pub sponsor: Option<Arc<Keypair>>,
+    /// Triggered on SIGTERM; workers stop at their next safe point.
+    pub shutdown: Shutdown,
+    pub join_tickets: Arc<JoinTicketIssuer>,
+}
+
+/// A game program on its cluster: where its games live and how to reach them.
+#[derive(Clone)]
+pub struct ProgramEnv {
+    pub config: ProgramEnvConfig,
+    pub program_id: Pubkey,
+    pub chain: Arc<dyn ChainGateway>,
+    pub idl: Arc<Idl>,
+    /// Set when `chain` is the live RPC pool; drives the endpoint health checker.
+    pub rpc: Option<Arc<RpcPool>>,
+    /// Updated by this environment's finalizer task.
+    pub finalizer: Arc<FinalizerLiveness>,
 }
 
 impl AppState {
-    pub fn new(config: Config, pool: PgPool) -> Self {
-        Self { config, pool }
+    pub fn new(config: Config, pool: PgPool) -> Result<Self> {
+        let envs = config
+            .environments
+            .iter()
+            .map(|env| {
+                let rpc = Arc::new(RpcPool::new(
+                    &env.solana_rpc_urls,
+                    RpcPoolOptions {
+                        call_timeout: Duration::from_millis(config.rpc_timeout_ms),
+                        slow_call_threshold: Duration::from_millis(config.rpc_slow_call_ms),
+                    },
+                )?);
+                ProgramEnv::new(env, rpc.clone(), Some(rpc))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Self::build(config, pool, envs, false)
+    }
+
+    /// Runs the API and workers against arbitrary chains, e.g. in-memory fakes.
+    pub fn with_chains(
+        config: Config,
+        pool: PgPool,
+        mut chain_for: impl FnMut(&ProgramEnvConfig) -> Arc<dyn ChainGateway>,
+    ) -> Result<Self> {
+        let envs = config
+            .environments
+            .iter()
+            .map(|env| ProgramEnv::new(env, chain_for(env), None))
+            .collect::<Result<Vec<_>>>()?;
+        Self::build(config, pool, envs, true)
+    }
+
+    /// `mock_chain` relaxes settings that only matter against a real cluster.
+    fn build(
+        config: Config,
+        pool: PgPool,
+        envs: Vec<ProgramEnv>,
+        mock_chain: bool,
+    ) -> Result<Self> {
+        if envs.is_empty() {
+            bail!("at least one program environment is required");
+        }
+        let sponsor = load_sponsor(&config)?;
+        let join_tickets = JoinTicketIssuer::from_config(&config, mock_chain)?;
+        Ok(Self {
+            config,
+            pool,
+            envs: Arc::new(envs),
+            sponsor,
+            shutdown: Shutdown::default(),
+            join_tickets,
+        })
+    }
+
+    /// The environment configured from `PROGRAM_ID`.
+    pub fn default_env(&self) -> &ProgramEnv {
+        &self.envs[0]
+    }
+
+    /// The environment of `program_id`, as stored on a match row.
+    pub fn env(&self, program_id: &str) -> Result<&ProgramEnv, AppError> {
+        self.envs
+            .iter()
+            .find(|env| env.config.program_id == program_id)
+            .ok_or_else(|| {
+                AppError::BadRequest(format!(
+                    "program {program_id} is not served by this backend"
+                ))
+            })
+    }
+
+    /// The environment a request names, or the default one.
+    pub fn requested_env(&self, program_id: Option<&str>) -> Result<&ProgramEnv, AppError> {
+        match program_id.map(str::trim).filter(|p| !p.is_empty()) {
+            Some(program_id) => self.env(program_id),
+            None => Ok(self.default_env()),
+        }
+    }
+
+    /// The environment of a game: the program named by the request, else the one
+    /// its match row was recorded under, else the default.
+    pub async fn env_for_game(
+        &self,
+        program_id: Option<&str>,
+        game_pda: &str,
+    ) -> Result<&ProgramEnv, AppError> {
+        if program_id.is_some_and(|p| !p.trim().is_empty()) {
+            return self.requested_env(program_id);
+        }
+        match matches_db::find_program_id_by_game_pda(&self.pool, game_pda).await? {
+            Some(program_id) => self.env(&program_id),
+            None => Ok(self.default_env()),
+        }
+    }
+}
+
+impl ProgramEnv {
+    fn new(
+        config: &ProgramEnvConfig,
+        chain: Arc<dyn ChainGateway>,
+        rpc: Option<Arc<RpcPool>>,
+    ) -> Result<Self> {
+        let program_id = Pubkey::from_str(&config.program_id)
+            .with_context(|| format!("invalid program id for environment {}", config.name))?;
+        let idl = Arc::new(Idl::load(config.program_idl_path.as_deref())?);
+        Ok(Self {
+            config: config.clone(),
+            program_id,
+            chain,
+            idl,
+            rpc,
+            finalizer: Arc::default(),
+        })
+    }
+}
+
+/// The sponsor only ever pays fees, so it must not be a settlement authority.
+fn load_sponsor(config: &Config) -> Result<Option<Arc<Keypair>>> {
+    let Some(path) = config.sponsor_keypair_path.as_deref() else {
+        return Ok(None);
+    };
+    let sponsor = read_keypair_file(path)
+        .map_err(|e| anyhow!("failed to read SPONSOR_KEYPAIR_PATH {path}: {e}"))?;
+    if config.authority(&sponsor.pubkey().to_string()).is_some() {
+        bail!("SPONSOR_KEYPAIR_PATH must not be an authority keypair");
     }
+    Ok(Some(Arc::new(sponsor)))
 }
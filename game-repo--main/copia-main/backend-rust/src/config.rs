@@ -1,15 +1,96 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::{reason_policy::ReasonPolicy, solana::signer::SignerConfig};
+
+/// Whether an authority still takes new games.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorityStatus {
+    /// New games may be created and joined under it.
+    Active,
+    /// Retired: its open games are still settled or refunded, nothing new starts.
+    SettleOnly,
+}
+
+#[derive(Debug, Clone)]
+pub struct AuthorityConfig {
+    pub pubkey: String,
+    pub signer: SignerConfig,
+    pub status: AuthorityStatus,
+}
+
+/// One game program on one cluster, with its own RPC endpoints and authorities.
+#[derive(Debug, Clone)]
+pub struct ProgramEnvConfig {
+    /// Short label for logs, e.g. `devnet` or `mainnet-v2`.
+    pub name: String,
+    pub program_id: String,
+    /// Anchor IDL of the game program; the bundled copy is used when unset.
+    pub program_idl_path: Option<String>,
+    pub solana_rpc_urls: Vec<String>,
+    /// Authority new games are created under; always an active entry of `authorities`.
+    pub authority_pubkey: String,
+    /// Every authority the backend finalizes for, each with its own signer.
+    pub authorities: Vec<AuthorityConfig>,
+}
+
+impl ProgramEnvConfig {
+    /// The configured authority with this pubkey, active or settle-only.
+    pub fn authority(&self, pubkey: &str) -> Option<&AuthorityConfig> {
+        self.authorities.iter().find(|a| a.pubkey == pubkey)
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub app_bind_addr: String,
     pub database_url: String,
-    pub solana_rpc_url: String,
-    pub program_id: String,
-    pub authority_pubkey: String,
-    pub authority_keypair_path: String,
+    /// The default environment first, then those from `PROGRAM_ENVIRONMENTS_PATH`.
+    pub environments: Vec<ProgramEnvConfig>,
+    pub rpc_timeout_ms: u64,
+    pub rpc_slow_call_ms: u64,
+    pub rpc_health_check_ms: u64,
+    /// Pays finalizer transaction fees so the authority only signs; the
+    /// authority pays when unset.
+    pub fee_payer_keypair_path: Option<String>,
+    /// The finalizer does not start while the fee payer holds less than this.
+    pub fee_payer_min_balance_lamports: u64,
+    /// Minimum authority balance when it is not also the fee payer.
+    pub authority_min_balance_lamports: u64,
+    pub balance_check_ms: u64,
+    /// Shared by every game server; requests without `X-Key-Id` are signed with it.
     pub internal_hmac_secret: String,
+    /// Off once every server signs with its own `server_credentials`.
+    pub shared_hmac_secret_enabled: bool,
+    /// Accept `timestamp.nonce.body` signatures; off once every caller signs
+    /// canonical requests.
+    pub hmac_v1_signatures_enabled: bool,
+    /// Signs admin requests, sent with `X-Key-Id: admin`; admin endpoints are off when unset.
+    pub admin_hmac_secret: Option<String>,
     pub finalizer_poll_ms: u64,
+    /// How long a reserved `match_id` waits for its `create_game` transaction.
+    pub match_reservation_ttl_seconds: i64,
+    pub create_watch_poll_ms: u64,
+    /// Interval of the worker deleting expired HMAC nonces.
+    pub nonce_prune_ms: u64,
+    /// Fee payer for sponsored player transactions; sponsorship is off when unset.
+    pub sponsor_keypair_path: Option<String>,
+    /// Sponsored transactions allowed per player wallet in a rolling 24 hours.
+    pub sponsor_wallet_daily_limit: i64,
+    /// Sponsored transactions allowed across all wallets in a rolling 24 hours.
+    pub sponsor_daily_limit: i64,
+    /// Games to preload into the in-memory chain when started with `--mock-chain`.
+    pub mock_chain_seed_path: Option<String>,
+    /// How long, after SIGTERM, the finalizer may keep waiting on a confirmation
+    /// before it releases the job for the next start.
+    pub shutdown_grace_ms: u64,
+    /// Signs join tickets. Required outside `--mock-chain`, where a key generated
+    /// at startup is used when unset.
+    pub join_ticket_keypair_path: Option<String>,
+    pub join_ticket_ttl_seconds: i64,
+    /// What each `broken` reason_code does; see `reason_policy`.
+    pub reason_policy: ReasonPolicy,
 }
 
 impl Config {
@@ -17,20 +98,270 @@ impl Config {
         Ok(Self {
             app_bind_addr: env("APP_BIND_ADDR")?,
             database_url: env("DATABASE_URL")?,
-            solana_rpc_url: env("SOLANA_RPC_URL")?,
-            program_id: env("PROGRAM_ID")?,
-            authority_pubkey: env("AUTHORITY_PUBKEY")?,
-            authority_keypair_path: env("AUTHORITY_KEYPAIR_PATH")?,
-            internal_hmac_secret: env("INTERNAL_HMAC_SECRET")?,
+            environments: environments_from_env()?,
+            rpc_timeout_ms: env_parse_or("RPC_TIMEOUT_MS", 10_000)?,
+            rpc_slow_call_ms: env_parse_or("RPC_SLOW_CALL_MS", 2_000)?,
+            rpc_health_check_ms: env_parse_or("RPC_HEALTH_CHECK_MS", 15_000)?,
+            fee_payer_keypair_path: env_opt("FEE_PAYER_KEYPAIR_PATH"),
+            fee_payer_min_balance_lamports: env_parse_or(
+                "FEE_PAYER_MIN_BALANCE_LAMPORTS",
+                10_000_000,
+            )?,
+            authority_min_balance_lamports: env_parse_or("AUTHORITY_MIN_BALANCE_LAMPORTS", 0)?,
+            balance_check_ms: env_parse_or("BALANCE_CHECK_MS", 60_000)?,
+            internal_hmac_secret: env_opt("INTERNAL_HMAC_SECRET").unwrap_or_default(),
+            shared_hmac_secret_enabled: env_parse_or("SHARED_HMAC_SECRET_ENABLED", true)?,
+            hmac_v1_signatures_enabled: env_parse_or("HMAC_V1_SIGNATURES_ENABLED", true)?,
+            admin_hmac_secret: env_opt("ADMIN_HMAC_SECRET"),
             finalizer_poll_ms: env_parse("FINALIZER_POLL_MS")?,
+            match_reservation_ttl_seconds: env_parse_or("MATCH_RESERVATION_TTL_SECONDS", 300)?,
+            create_watch_poll_ms: env_parse_or("CREATE_WATCH_POLL_MS", 2_000)?,
+            nonce_prune_ms: env_parse_or("NONCE_PRUNE_MS", 60_000)?,
+            sponsor_keypair_path: env_opt("SPONSOR_KEYPAIR_PATH"),
+            sponsor_wallet_daily_limit: env_parse_or("SPONSOR_WALLET_DAILY_LIMIT", 5)?,
+            sponsor_daily_limit: env_parse_or("SPONSOR_DAILY_LIMIT", 500)?,
+            mock_chain_seed_path: env_opt("MOCK_CHAIN_SEED_PATH"),
+            shutdown_grace_ms: env_parse_or("SHUTDOWN_GRACE_MS", 20_000)?,
+            join_ticket_keypair_path: env_opt("JOIN_TICKET_KEYPAIR_PATH"),
+            join_ticket_ttl_seconds: env_parse_or("JOIN_TICKET_TTL_SECONDS", 120)?,
+            reason_policy: match env_opt("REASON_CODE_POLICIES") {
+                Some(overrides) => ReasonPolicy::with_overrides(&overrides)?,
+                None => ReasonPolicy::default(),
+            },
         })
     }
+
+    /// The environment configured from `PROGRAM_ID`, used when a request names none.
+    pub fn default_environment(&self) -> &ProgramEnvConfig {
+        &self.environments[0]
+    }
+
+    /// The configured authority with this pubkey in any environment.
+    pub fn authority(&self, pubkey: &str) -> Option<&AuthorityConfig> {
+        self.environments
+            .iter()
+            .find_map(|env| env.authority(pubkey))
+    }
+}
+
+/// One entry of the `PROGRAM_ENVIRONMENTS_PATH` file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProgramEnvEntry {
+    name: String,
+    program_id: String,
+    program_idl_path: Option<String>,
+    rpc_urls: Vec<String>,
+    authority_pubkey: String,
+    authorities: Vec<AuthorityEntry>,
+}
+
+/// The default environment from `PROGRAM_ID`, `SOLANA_RPC_URL(S)` and
+/// `AUTHORITY_*`, then any listed in the JSON file at `PROGRAM_ENVIRONMENTS_PATH`.
+fn environments_from_env() -> Result<Vec<ProgramEnvConfig>> {
+    let mut environments = vec![ProgramEnvConfig {
+        name: env_opt("PROGRAM_ENV_NAME").unwrap_or_else(|| "default".into()),
+        program_id: env("PROGRAM_ID")?,
+        program_idl_path: env_opt("PROGRAM_IDL_PATH"),
+        solana_rpc_urls: rpc_urls_from_env()?,
+        authority_pubkey: env("AUTHORITY_PUBKEY")?,
+        authorities: authorities_from_env()?,
+    }];
+    if let Some(path) = env_opt("PROGRAM_ENVIRONMENTS_PATH") {
+        let raw = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read PROGRAM_ENVIRONMENTS_PATH {path}"))?;
+        let entries: Vec<ProgramEnvEntry> = serde_json::from_str(&raw)
+            .with_context(|| format!("invalid PROGRAM_ENVIRONMENTS_PATH {path}"))?;
+        for entry in entries {
+            environments.push(environment_from_entry(entry)?);
+        }
+    }
+    validate_environments(&environments)?;
+    Ok(environments)
+}
+
+fn environment_from_entry(entry: ProgramEnvEntry) -> Result<ProgramEnvConfig> {
+    let authorities = entry
+        .authorities
+        .into_iter()
+        .map(authority_from_entry)
+        .collect::<Result<Vec<_>>>()?;
+    let rpc_urls: Vec<String> = entry
+        .rpc_urls
+        .iter()
+        .map(|url| url.trim().to_string())
+        .filter(|url| !url.is_empty())
+        .collect();
+    if rpc_urls.is_empty() {
+        bail!("environment {} needs at least one RPC URL", entry.name);
+    }
+    Ok(ProgramEnvConfig {
+        name: entry.name,
+        program_id: entry.program_id,
+        program_idl_path: entry.program_idl_path,
+        solana_rpc_urls: rpc_urls,
+        authority_pubkey: entry.authority_pubkey,
+        authorities,
+    })
+}
+
+/// Names and program ids are unique, every authority is configured once, and each
+/// environment's `authority_pubkey` is one of its active authorities.
+pub fn validate_environments(environments: &[ProgramEnvConfig]) -> Result<()> {
+    for (i, environment) in environments.iter().enumerate() {
+        for other in &environments[..i] {
+            if other.name == environment.name {
+                bail!("environment name {} is used twice", environment.name);
+            }
+            if other.program_id == environment.program_id {
+                bail!(
+                    "program {} is configured by both {} and {}",
+                    environment.program_id,
+                    other.name,
+                    environment.name
+                );
+            }
+        }
+        for (j, authority) in environment.authorities.iter().enumerate() {
+            let repeated = environment.authorities[..j]
+                .iter()
+                .chain(environments[..i].iter().flat_map(|e| &e.authorities))
+                .any(|a| a.pubkey == authority.pubkey);
+            if repeated {
+                bail!("authority {} is configured twice", authority.pubkey);
+            }
+        }
+        match environment.authority(&environment.authority_pubkey) {
+            Some(a) if a.status == AuthorityStatus::Active => {}
+            _ => bail!(
+                "environment {}: authority_pubkey {} must be one of its active authorities",
+                environment.name,
+                environment.authority_pubkey
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// One entry of the `AUTHORITIES_PATH` file. Exactly one signer source is set;
+/// keystore passphrases are only read from files.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AuthorityEntry {
+    pubkey: String,
+    status: AuthorityStatus,
+    keypair_path: Option<String>,
+    keystore_path: Option<String>,
+    keystore_passphrase_file: Option<String>,
+    remote_signer: Option<String>,
+}
+
+/// `AUTHORITY_PUBKEY` with its signer (always active), then the extra
+/// authorities listed in the JSON file at `AUTHORITIES_PATH`.
+fn authorities_from_env() -> Result<Vec<AuthorityConfig>> {
+    let mut authorities = vec![AuthorityConfig {
+        pubkey: env("AUTHORITY_PUBKEY")?,
+        signer: authority_signer_from_env()?,
+        status: AuthorityStatus::Active,
+    }];
+    if let Some(path) = env_opt("AUTHORITIES_PATH") {
+        let raw = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read AUTHORITIES_PATH {path}"))?;
+        let entries: Vec<AuthorityEntry> = serde_json::from_str(&raw)
+            .with_context(|| format!("invalid AUTHORITIES_PATH {path}"))?;
+        for entry in entries {
+            authorities.push(authority_from_entry(entry)?);
+        }
+    }
+    Ok(authorities)
+}
+
+fn authority_from_entry(entry: AuthorityEntry) -> Result<AuthorityConfig> {
+    let signer = match (entry.keypair_path, entry.keystore_path, entry.remote_signer) {
+        (Some(path), None, None) => SignerConfig::KeypairFile { path },
+        (None, Some(path), None) => {
+            let passphrase_file = entry.keystore_passphrase_file.with_context(|| {
+                format!("authority {} needs keystore_passphrase_file", entry.pubkey)
+            })?;
+            SignerConfig::Keystore {
+                path,
+                passphrase: read_passphrase_file(&passphrase_file)?,
+            }
+        }
+        (None, None, Some(endpoint)) => SignerConfig::Remote { endpoint },
+        _ => bail!(
+            "authority {} needs exactly one of keypair_path, keystore_path or remote_signer",
+            entry.pubkey
+        ),
+    };
+    Ok(AuthorityConfig {
+        pubkey: entry.pubkey,
+        signer,
+        status: entry.status,
+    })
+}
+
+/// `AUTHORITY_REMOTE_SIGNER` wins over `AUTHORITY_KEYSTORE_PATH`, which wins over
+/// the plaintext `AUTHORITY_KEYPAIR_PATH`.
+pub fn authority_signer_from_env() -> Result<SignerConfig> {
+    if let Some(endpoint) = env_opt("AUTHORITY_REMOTE_SIGNER") {
+        return Ok(SignerConfig::Remote { endpoint });
+    }
+    if let Some(path) = env_opt("AUTHORITY_KEYSTORE_PATH") {
+        return Ok(SignerConfig::Keystore {
+            path,
+            passphrase: keystore_passphrase_from_env()?,
+        });
+    }
+    Ok(SignerConfig::KeypairFile {
+        path: env("AUTHORITY_KEYPAIR_PATH")?,
+    })
+}
+
+/// `AUTHORITY_KEYSTORE_PASSPHRASE`, or the first line of `AUTHORITY_KEYSTORE_PASSPHRASE_FILE`.
+pub fn keystore_passphrase_from_env() -> Result<String> {
+    if let Some(passphrase) = env_opt("AUTHORITY_KEYSTORE_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    let path = env("AUTHORITY_KEYSTORE_PASSPHRASE_FILE").context(
+        "AUTHORITY_KEYSTORE_PASSPHRASE or AUTHORITY_KEYSTORE_PASSPHRASE_FILE is required",
+    )?;
+    read_passphrase_file(&path)
+}
+
+fn read_passphrase_file(path: &str) -> Result<String> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read passphrase file {path}"))?;
+    Ok(raw.lines().next().unwrap_or_default().to_string())
+}
+
+/// `SOLANA_RPC_URLS` (comma-separated, in preference order) wins over `SOLANA_RPC_URL`.
+fn rpc_urls_from_env() -> Result<Vec<String>> {
+    let raw = match env_opt("SOLANA_RPC_URLS") {
+        Some(list) => list,
+        None => env("SOLANA_RPC_URL")?,
+    };
+
+    let urls: Vec<String> = raw
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(ToOwned::to_owned)
+        .collect();
+    if urls.is_empty() {
+        bail!("SOLANA_RPC_URLS/SOLANA_RPC_URL must contain at least one URL");
+    }
+    Ok(urls)
 }
 
 fn env(name: &str) -> Result<String> {
     std::env::var(name).with_context(|| format!("missing env var {}", name))
 }
 
+fn env_opt(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
 fn env_parse<T>(name: &str) -> Result<T>
 where
     T: std::str::FromStr,
@@ -40,3 +371,16 @@ where
     raw.parse::<T>()
         .with_context(|| format!("invalid value for {}: {}", name, raw))
 }
+
+fn env_parse_or<T>(name: &str, default: T) -> Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match env_opt(name) {
+        Some(raw) => raw
+            .parse::<T>()
+            .with_context(|| format!("invalid value for {}: {}", name, raw)),
+        None => Ok(default),
+    }
+}
@@ -11,6 +11,7 @@ use uuid::Uuid;
 
 use crate::{
     error::AppError,
+    metrics::metrics,
     models::enums::{ChainJobStatus, ChainJobType, MatchStatus},
 };
 
@@ -22,6 +23,12 @@ pub struct PersistResultAndEnqueueParams {
     pub reason_code: String,
     pub reason_detail: Option<String>,
     pub idempotency_key: String,
+    /// `traceparent` of the enqueueing request; kept from the first submission.
+    pub trace_parent: Option<String>,
+    /// The authenticated server reporting the result; `None` for the shared secret.
+    pub reported_by: Option<String>,
+    /// An admin override, which may also finalize a match held for review.
+    pub resolve_review: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -43,6 +50,7 @@ pub struct ClaimedFinalizerJob {
     pub last_tx_sig: Option<String>,
     pub game_pda: String,
     pub vault_pda: String,
+    pub trace_parent: Option<String>,
 }
 
 #[derive(Debug)]
@@ -54,6 +62,8 @@ struct MatchResultUpdateRow {
 struct ChainJobUpsertRow {
     job_type: ChainJobType,
     status: ChainJobStatus,
+    /// `false` when the result was resubmitted for an existing job.
+    inserted: bool,
 }
 
 #[derive(Debug)]
@@ -67,8 +77,10 @@ struct ClaimedJobSelectRow {
     last_tx_sig: Option<String>,
     game_pda: String,
     vault_pda: String,
+    trace_parent: Option<String>,
 }
 
+#[tracing::instrument(skip_all, fields(match_id = params.match_id))]
 pub async fn persist_result_and_enqueue(
     pool: &PgPool,
     params: PersistResultAndEnqueueParams,
@@ -84,6 +96,9 @@ pub async fn persist_result_and_enqueue(
     tx.commit()
         .await
         .map_err(|e| AppError::Internal(format!("failed to commit result transaction: {e}")))?;
+    if chain_job_row.inserted {
+        record_transition("pending");
+    }
 
     Ok(PersistResultAndEnqueueResult {
         match_status: match_row.match_status,
@@ -92,8 +107,11 @@ pub async fn persist_result_and_enqueue(
     })
 }
 
+/// Claims the next due job of a game of `program_id`.
+#[tracing::instrument(skip(pool))]
 pub async fn claim_next_due_finalizer_job(
     pool: &PgPool,
+    program_id: &str,
 ) -> Result<Option<ClaimedFinalizerJob>, AppError> {
     let mut tx = pool
         .begin()
@@ -110,11 +128,13 @@ pub async fn claim_next_due_finalizer_job(
           cj.winner_pubkey,
           cj.attempt_count,
           cj.last_tx_sig,
+          cj.trace_parent,
           m.game_pda,
           m.vault_pda
         from chain_jobs cj
         join matches m on m.match_id = cj.match_id
         where cj.status in ('pending', 'retrying', 'submitted')
+          and m.program_id = $1
           and cj.next_attempt_at <= now()
           and (
             cj.lock_token is null
@@ -126,6 +146,7 @@ pub async fn claim_next_due_finalizer_job(
         limit 1
         "#,
     )
+    .bind(program_id)
     .fetch_optional(&mut *tx)
     .await
     .map_err(|e| AppError::Internal(format!("failed to select due chain job: {e}")))?;
@@ -168,9 +189,11 @@ pub async fn claim_next_due_finalizer_job(
         last_tx_sig: claimed.last_tx_sig,
         game_pda: claimed.game_pda,
         vault_pda: claimed.vault_pda,
+        trace_parent: claimed.trace_parent,
     }))
 }
 
+#[tracing::instrument(skip(pool, lock_token))]
 pub async fn mark_job_submitted(
     pool: &PgPool,
     match_id: i64,
@@ -228,6 +251,8 @@ pub async fn mark_job_submitted(
     tx.commit()
         .await
         .map_err(|e| AppError::Internal(format!("failed to commit submit transaction: {e}")))?;
+    record_transition("submitted");
+    metrics().chain_job_attempts.inc();
 
     Ok(())
 }
@@ -271,6 +296,7 @@ pub async fn mark_job_failed(
     .await
 }
 
+#[tracing::instrument(skip(pool, lock_token))]
 pub async fn mark_job_confirmed_and_finalize_match(
     pool: &PgPool,
     match_id: i64,
@@ -285,7 +311,7 @@ pub async fn mark_job_confirmed_and_finalize_match(
         .await
         .map_err(|e| AppError::Internal(format!("failed to begin confirm transaction: {e}")))?;
 
-    let updated_job = sqlx::query(
+    let confirmed_job = sqlx::query(
         r#"
         update chain_jobs
         set
@@ -296,22 +322,20 @@ pub async fn mark_job_confirmed_and_finalize_match(
           locked_at = null,
           updated_at = now()
         where match_id = $1 and lock_token = $2
+        returning job_type, extract(epoch from now() - created_at)::float8 as queued_seconds
         "#,
     )
     .bind(match_id)
     .bind(lock_token)
     .bind(final_tx_sig)
-    .execute(&mut *tx)
+    .fetch_optional(&mut *tx)
     .await
-    .map_err(|e| AppError::Internal(format!("failed to mark chain job confirmed: {e}")))?;
-
-    if updated_job.rows_affected() != 1 {
-        return Err(AppError::Conflict(
-            "chain job confirm update lost lock or job no longer exists".into(),
-        ));
-    }
+    .map_err(|e| AppError::Internal(format!("failed to mark chain job confirmed: {e}")))?
+    .ok_or_else(|| {
+        AppError::Conflict("chain job confirm update lost lock or job no longer exists".into())
+    })?;
 
-    sqlx::query(
+    let finalized_match = sqlx::query(
         r#"
         update matches
         set
@@ -321,12 +345,13 @@ pub async fn mark_job_confirmed_and_finalize_match(
           last_error = null,
           updated_at = now()
         where match_id = $1
+        returning entry_lamports, player2_pubkey is not null as joined
         "#,
     )
     .bind(match_id)
     .bind(final_match_status_db)
     .bind(final_tx_sig)
-    .execute(&mut *tx)
+    .fetch_one(&mut *tx)
     .await
     .map_err(|e| AppError::Internal(format!("failed to finalize match status: {e}")))?;
 
@@ -334,6 +359,19 @@ pub async fn mark_job_confirmed_and_finalize_match(
         .await
         .map_err(|e| AppError::Internal(format!("failed to commit confirm transaction: {e}")))?;
 
+    let m = metrics();
+    record_transition("confirmed");
+    m.chain_job_confirmation_seconds
+        .with_label_values(&[confirmed_job.get::<&str, _>("job_type")])
+        .observe(confirmed_job.get("queued_seconds"));
+    let entry_lamports =
+        u64::try_from(finalized_match.get::<i64, _>("entry_lamports")).unwrap_or(0);
+    let players = if finalized_match.get("joined") { 2 } else { 1 };
+    match final_match_status {
+        MatchStatus::Settled => m.lamports_settled.inc_by(entry_lamports * 2),
+        _ => m.lamports_refunded.inc_by(entry_lamports * players),
+    }
+
     Ok(())
 }
 
@@ -369,6 +407,7 @@ async fn update_match_result(
         set
           match_status = case
             when match_status in ('created_on_chain', 'joined_on_chain', 'in_progress') then 'result_pending_finalize'
+            when $8 and match_status = 'held_for_review' then 'result_pending_finalize'
             else match_status
           end,
           finalization_reason_code = coalesce(finalization_reason_code, $2),
@@ -376,10 +415,15 @@ async fn update_match_result(
           winner_pubkey = coalesce(winner_pubkey, $4),
           result_idempotency_key = coalesce(result_idempotency_key, $5),
           result_reported_at = coalesce(result_reported_at, $6),
+          result_reported_by = case
+            when result_reported_at is null then $7
+            else result_reported_by
+          end,
           updated_at = $6
         where match_id = $1
           and (
             match_status in ('created_on_chain', 'joined_on_chain', 'in_progress')
+            or ($8 and match_status = 'held_for_review')
             or result_idempotency_key = $5
           )
           and (result_idempotency_key is null or result_idempotency_key = $5)
@@ -394,6 +438,8 @@ async fn update_match_result(
     .bind(&params.winner_pubkey)
     .bind(&params.idempotency_key)
     .bind(now)
+    .bind(&params.reported_by)
+    .bind(params.resolve_review)
     .fetch_optional(&mut **tx)
     .await
     .map_err(|e| AppError::Internal(format!("failed to persist match result: {e}")))?;
@@ -420,19 +466,21 @@ async fn upsert_chain_job(
           job_type,
           status,
           winner_pubkey,
-          next_attempt_at
+          next_attempt_at,
+          trace_parent
         )
-        values ($1, $2, 'pending', $3, now())
+        values ($1, $2, 'pending', $3, now(), $4)
         on conflict (match_id) do update
           set updated_at = now()
         where chain_jobs.job_type = excluded.job_type
           and chain_jobs.winner_pubkey is not distinct from excluded.winner_pubkey
-        returning job_type, status
+        returning job_type, status, (xmax = 0) as inserted
         "#,
     )
     .bind(params.match_id)
     .bind(chain_job_type_to_db(params.job_type))
     .bind(&params.winner_pubkey)
+    .bind(&params.trace_parent)
     .fetch_optional(&mut **tx)
     .await
     .map_err(|e| AppError::Internal(format!("failed to upsert chain job: {e}")))?;
@@ -444,6 +492,7 @@ async fn upsert_chain_job(
     Ok(ChainJobUpsertRow {
         job_type: parse_chain_job_type(row.get::<String, _>("job_type").as_str())?,
         status: parse_chain_job_status(row.get::<String, _>("status").as_str())?,
+        inserted: row.get("inserted"),
     })
 }
 
@@ -478,9 +527,11 @@ fn map_claimed_job_row(row: sqlx::postgres::PgRow) -> Result<ClaimedJobSelectRow
         last_tx_sig: row.get::<Option<String>, _>("last_tx_sig"),
         game_pda: row.get::<String, _>("game_pda"),
         vault_pda: row.get::<String, _>("vault_pda"),
+        trace_parent: row.get::<Option<String>, _>("trace_parent"),
     })
 }
 
+#[tracing::instrument(skip(pool, lock_token, error_message))]
 async fn mark_job_retry_or_failed(
     pool: &PgPool,
     match_id: i64,
@@ -552,17 +603,94 @@ async fn mark_job_retry_or_failed(
     tx.commit()
         .await
         .map_err(|e| AppError::Internal(format!("failed to commit retry/fail transaction: {e}")))?;
+    record_transition(next_status_db);
+    if increment_attempt_count {
+        metrics().chain_job_attempts.inc();
+    }
 
     parse_chain_job_status(row.get::<String, _>("status").as_str()).or(Ok(next_status))
 }
 
-fn parse_match_status(raw: &str) -> Result<MatchStatus, AppError> {
+/// Backlog of one program's finalizer queue, for the status endpoint.
+#[derive(Debug, Clone, Default)]
+pub struct FinalizerQueueStats {
+    /// Job count per `chain_jobs.status`, in status order.
+    pub by_status: Vec<(String, i64)>,
+    /// How long the longest-waiting due job has been due.
+    pub oldest_due_age_seconds: Option<i64>,
+    pub last_job_error: Option<String>,
+    pub last_job_error_at: Option<chrono::DateTime<Utc>>,
+}
+
+pub async fn finalizer_queue_stats(
+    pool: &PgPool,
+    program_id: &str,
+) -> Result<FinalizerQueueStats, AppError> {
+    let by_status = sqlx::query(
+        r#"
+        select cj.status, count(*) as jobs
+        from chain_jobs cj
+        join matches m on m.match_id = cj.match_id
+        where m.program_id = $1
+        group by cj.status
+        order by cj.status
+        "#,
+    )
+    .bind(program_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to count chain jobs: {e}")))?
+    .into_iter()
+    .map(|row| (row.get("status"), row.get("jobs")))
+    .collect();
+
+    let oldest_due_age_seconds = sqlx::query_scalar::<_, Option<i64>>(
+        r#"
+        select extract(epoch from now() - min(cj.next_attempt_at))::bigint
+        from chain_jobs cj
+        join matches m on m.match_id = cj.match_id
+        where m.program_id = $1
+          and cj.status in ('pending', 'retrying', 'submitted')
+          and cj.next_attempt_at <= now()
+        "#,
+    )
+    .bind(program_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to read oldest due job: {e}")))?;
+
+    let last_error = sqlx::query(
+        r#"
+        select cj.last_error, cj.updated_at
+        from chain_jobs cj
+        join matches m on m.match_id = cj.match_id
+        where m.program_id = $1
+          and cj.last_error is not null
+        order by cj.updated_at desc
+        limit 1
+        "#,
+    )
+    .bind(program_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to read last job error: {e}")))?;
+
+    Ok(FinalizerQueueStats {
+        by_status,
+        oldest_due_age_seconds,
+        last_job_error: last_error.as_ref().map(|row| row.get("last_error")),
+        last_job_error_at: last_error.as_ref().map(|row| row.get("updated_at")),
+    })
+}
+
+pub fn parse_match_status(raw: &str) -> Result<MatchStatus, AppError> {
     let status = match raw {
         "waiting_create_tx" => MatchStatus::WaitingCreateTx,
         "created_on_chain" => MatchStatus::CreatedOnChain,
         "joined_on_chain" => MatchStatus::JoinedOnChain,
         "in_progress" => MatchStatus::InProgress,
         "result_pending_finalize" => MatchStatus::ResultPendingFinalize,
+        "held_for_review" => MatchStatus::HeldForReview,
         "finalizing" => MatchStatus::Finalizing,
         "settled" => MatchStatus::Settled,
         "refunded" => MatchStatus::Refunded,
@@ -603,3 +731,10 @@ fn parse_chain_job_status(raw: &str) -> Result<ChainJobStatus, AppError> {
     };
     Ok(value)
 }
+
+fn record_transition(to: &str) {
+    metrics()
+        .chain_job_transitions
+        .with_label_values(&[to])
+        .inc();
+}
@@ -1,9 +1,9 @@
 //! Minimal DB helpers for `matches` in thin-finalizer mode.
 
 use chrono::{DateTime, Utc};
-use sqlx::PgPool;
+use sqlx::{PgPool, Row};
 
-use crate::{error::AppError, models::enums::MatchStatus};
+use crate::{error::AppError, models::enums::MatchStatus, solana::pda::derive_match_pdas};
 
 #[derive(Debug, Clone)]
 pub struct UpsertMatchFromChainParams<'a> {
@@ -61,7 +61,7 @@ pub async fn upsert_match_from_chain(
           player2_pubkey = coalesce(matches.player2_pubkey, excluded.player2_pubkey),
           entry_lamports = excluded.entry_lamports,
           match_status = case
-            when matches.match_status in ('settled', 'refunded', 'result_pending_finalize', 'finalizing')
+            when matches.match_status in ('settled', 'refunded', 'result_pending_finalize', 'held_for_review', 'finalizing')
               then matches.match_status
             when matches.match_status = 'joined_on_chain'
               then 'joined_on_chain'
@@ -154,3 +154,634 @@ fn to_base36(mut value: u64) -> String {
     }
     String::from_utf8_lossy(&buf[idx..]).into_owned()
 }
+
+#[derive(Debug, Clone)]
+pub struct ReservedMatch {
+    pub match_id: i64,
+    pub join_code: String,
+    pub game_pda: String,
+    pub vault_pda: String,
+    pub create_expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WaitingCreateMatch {
+    pub match_id: i64,
+    pub program_id: String,
+    pub authority_pubkey: String,
+    pub game_pda: String,
+    pub player1_pubkey: String,
+    pub entry_lamports: i64,
+    pub create_expires_at: Option<DateTime<Utc>>,
+}
+
+const MAX_RESERVE_ATTEMPTS: usize = 5;
+
+/// Allocates a fresh `match_id` and records it as `waiting_create_tx` until
+/// `ttl_seconds` from now.
+pub async fn reserve_match(
+    pool: &PgPool,
+    program_id: &str,
+    authority_pubkey: &str,
+    player1_pubkey: &str,
+    entry_lamports: i64,
+    ttl_seconds: i64,
+) -> Result<ReservedMatch, AppError> {
+    if entry_lamports <= 0 {
+        return Err(AppError::BadRequest("entry_lamports must be > 0".into()));
+    }
+
+    // Ids chosen by older clients live in the same key space, so a sequence value
+    // can already be taken; skip to the next one.
+    for _ in 0..MAX_RESERVE_ATTEMPTS {
+        let match_id: i64 =
+            sqlx::query_scalar("select nextval(pg_get_serial_sequence('matches', 'match_id'))")
+                .fetch_one(pool)
+                .await
+                .map_err(|e| AppError::Internal(format!("failed to allocate match_id: {e}")))?;
+
+        let join_code = join_code_from_match_id(match_id)?;
+        let pdas = derive_match_pdas(program_id, authority_pubkey, player1_pubkey, match_id)
+            .map_err(|e| AppError::BadRequest(format!("{e:#}")))?;
+
+        let expires_at: Option<DateTime<Utc>> = sqlx::query_scalar(
+            r#"
+            insert into matches (
+              match_id,
+              join_code,
+              program_id,
+              authority_pubkey,
+              game_pda,
+              vault_pda,
+              player1_pubkey,
+              entry_lamports,
+              match_status,
+              create_expires_at
+            )
+            values ($1, $2, $3, $4, $5, $6, $7, $8, 'waiting_create_tx',
+                    now() + make_interval(secs => $9))
+            on conflict do nothing
+            returning create_expires_at
+            "#,
+        )
+        .bind(match_id)
+        .bind(&join_code)
+        .bind(program_id)
+        .bind(authority_pubkey)
+        .bind(&pdas.game_pda)
+        .bind(&pdas.vault_pda)
+        .bind(player1_pubkey)
+        .bind(entry_lamports)
+        .bind(ttl_seconds as f64)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AppError::Internal(format!("failed to reserve match: {e}")))?
+        .flatten();
+
+        if let Some(create_expires_at) = expires_at {
+            return Ok(ReservedMatch {
+                match_id,
+                join_code,
+                game_pda: pdas.game_pda,
+                vault_pda: pdas.vault_pda,
+                create_expires_at,
+            });
+        }
+    }
+
+    Err(AppError::Internal(
+        "failed to allocate an unused match_id".into(),
+    ))
+}
+
+pub async fn list_waiting_create_tx(
+    pool: &PgPool,
+    program_id: &str,
+    limit: i64,
+) -> Result<Vec<WaitingCreateMatch>, AppError> {
+    let rows = sqlx::query(
+        r#"
+        select match_id, program_id, authority_pubkey, game_pda, player1_pubkey,
+               entry_lamports, create_expires_at
+        from matches
+        where match_status = 'waiting_create_tx'
+          and program_id = $1
+        order by create_expires_at asc nulls last
+        limit $2
+        "#,
+    )
+    .bind(program_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to list waiting matches: {e}")))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| WaitingCreateMatch {
+            match_id: r.get("match_id"),
+            program_id: r.get("program_id"),
+            authority_pubkey: r.get("authority_pubkey"),
+            game_pda: r.get("game_pda"),
+            player1_pubkey: r.get("player1_pubkey"),
+            entry_lamports: r.get("entry_lamports"),
+            create_expires_at: r.get("create_expires_at"),
+        })
+        .collect())
+}
+
+/// Moves a reservation to `created_on_chain` once its game account exists.
+/// The on-chain stake and creation time win over what was known at reservation.
+pub async fn mark_created_on_chain(
+    pool: &PgPool,
+    match_id: i64,
+    entry_lamports: i64,
+    created_onchain_at: DateTime<Utc>,
+) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        r#"
+        update matches
+        set match_status = 'created_on_chain',
+            entry_lamports = $2,
+            created_onchain_at = $3,
+            create_expires_at = null,
+            updated_at = now()
+        where match_id = $1
+          and match_status = 'waiting_create_tx'
+        "#,
+    )
+    .bind(match_id)
+    .bind(entry_lamports)
+    .bind(created_onchain_at)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to mark match created: {e}")))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Drops a reservation whose create transaction never showed up.
+pub async fn delete_expired_reservation(pool: &PgPool, match_id: i64) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        r#"
+        delete from matches
+        where match_id = $1
+          and match_status = 'waiting_create_tx'
+          and create_expires_at <= now()
+          and assigned_server_id is null
+        "#,
+    )
+    .bind(match_id)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to delete expired reservation: {e}")))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// The program a tracked game was recorded under.
+pub async fn find_program_id_by_game_pda(
+    pool: &PgPool,
+    game_pda: &str,
+) -> Result<Option<String>, AppError> {
+    sqlx::query_scalar("select program_id from matches where game_pda = $1 limit 1")
+        .bind(game_pda)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| AppError::Internal(format!("failed to lookup match program: {e}")))
+}
+
+/// The server hosting a match; `None` when the match is unknown or unassigned.
+pub async fn find_assigned_server(
+    pool: &PgPool,
+    match_id: i64,
+) -> Result<Option<String>, AppError> {
+    sqlx::query_scalar::<_, Option<String>>(
+        "select assigned_server_id from matches where match_id = $1",
+    )
+    .bind(match_id)
+    .fetch_optional(pool)
+    .await
+    .map(Option::flatten)
+    .map_err(|e| AppError::Internal(format!("failed to lookup assigned server: {e}")))
+}
+
+/// A live (unexpired) reservation, for building its `create_game` transaction.
+pub async fn find_open_reservation(
+    pool: &PgPool,
+    match_id: i64,
+) -> Result<Option<WaitingCreateMatch>, AppError> {
+    let row = sqlx::query(
+        r#"
+        select match_id, program_id, authority_pubkey, game_pda, player1_pubkey,
+               entry_lamports, create_expires_at
+        from matches
+        where match_id = $1
+          and match_status = 'waiting_create_tx'
+          and (create_expires_at is null or create_expires_at > now())
+        "#,
+    )
+    .bind(match_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to lookup reservation: {e}")))?;
+
+    Ok(row.map(|r| WaitingCreateMatch {
+        match_id: r.get("match_id"),
+        program_id: r.get("program_id"),
+        authority_pubkey: r.get("authority_pubkey"),
+        game_pda: r.get("game_pda"),
+        player1_pubkey: r.get("player1_pubkey"),
+        entry_lamports: r.get("entry_lamports"),
+        create_expires_at: r.get("create_expires_at"),
+    }))
+}
+
+/// Stores the signature of a relayed `create_game` and keeps the reservation
+/// alive for at least `min_ttl_seconds` so it outlives the transaction.
+pub async fn record_create_tx(
+    pool: &PgPool,
+    match_id: i64,
+    signature: &str,
+    min_ttl_seconds: i64,
+) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        r#"
+        update matches
+        set create_tx_sig = $2,
+            create_tx_sent_at = now(),
+            create_expires_at = greatest(create_expires_at, now() + make_interval(secs => $3)),
+            last_error = null,
+            updated_at = now()
+        where match_id = $1
+          and match_status = 'waiting_create_tx'
+        "#,
+    )
+    .bind(match_id)
+    .bind(signature)
+    .bind(min_ttl_seconds as f64)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to record create tx: {e}")))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+#[derive(Debug, Clone)]
+pub struct JoinableMatch {
+    pub match_id: i64,
+    pub program_id: String,
+    pub authority_pubkey: String,
+    pub player1_pubkey: String,
+    pub join_tx_sig: Option<String>,
+}
+
+/// A match whose game is on-chain and has no second player yet.
+pub async fn find_joinable_match(
+    pool: &PgPool,
+    game_pda: &str,
+) -> Result<Option<JoinableMatch>, AppError> {
+    let row = sqlx::query(
+        r#"
+        select match_id, program_id, authority_pubkey, player1_pubkey, join_tx_sig
+        from matches
+        where game_pda = $1
+          and match_status = 'created_on_chain'
+        "#,
+    )
+    .bind(game_pda)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to lookup match: {e}")))?;
+
+    Ok(row.map(|r| JoinableMatch {
+        match_id: r.get("match_id"),
+        program_id: r.get("program_id"),
+        authority_pubkey: r.get("authority_pubkey"),
+        player1_pubkey: r.get("player1_pubkey"),
+        join_tx_sig: r.get("join_tx_sig"),
+    }))
+}
+
+/// Stores the signature of a relayed `join_game`. Only one join may be in flight.
+pub async fn record_join_tx(
+    pool: &PgPool,
+    match_id: i64,
+    signature: &str,
+) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        r#"
+        update matches
+        set join_tx_sig = $2,
+            join_tx_sent_at = now(),
+            last_error = null,
+            updated_at = now()
+        where match_id = $1
+          and match_status = 'created_on_chain'
+          and (join_tx_sig is null or join_tx_sig = $2)
+        "#,
+    )
+    .bind(match_id)
+    .bind(signature)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to record join tx: {e}")))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayedTxKind {
+    Create,
+    Join,
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingRelayedTx {
+    pub match_id: i64,
+    pub kind: RelayedTxKind,
+    pub signature: String,
+    pub game_pda: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// Relayed signatures whose match has not moved past the step they submit.
+pub async fn list_pending_relayed_txs(
+    pool: &PgPool,
+    program_id: &str,
+    limit: i64,
+) -> Result<Vec<PendingRelayedTx>, AppError> {
+    let rows = sqlx::query(
+        r#"
+        select match_id, 'create' as kind, create_tx_sig as signature, game_pda,
+               create_tx_sent_at as sent_at
+        from matches
+        where match_status = 'waiting_create_tx'
+          and program_id = $1
+          and create_tx_sig is not null
+        union all
+        select match_id, 'join' as kind, join_tx_sig as signature, game_pda,
+               join_tx_sent_at as sent_at
+        from matches
+        where match_status = 'created_on_chain'
+          and program_id = $1
+          and join_tx_sig is not null
+        order by sent_at asc
+        limit $2
+        "#,
+    )
+    .bind(program_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to list relayed transactions: {e}")))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| PendingRelayedTx {
+            match_id: r.get("match_id"),
+            kind: match r.get::<&str, _>("kind") {
+                "create" => RelayedTxKind::Create,
+                _ => RelayedTxKind::Join,
+            },
+            signature: r.get("signature"),
+            game_pda: r.get("game_pda"),
+            sent_at: r
+                .get::<Option<DateTime<Utc>>, _>("sent_at")
+                .unwrap_or_else(Utc::now),
+        })
+        .collect())
+}
+
+/// Forgets a relayed signature that failed or never landed, so the player can
+/// send a new transaction.
+pub async fn clear_relayed_tx(
+    pool: &PgPool,
+    match_id: i64,
+    kind: RelayedTxKind,
+    signature: &str,
+    error: &str,
+) -> Result<bool, AppError> {
+    let sql = match kind {
+        RelayedTxKind::Create => {
+            r#"
+            update matches
+            set create_tx_sig = null, create_tx_sent_at = null, last_error = $3, updated_at = now()
+            where match_id = $1 and create_tx_sig = $2
+            "#
+        }
+        RelayedTxKind::Join => {
+            r#"
+            update matches
+            set join_tx_sig = null, join_tx_sent_at = null, last_error = $3, updated_at = now()
+            where match_id = $1 and join_tx_sig = $2
+            "#
+        }
+    };
+    let result = sqlx::query(sql)
+        .bind(match_id)
+        .bind(signature)
+        .bind(error)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Internal(format!("failed to clear relayed tx: {e}")))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Moves a match to `joined_on_chain` once its `join_game` has landed.
+pub async fn mark_joined_on_chain(
+    pool: &PgPool,
+    match_id: i64,
+    player2_pubkey: &str,
+) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        r#"
+        update matches
+        set match_status = 'joined_on_chain',
+            player2_pubkey = $2,
+            acceptor_pubkey = coalesce(acceptor_pubkey, $2),
+            joined_onchain_at = coalesce(joined_onchain_at, now()),
+            updated_at = now()
+        where match_id = $1
+          and match_status = 'created_on_chain'
+        "#,
+    )
+    .bind(match_id)
+    .bind(player2_pubkey)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to mark match joined: {e}")))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// What a kickoff report is checked against.
+#[derive(Debug, Clone)]
+pub struct MatchKickoff {
+    pub match_id: i64,
+    pub match_status: String,
+    pub player1_pubkey: String,
+    pub player2_pubkey: Option<String>,
+    pub assigned_server_id: Option<String>,
+    pub kicked_off_at: Option<DateTime<Utc>>,
+    pub kickoff_wallets: Option<Vec<String>>,
+    pub player1_connected_at: Option<DateTime<Utc>>,
+    pub player2_connected_at: Option<DateTime<Utc>>,
+}
+
+impl MatchKickoff {
+    /// The players the assigned server has reported connected, player1 first.
+    pub fn connected_wallets(&self) -> Vec<String> {
+        let player1 = self
+            .player1_connected_at
+            .map(|_| self.player1_pubkey.clone());
+        let player2 = self.player2_connected_at.and(self.player2_pubkey.clone());
+        player1.into_iter().chain(player2).collect()
+    }
+
+    pub fn seen_connected(&self, wallet: &str) -> bool {
+        self.connected_wallets().iter().any(|w| w == wallet)
+    }
+}
+
+pub async fn find_match_kickoff(
+    pool: &PgPool,
+    match_id: i64,
+) -> Result<Option<MatchKickoff>, AppError> {
+    let row = sqlx::query(
+        r#"
+        select match_id, match_status, player1_pubkey, player2_pubkey,
+               assigned_server_id, kicked_off_at, kickoff_wallets,
+               player1_connected_at, player2_connected_at
+        from matches
+        where match_id = $1
+        "#,
+    )
+    .bind(match_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to lookup match kickoff: {e}")))?;
+
+    Ok(row.map(|row| MatchKickoff {
+        match_id: row.get("match_id"),
+        match_status: row.get("match_status"),
+        player1_pubkey: row.get("player1_pubkey"),
+        player2_pubkey: row.get("player2_pubkey"),
+        assigned_server_id: row.get("assigned_server_id"),
+        kicked_off_at: row.get("kicked_off_at"),
+        kickoff_wallets: row.get("kickoff_wallets"),
+        player1_connected_at: row.get("player1_connected_at"),
+        player2_connected_at: row.get("player2_connected_at"),
+    }))
+}
+
+/// Records that the players flagged are connected, keeping the first time each
+/// was seen. Returns `false` if the match is past the point of being played.
+pub async fn record_player_connections(
+    pool: &PgPool,
+    match_id: i64,
+    player1: bool,
+    player2: bool,
+) -> Result<bool, AppError> {
+    let result = sqlx::query(
+        r#"
+        update matches
+        set player1_connected_at = case when $2 then coalesce(player1_connected_at, now())
+                                        else player1_connected_at end,
+            player2_connected_at = case when $3 then coalesce(player2_connected_at, now())
+                                        else player2_connected_at end,
+            updated_at = now()
+        where match_id = $1
+          and match_status in ('created_on_chain', 'joined_on_chain', 'in_progress')
+        "#,
+    )
+    .bind(match_id)
+    .bind(player1)
+    .bind(player2)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to record player connections: {e}")))?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+/// Moves a `joined_on_chain` match whose players have both connected to
+/// `in_progress`, recording them as its `kickoff_wallets`. Returns the kickoff
+/// time, or `None` if the match was not ready to kick off.
+pub async fn mark_in_progress(
+    pool: &PgPool,
+    match_id: i64,
+) -> Result<Option<DateTime<Utc>>, AppError> {
+    sqlx::query_scalar(
+        r#"
+        update matches
+        set match_status = 'in_progress',
+            kicked_off_at = now(),
+            kickoff_wallets = array[player1_pubkey, player2_pubkey],
+            updated_at = now()
+        where match_id = $1
+          and match_status = 'joined_on_chain'
+          and player2_pubkey is not null
+          and player1_connected_at is not null
+          and player2_connected_at is not null
+        returning kicked_off_at
+        "#,
+    )
+    .bind(match_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to mark match in progress: {e}")))
+}
+
+#[derive(Debug, Clone)]
+pub struct HoldForReviewParams<'a> {
+    pub match_id: i64,
+    pub reason_code: &'a str,
+    pub reason_detail: Option<&'a str>,
+    pub idempotency_key: &'a str,
+    pub held_by: Option<&'a str>,
+}
+
+/// Parks a `broken` result for an operator instead of enqueueing a chain job.
+/// A repeat with the same idempotency key is accepted again.
+pub async fn hold_for_review(
+    pool: &PgPool,
+    params: &HoldForReviewParams<'_>,
+) -> Result<(), AppError> {
+    let result = sqlx::query(
+        r#"
+        update matches
+        set match_status = 'held_for_review',
+            held_at = coalesce(held_at, now()),
+            held_by = case when held_at is null then $5 else held_by end,
+            hold_reason_code = $2,
+            hold_reason_detail = $3,
+            hold_idempotency_key = $4,
+            updated_at = now()
+        where match_id = $1
+          and (
+            (match_status in ('created_on_chain', 'joined_on_chain', 'in_progress')
+              and result_idempotency_key is null)
+            or (match_status = 'held_for_review'
+              and hold_idempotency_key = $4
+              and hold_reason_code = $2)
+          )
+        "#,
+    )
+    .bind(params.match_id)
+    .bind(params.reason_code)
+    .bind(params.reason_detail)
+    .bind(params.idempotency_key)
+    .bind(params.held_by)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to hold match for review: {e}")))?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::Conflict(
+            "result conflicts with existing match finalization state or idempotency key".into(),
+        ));
+    }
+    Ok(())
+}
@@ -1,3 +1,9 @@
 pub mod chain_jobs;
 pub mod matches;
+pub mod security_audit;
+pub mod server_credentials;
+pub mod sponsorships;
 pub mod used_nonces;
+
+/// The schema migrations embedded in this build.
+pub static MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations");
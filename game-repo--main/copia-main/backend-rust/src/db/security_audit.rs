@@ -0,0 +1,38 @@
+use sqlx::PgPool;
+
+use crate::error::AppError;
+
+#[derive(Debug)]
+pub struct AuditRecord<'a> {
+    /// e.g. `finalize_not_assigned_server` or `finalize_admin_override`.
+    pub event: &'a str,
+    /// `server`, `shared` or `admin`.
+    pub caller: &'a str,
+    pub caller_server_id: Option<&'a str>,
+    pub caller_key_id: Option<&'a str>,
+    pub target_server_id: Option<&'a str>,
+    pub match_id: Option<i64>,
+    pub detail: &'a str,
+}
+
+pub async fn insert_audit_record(pool: &PgPool, record: &AuditRecord<'_>) -> Result<(), AppError> {
+    sqlx::query(
+        r#"
+        insert into security_audit_log (
+          event, caller, caller_server_id, caller_key_id, target_server_id, match_id, detail
+        )
+        values ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(record.event)
+    .bind(record.caller)
+    .bind(record.caller_server_id)
+    .bind(record.caller_key_id)
+    .bind(record.target_server_id)
+    .bind(record.match_id)
+    .bind(record.detail)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to write security audit log: {e}")))?;
+    Ok(())
+}
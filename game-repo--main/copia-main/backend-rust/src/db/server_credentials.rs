@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, PgPool, Row};
+
+use crate::error::AppError;
+
+#[derive(Debug, Clone)]
+pub struct ServerCredential {
+    pub key_id: String,
+    pub server_id: String,
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ServerCredential {
+    fn from_row(row: PgRow) -> Self {
+        Self {
+            key_id: row.get("key_id"),
+            server_id: row.get("server_id"),
+            secret: row.get("secret"),
+            created_at: row.get("created_at"),
+            expires_at: row.get("expires_at"),
+            revoked_at: row.get("revoked_at"),
+        }
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+pub async fn insert_credential(
+    pool: &PgPool,
+    key_id: &str,
+    server_id: &str,
+    secret: &str,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<ServerCredential, AppError> {
+    sqlx::query(
+        r#"
+        insert into server_credentials (key_id, server_id, secret, expires_at)
+        values ($1, $2, $3, $4)
+        returning key_id, server_id, secret, created_at, expires_at, revoked_at
+        "#,
+    )
+    .bind(key_id)
+    .bind(server_id)
+    .bind(secret)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await
+    .map(ServerCredential::from_row)
+    .map_err(|e| AppError::Internal(format!("failed to store server credential: {e}")))
+}
+
+/// The credential with this key id, revoked and expired ones included.
+pub async fn find_credential(
+    pool: &PgPool,
+    key_id: &str,
+) -> Result<Option<ServerCredential>, AppError> {
+    sqlx::query(
+        r#"
+        select key_id, server_id, secret, created_at, expires_at, revoked_at
+        from server_credentials
+        where key_id = $1
+        "#,
+    )
+    .bind(key_id)
+    .fetch_optional(pool)
+    .await
+    .map(|row| row.map(ServerCredential::from_row))
+    .map_err(|e| AppError::Internal(format!("failed to load server credential: {e}")))
+}
+
+/// Every credential ever issued to the server, newest first.
+pub async fn list_credentials(
+    pool: &PgPool,
+    server_id: &str,
+) -> Result<Vec<ServerCredential>, AppError> {
+    let rows = sqlx::query(
+        r#"
+        select key_id, server_id, secret, created_at, expires_at, revoked_at
+        from server_credentials
+        where server_id = $1
+        order by created_at desc, key_id
+        "#,
+    )
+    .bind(server_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to list server credentials: {e}")))?;
+
+    Ok(rows.into_iter().map(ServerCredential::from_row).collect())
+}
+
+/// Whether the server was ever issued a credential, revoked and expired ones included.
+pub async fn has_any_credential(pool: &PgPool, server_id: &str) -> Result<bool, AppError> {
+    sqlx::query_scalar("select exists (select 1 from server_credentials where server_id = $1)")
+        .bind(server_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| AppError::Internal(format!("failed to check server credentials: {e}")))
+}
+
+/// Revokes the credential; revoking it again keeps the first revocation time.
+pub async fn revoke_credential(
+    pool: &PgPool,
+    key_id: &str,
+) -> Result<Option<ServerCredential>, AppError> {
+    sqlx::query(
+        r#"
+        update server_credentials
+        set revoked_at = coalesce(revoked_at, now())
+        where key_id = $1
+        returning key_id, server_id, secret, created_at, expires_at, revoked_at
+        "#,
+    )
+    .bind(key_id)
+    .fetch_optional(pool)
+    .await
+    .map(|row| row.map(ServerCredential::from_row))
+    .map_err(|e| AppError::Internal(format!("failed to revoke server credential: {e}")))
+}
@@ -0,0 +1,96 @@
+//! Quota bookkeeping for transactions whose fee the backend sponsor pays.
+
+use sqlx::PgPool;
+
+use crate::error::AppError;
+
+/// Serializes quota checks so concurrent requests cannot overshoot a limit.
+const SPONSOR_QUOTA_LOCK: i64 = 0x0053_504f_4e53_4f52;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SponsorshipGrant {
+    Granted,
+    WalletQuotaExceeded,
+    DailyQuotaExceeded,
+}
+
+pub struct NewSponsorship<'a> {
+    pub signature: &'a str,
+    pub wallet: &'a str,
+    pub match_id: i64,
+    pub instruction: &'a str,
+    pub wallet_daily_limit: i64,
+    pub daily_limit: i64,
+}
+
+/// Records a sponsored transaction if both rolling 24-hour quotas allow it.
+/// Re-recording the same signature is granted without counting twice.
+pub async fn try_record_sponsorship(
+    pool: &PgPool,
+    new: &NewSponsorship<'_>,
+) -> Result<SponsorshipGrant, AppError> {
+    let map_err = |e: sqlx::Error| AppError::Internal(format!("failed to record sponsorship: {e}"));
+    let mut tx = pool.begin().await.map_err(map_err)?;
+
+    sqlx::query("select pg_advisory_xact_lock($1)")
+        .bind(SPONSOR_QUOTA_LOCK)
+        .execute(&mut *tx)
+        .await
+        .map_err(map_err)?;
+
+    let existing: Option<String> =
+        sqlx::query_scalar("select signature from sponsored_txs where signature = $1")
+            .bind(new.signature)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(map_err)?;
+    if existing.is_some() {
+        return Ok(SponsorshipGrant::Granted);
+    }
+
+    let (wallet_count, total_count): (i64, i64) = sqlx::query_as(
+        r#"
+        select count(*) filter (where wallet = $1), count(*)
+        from sponsored_txs
+        where created_at > now() - interval '1 day'
+        "#,
+    )
+    .bind(new.wallet)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(map_err)?;
+    if wallet_count >= new.wallet_daily_limit {
+        return Ok(SponsorshipGrant::WalletQuotaExceeded);
+    }
+    if total_count >= new.daily_limit {
+        return Ok(SponsorshipGrant::DailyQuotaExceeded);
+    }
+
+    sqlx::query(
+        r#"
+        insert into sponsored_txs (signature, wallet, match_id, instruction)
+        values ($1, $2, $3, $4)
+        "#,
+    )
+    .bind(new.signature)
+    .bind(new.wallet)
+    .bind(new.match_id)
+    .bind(new.instruction)
+    .execute(&mut *tx)
+    .await
+    .map_err(map_err)?;
+
+    tx.commit().await.map_err(map_err)?;
+    Ok(SponsorshipGrant::Granted)
+}
+
+/// Gives the quota back for a transaction the cluster rejected before it was
+/// processed, so no fee was charged.
+pub async fn release_sponsorship(pool: &PgPool, signature: &str) -> Result<(), AppError> {
+    sqlx::query("delete from sponsored_txs where signature = $1")
+        .bind(signature)
+        .execute(pool)
+        .await
+        .map_err(|e| AppError::Internal(format!("failed to release sponsorship: {e}")))?;
+    Ok(())
+}
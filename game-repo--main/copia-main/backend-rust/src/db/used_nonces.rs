@@ -17,3 +17,45 @@ pub async fn insert_nonce_if_unused(pool: &PgPool, nonce: &str) -> Result<bool,
 
     Ok(result.rows_affected() == 1)
 }
+
+/// Deletes up to `limit` nonces recorded more than `older_than_seconds` ago.
+/// Returns how many were deleted.
+pub async fn prune_nonces(
+    pool: &PgPool,
+    older_than_seconds: i64,
+    limit: i64,
+) -> Result<u64, AppError> {
+    let result = sqlx::query(
+        r#"
+        delete from used_nonces
+        where nonce in (
+          select nonce
+          from used_nonces
+          where created_at < now() - make_interval(secs => $1)
+          order by created_at
+          limit $2
+        )
+        "#,
+    )
+    .bind(older_than_seconds as f64)
+    .bind(limit)
+    .execute(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to prune nonces: {e}")))?;
+
+    Ok(result.rows_affected())
+}
+
+/// Rows and on-disk bytes (indexes included) of `used_nonces`.
+pub async fn table_size(pool: &PgPool) -> Result<(i64, i64), AppError> {
+    sqlx::query_as(
+        r#"
+        select
+          (select count(*) from used_nonces),
+          pg_total_relation_size('used_nonces')
+        "#,
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| AppError::Internal(format!("failed to measure used_nonces: {e}")))
+}
@@ -0,0 +1,139 @@
+//! Ed25519-signed join tickets: what a player shows a game server to connect.
+//!
+//! A ticket is `<payload>.<signature>`: the base64url (unpadded) JSON of
+//! [`JoinTicketClaims`], then the base64url ed25519 signature of the payload
+//! string's bytes. Game servers verify it offline against the public key served
+//! at `GET /v1/join-tickets/public-key`; [`verify_join_ticket`] is the reference.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL, Engine as _};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signature, Signer},
+};
+
+use crate::config::Config;
+
+pub const JOIN_TICKET_VERSION: u8 = 1;
+
+/// What a ticket grants: `wallet` may join `match_id` on `server_id` until `expires_at`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JoinTicketClaims {
+    pub v: u8,
+    pub wallet: String,
+    pub match_id: i64,
+    pub server_id: String,
+    /// Unix seconds.
+    pub issued_at: i64,
+    /// Unix seconds.
+    pub expires_at: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct IssuedJoinTicket {
+    pub ticket: String,
+    pub expires_at: i64,
+}
+
+pub struct JoinTicketIssuer {
+    keypair: Keypair,
+    ttl_seconds: i64,
+}
+
+impl JoinTicketIssuer {
+    pub fn new(keypair: Keypair, ttl_seconds: i64) -> Self {
+        Self {
+            keypair,
+            ttl_seconds,
+        }
+    }
+
+    /// From `JOIN_TICKET_KEYPAIR_PATH`, which is required unless `mock_chain`:
+    /// against the in-memory chain a key is generated for this process instead.
+    pub fn from_config(config: &Config, mock_chain: bool) -> Result<Arc<Self>> {
+        let keypair = match config.join_ticket_keypair_path.as_deref() {
+            Some(path) => {
+                let keypair = read_keypair_file(path)
+                    .map_err(|e| anyhow!("failed to read JOIN_TICKET_KEYPAIR_PATH {path}: {e}"))?;
+                if config.authority(&keypair.pubkey().to_string()).is_some() {
+                    bail!("JOIN_TICKET_KEYPAIR_PATH must not be an authority keypair");
+                }
+                keypair
+            }
+            None if mock_chain => {
+                tracing::warn!(
+                    "JOIN_TICKET_KEYPAIR_PATH is not set; signing join tickets with a key that lasts until restart"
+                );
+                Keypair::new()
+            }
+            None => bail!(
+                "JOIN_TICKET_KEYPAIR_PATH is required; game servers verify join tickets with its public key"
+            ),
+        };
+        Ok(Arc::new(Self::new(keypair, config.join_ticket_ttl_seconds)))
+    }
+
+    pub fn public_key(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    pub fn ttl_seconds(&self) -> i64 {
+        self.ttl_seconds
+    }
+
+    pub fn issue(&self, wallet: &str, match_id: i64, server_id: &str) -> IssuedJoinTicket {
+        let issued_at = Utc::now().timestamp();
+        self.sign(&JoinTicketClaims {
+            v: JOIN_TICKET_VERSION,
+            wallet: wallet.to_string(),
+            match_id,
+            server_id: server_id.to_string(),
+            issued_at,
+            expires_at: issued_at + self.ttl_seconds,
+        })
+    }
+
+    pub fn sign(&self, claims: &JoinTicketClaims) -> IssuedJoinTicket {
+        let payload = BASE64_URL
+            .encode(serde_json::to_vec(claims).expect("join ticket claims always serialize"));
+        let signature = self.keypair.sign_message(payload.as_bytes());
+        IssuedJoinTicket {
+            ticket: format!("{payload}.{}", BASE64_URL.encode(signature.as_ref())),
+            expires_at: claims.expires_at,
+        }
+    }
+}
+
+/// Checks a ticket's signature against `public_key` and that it has not expired
+/// at `now` (unix seconds). The caller still checks the claims are for itself.
+pub fn verify_join_ticket(ticket: &str, public_key: &Pubkey, now: i64) -> Result<JoinTicketClaims> {
+    let (payload, signature) = ticket
+        .split_once('.')
+        .context("join ticket is not <payload>.<signature>")?;
+    let signature = BASE64_URL
+        .decode(signature)
+        .context("join ticket signature is not base64url")?;
+    let signature =
+        Signature::try_from(signature.as_slice()).map_err(|_| anyhow!("bad signature length"))?;
+    if !signature.verify(public_key.as_ref(), payload.as_bytes()) {
+        bail!("join ticket signature does not verify");
+    }
+
+    let claims: JoinTicketClaims = serde_json::from_slice(
+        &BASE64_URL
+            .decode(payload)
+            .context("join ticket payload is not base64url")?,
+    )
+    .context("join ticket payload is not valid claims")?;
+    if claims.v != JOIN_TICKET_VERSION {
+        bail!("unsupported join ticket version {}", claims.v);
+    }
+    if now >= claims.expires_at {
+        bail!("join ticket expired at {}", claims.expires_at);
+    }
+    Ok(claims)
+}
@@ -0,0 +1,37 @@
+pub mod api;
+pub mod app_state;
+pub mod config;
+pub mod db;
+pub mod error;
+pub mod join_ticket;
+pub mod metrics;
+pub mod models;
+pub mod preflight;
+pub mod reason_policy;
+pub mod shutdown;
+pub mod solana;
+pub mod telemetry;
+pub mod worker;
+
+use axum::{middleware, Router};
+use tower_http::{
+    cors::CorsLayer,
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+
+use crate::app_state::AppState;
+
+pub fn build_router(state: AppState) -> Router {
+    Router::new()
+        .merge(api::health::probe_router())
+        .nest("/v1", api::router())
+        .with_state(state)
+        .layer(middleware::from_fn(metrics::track_http))
+        .layer(TraceLayer::new_for_http().make_span_with(telemetry::request_span))
+        // Outermost so the trace span sees the id: keeps a caller's `x-request-id`,
+        // else generates one, and echoes it in the response.
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        .layer(CorsLayer::permissive())
+}
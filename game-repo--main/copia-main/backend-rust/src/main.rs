@@ -1,59 +1,250 @@
-mod api;
-mod app_state;
-mod config;
-mod db;
-mod error;
-mod models;
-mod solana;
-mod worker;
+use std::{net::SocketAddr, str::FromStr, sync::Arc, time::Duration};
 
-use std::net::SocketAddr;
+use anyhow::{bail, Context};
+use axum::http::Method;
+use solana_sdk::{pubkey::Pubkey, signature::read_keypair_file};
+use sqlx::{postgres::PgPoolOptions, PgPool};
 
-use axum::Router;
-use sqlx::postgres::PgPoolOptions;
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use backend_rust::{
+    api::request_signing::RequestSigner,
+    app_state::AppState,
+    build_router,
+    config::{self, Config},
+    db, preflight,
+    solana::{
+        fake_chain::FakeChain,
+        gateway::ChainGateway,
+        idl::Idl,
+        keystore, remote_signer,
+        signer::{load_signer, SignerConfig},
+    },
+    telemetry,
+    worker::{self, finalizer::FinalizerSigners},
+};
 
-use crate::app_state::AppState;
-use crate::config::Config;
+/// Lamports airdropped to the finalizer's keys in `--mock-chain` mode.
+const MOCK_SIGNER_LAMPORTS: u64 = 1_000_000_000_000;
+/// Extra time past `SHUTDOWN_GRACE_MS` for workers to finish the DB write they are
+/// in, e.g. releasing a job lock, before they are aborted.
+const SHUTDOWN_DRAIN_MARGIN: Duration = Duration::from_secs(5);
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv().ok();
-    init_tracing();
+    let telemetry = telemetry::init()?;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mock_chain = args.iter().any(|arg| arg == "--mock-chain");
+    match args.first().map(String::as_str) {
+        Some("encrypt-keystore") => return encrypt_keystore(&args[1..]),
+        Some("signer-server") => return signer_server(&args[1..]).await,
+        Some("check") => return check(mock_chain).await,
+        Some("sign-request") => return sign_request(&args[1..]),
+        _ => {}
+    }
 
     let config = Config::from_env()?;
 
-    let pool = PgPoolOptions::new()
-        .max_connections(10)
-        .connect(&config.database_url)
-        .await?;
+    let pool = connect(&config).await?;
+    db::MIGRATOR.run(&pool).await?;
+    let state = build_state(config.clone(), pool, mock_chain)?;
 
-    sqlx::migrate!("./migrations").run(&pool).await?;
+    // Refuse to run half-broken, e.g. accepting results the finalizer can never settle.
+    let report = preflight::run(&state).await;
+    report.log();
+    if !report.passed() {
+        bail!("preflight failed; run `backend-rust check` for the full report");
+    }
 
-    let state = AppState::new(config.clone(), pool);
-    worker::spawn_workers(state.clone());
+    let workers = worker::spawn_workers(state.clone());
 
-    let app = Router::new()
-        .nest("/v1", api::router())
-        .with_state(state)
-        .layer(TraceLayer::new_for_http())
-        .layer(CorsLayer::permissive());
+    let app = build_router(state.clone());
 
     let addr: SocketAddr = config.app_bind_addr.parse()?;
     let listener = tokio::net::TcpListener::bind(addr).await?;
     tracing::info!("backend listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    // Stops accepting connections on the signal and returns once in-flight
+    // requests have been answered.
+    let grace = Duration::from_millis(config.shutdown_grace_ms);
+    let shutdown = state.shutdown.clone();
+    let served = axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            tracing::info!(grace_ms = grace.as_millis() as u64, "shutting down");
+            shutdown.trigger(grace);
+        })
+        .await;
+    // Also reached when serving failed; the workers must stop either way.
+    state.shutdown.trigger(grace);
+
+    drain_workers(workers, grace + SHUTDOWN_DRAIN_MARGIN).await;
+    state.pool.close().await;
+    tracing::info!("shutdown complete");
+    telemetry.shutdown();
+    Ok(served?)
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("failed to listen for ctrl-c: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Waits for every worker to stop, aborting those still running after `limit`.
+async fn drain_workers(workers: Vec<tokio::task::JoinHandle<()>>, limit: Duration) {
+    let deadline = tokio::time::Instant::now() + limit;
+    for mut worker in workers {
+        match tokio::time::timeout_at(deadline, &mut worker).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => tracing::error!("worker ended abnormally: {e}"),
+            Err(_) => {
+                tracing::warn!("worker did not stop in time; aborting it");
+                worker.abort();
+            }
+        }
+    }
+}
+
+/// `check [--mock-chain]`: runs the startup preflight without migrating or serving,
+/// and exits non-zero if a critical check fails.
+async fn check(mock_chain: bool) -> anyhow::Result<()> {
+    let config = Config::from_env()?;
+    let pool = connect(&config).await?;
+    let state = build_state(config, pool, mock_chain)?;
+
+    let report = preflight::run(&state).await;
+    print!("{}", report.render());
+    let failed = report.critical_failures().count();
+    if failed > 0 {
+        bail!("{failed} critical preflight check(s) failed");
+    }
     Ok(())
 }
 
-fn init_tracing() {
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "backend_rust=debug,axum=info,sqlx=warn".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+async fn connect(config: &Config) -> anyhow::Result<PgPool> {
+    Ok(PgPoolOptions::new()
+        .max_connections(10)
+        .connect(&config.database_url)
+        .await?)
+}
+
+/// Live RPC pools, or with `--mock-chain` one in-memory chain per environment.
+fn build_state(config: Config, pool: PgPool, mock_chain: bool) -> anyhow::Result<AppState> {
+    if !mock_chain {
+        return AppState::new(config, pool);
+    }
+    let mut chains = Vec::new();
+    for env in &config.environments {
+        // Each fake implements the program its environment is configured for.
+        let chain = Arc::new(FakeChain::with_idl(
+            Pubkey::from_str(&env.program_id)?,
+            Idl::load(env.program_idl_path.as_deref())?,
+        ));
+        if let Ok(signers) = FinalizerSigners::load(&config, env) {
+            for signer in signers.authorities.iter().chain(&signers.fee_payer) {
+                chain.airdrop(&signer.pubkey(), MOCK_SIGNER_LAMPORTS);
+            }
+        }
+        chains.push(chain);
+    }
+    // Seeds go to the default environment.
+    if let Some(path) = config.mock_chain_seed_path.as_deref() {
+        let authority = Pubkey::from_str(&config.default_environment().authority_pubkey)?;
+        let games = chains[0].seed_games_from_file(path, &authority)?;
+        tracing::info!(count = games.len(), "seeded mock chain games");
+    }
+    tracing::warn!("running against the in-memory mock chain; nothing is sent to Solana");
+    let mut chains = chains.into_iter();
+    AppState::with_chains(config, pool, |_| {
+        chains.next().expect("one mock chain per environment") as Arc<dyn ChainGateway>
+    })
+}
+
+/// `encrypt-keystore <keypair.json> <keystore.json>`, with the passphrase from
+/// `AUTHORITY_KEYSTORE_PASSPHRASE`/`AUTHORITY_KEYSTORE_PASSPHRASE_FILE`.
+fn encrypt_keystore(args: &[String]) -> anyhow::Result<()> {
+    let [input, output] = args else {
+        bail!("usage: backend-rust encrypt-keystore <keypair.json> <keystore.json>");
+    };
+    let keypair =
+        read_keypair_file(input).map_err(|e| anyhow::anyhow!("failed to read {input}: {e}"))?;
+    let passphrase = config::keystore_passphrase_from_env()?;
+    let sealed = keystore::encrypt_keypair(&keypair, &passphrase)?;
+    keystore::write_keystore(output, &sealed)?;
+    tracing::info!(pubkey = %sealed.pubkey, path = %output, "keystore written");
+    Ok(())
+}
+
+/// `sign-request <METHOD> <path?query> [body-file]`: prints the HMAC headers of one
+/// internal request as curl `-H` flags, signed with `SIGNING_SECRET` and, when set,
+/// `SIGNING_KEY_ID` (a server credential's key id, or `admin`). Send the body file
+/// unchanged, e.g. with `--data-binary @file`.
+fn sign_request(args: &[String]) -> anyhow::Result<()> {
+    let (method, target, body) = match args {
+        [method, target] => (method, target, Vec::new()),
+        [method, target, body_file] => (
+            method,
+            target,
+            std::fs::read(body_file).with_context(|| format!("failed to read {body_file}"))?,
+        ),
+        _ => bail!("usage: backend-rust sign-request <METHOD> <path?query> [body-file]"),
+    };
+    let method = Method::from_bytes(method.to_uppercase().as_bytes())
+        .with_context(|| format!("invalid method {method}"))?;
+    let secret = std::env::var("SIGNING_SECRET").context("missing env var SIGNING_SECRET")?;
+    let key_id = std::env::var("SIGNING_KEY_ID")
+        .ok()
+        .filter(|key_id| !key_id.trim().is_empty());
+
+    for (name, value) in RequestSigner::new(secret, key_id).sign(&method, target, &body) {
+        println!("-H '{name}: {value}'");
+    }
+    Ok(())
+}
+
+/// `signer-server <host:port | unix:/path>`: holds the authority key from
+/// `AUTHORITY_KEYSTORE_PATH` or `AUTHORITY_KEYPAIR_PATH` and signs only settle and
+/// refund instructions of `PROGRAM_ID`, decoded with `PROGRAM_IDL_PATH`.
+async fn signer_server(args: &[String]) -> anyhow::Result<()> {
+    let [listen] = args else {
+        bail!("usage: backend-rust signer-server <host:port | unix:/path>");
+    };
+    let signer_config = config::authority_signer_from_env()?;
+    if matches!(signer_config, SignerConfig::Remote { .. }) {
+        bail!("signer-server needs a local key; unset AUTHORITY_REMOTE_SIGNER");
+    }
+    let authority = Pubkey::from_str(
+        &std::env::var("AUTHORITY_PUBKEY").context("missing env var AUTHORITY_PUBKEY")?,
+    )?;
+    let program_id =
+        Pubkey::from_str(&std::env::var("PROGRAM_ID").context("missing env var PROGRAM_ID")?)?;
+
+    let idl = Idl::load(std::env::var("PROGRAM_IDL_PATH").ok().as_deref())?;
+
+    let signer = load_signer(&signer_config, &authority)?;
+    remote_signer::serve(listen, signer, program_id, idl).await
 }
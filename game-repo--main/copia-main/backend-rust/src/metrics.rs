@@ -0,0 +1,224 @@
+//! Prometheus metrics, served at `/metrics`.
+//!
+//! Everything registers in one process-wide registry; the instrumented modules
+//! update it through [`metrics()`].
+
+use std::{sync::LazyLock, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+const CONFIRMATION_BUCKETS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 900.0];
+
+pub struct Metrics {
+    registry: Registry,
+    /// `method`, `route`, `status`.
+    pub http_requests: IntCounterVec,
+    /// `method`, `route`.
+    pub http_request_duration: HistogramVec,
+    /// Accepted internal HMAC requests; `version`: `1` or `2`.
+    pub hmac_requests: IntCounterVec,
+    /// `reason`: `no_secret`, `missing_header`, `bad_nonce`, `bad_timestamp`,
+    /// `clock_skew`, `bad_signature`, `replayed_nonce`, `shared_secret_disabled`,
+    /// `unknown_key`, `revoked_key`, `expired_key`, `bad_version` or `legacy_signature`.
+    pub hmac_rejections: IntCounterVec,
+    /// `event`: a `security_audit_log` event, e.g. `finalize_not_assigned_server`.
+    pub security_audit_events: IntCounterVec,
+    /// `to`: the `chain_jobs.status` a job moved to.
+    pub chain_job_transitions: IntCounterVec,
+    pub chain_job_attempts: IntCounter,
+    /// From enqueue to confirmed.
+    pub chain_job_confirmation_seconds: HistogramVec,
+    /// `class`: where a job attempt failed, e.g. `send` or `confirmation`.
+    pub chain_job_failures: IntCounterVec,
+    /// `method`.
+    pub rpc_call_duration: HistogramVec,
+    /// `method`, `kind`: `transport`, `rejected` or `timeout`.
+    pub rpc_call_errors: IntCounterVec,
+    /// `status`; refreshed from `server_pool` on every scrape.
+    pub server_pool_servers: IntGaugeVec,
+    pub lamports_settled: IntCounter,
+    pub lamports_refunded: IntCounter,
+    /// Refreshed by the nonce pruner after every pass.
+    pub used_nonces_rows: IntGauge,
+    pub used_nonces_bytes: IntGauge,
+    pub used_nonces_pruned: IntCounter,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let counter_vec = |name: &str, help: &str, labels: &[&str]| {
+            let metric = IntCounterVec::new(Opts::new(name, help), labels).unwrap();
+            registry.register(Box::new(metric.clone())).unwrap();
+            metric
+        };
+        let counter = |name: &str, help: &str| {
+            let metric = IntCounter::new(name, help).unwrap();
+            registry.register(Box::new(metric.clone())).unwrap();
+            metric
+        };
+        let gauge = |name: &str, help: &str| {
+            let metric = IntGauge::new(name, help).unwrap();
+            registry.register(Box::new(metric.clone())).unwrap();
+            metric
+        };
+        let histogram_vec = |opts: HistogramOpts, labels: &[&str]| {
+            let metric = HistogramVec::new(opts, labels).unwrap();
+            registry.register(Box::new(metric.clone())).unwrap();
+            metric
+        };
+
+        let http_requests = counter_vec(
+            "http_requests_total",
+            "HTTP requests by route and response status.",
+            &["method", "route", "status"],
+        );
+        let http_request_duration = histogram_vec(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route.",
+            ),
+            &["method", "route"],
+        );
+        let hmac_requests = counter_vec(
+            "hmac_requests_total",
+            "Internal HMAC requests accepted, by signature version.",
+            &["version"],
+        );
+        let hmac_rejections = counter_vec(
+            "hmac_rejections_total",
+            "Internal HMAC requests rejected, by reason.",
+            &["reason"],
+        );
+        let security_audit_events = counter_vec(
+            "security_audit_events_total",
+            "Denied or overridden internal requests written to the security audit log, by event.",
+            &["event"],
+        );
+        let chain_job_transitions = counter_vec(
+            "chain_job_transitions_total",
+            "Chain job status changes, by new status.",
+            &["to"],
+        );
+        let chain_job_attempts = counter(
+            "chain_job_attempts_total",
+            "Chain job attempts counted against the retry limit.",
+        );
+        let chain_job_confirmation_seconds = histogram_vec(
+            HistogramOpts::new(
+                "chain_job_confirmation_seconds",
+                "Time from enqueueing a chain job to its confirmation.",
+            )
+            .buckets(CONFIRMATION_BUCKETS.to_vec()),
+            &["job_type"],
+        );
+        let chain_job_failures = counter_vec(
+            "chain_job_failures_total",
+            "Failed chain job attempts, by error class.",
+            &["class"],
+        );
+        let rpc_call_duration = histogram_vec(
+            HistogramOpts::new(
+                "rpc_call_duration_seconds",
+                "Solana RPC call latency per endpoint attempt.",
+            ),
+            &["method"],
+        );
+        let rpc_call_errors = counter_vec(
+            "rpc_call_errors_total",
+            "Failed Solana RPC endpoint attempts.",
+            &["method", "kind"],
+        );
+        let server_pool_servers = IntGaugeVec::new(
+            Opts::new("server_pool_servers", "Game servers by status."),
+            &["status"],
+        )
+        .unwrap();
+        registry
+            .register(Box::new(server_pool_servers.clone()))
+            .unwrap();
+        let lamports_settled = counter(
+            "lamports_settled_total",
+            "Pot lamports of games settled to a winner.",
+        );
+        let lamports_refunded = counter(
+            "lamports_refunded_total",
+            "Entry lamports refunded to players.",
+        );
+        let used_nonces_rows = gauge("used_nonces_rows", "Rows in used_nonces.");
+        let used_nonces_bytes = gauge(
+            "used_nonces_bytes",
+            "Size of used_nonces on disk, indexes included.",
+        );
+        let used_nonces_pruned = counter(
+            "used_nonces_pruned_total",
+            "Expired HMAC nonces deleted from used_nonces.",
+        );
+
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            hmac_requests,
+            hmac_rejections,
+            security_audit_events,
+            chain_job_transitions,
+            chain_job_attempts,
+            chain_job_confirmation_seconds,
+            chain_job_failures,
+            rpc_call_duration,
+            rpc_call_errors,
+            server_pool_servers,
+            lamports_settled,
+            lamports_refunded,
+            used_nonces_rows,
+            used_nonces_bytes,
+            used_nonces_pruned,
+        }
+    }
+
+    /// The registry in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("text encoding of gathered metrics");
+        String::from_utf8(buffer).expect("prometheus text format is UTF-8")
+    }
+}
+
+/// Counts and times every request by its route template, not its raw path, so
+/// ids in paths do not explode label cardinality.
+pub async fn track_http(request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "unmatched".to_string(), |path| path.as_str().to_string());
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    let m = metrics();
+    m.http_request_duration
+        .with_label_values(&[&method, &route])
+        .observe(started.elapsed().as_secs_f64());
+    m.http_requests
+        .with_label_values(&[&method, &route, response.status().as_str()])
+        .inc();
+    response
+}
@@ -1,6 +1,11 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
-use crate::models::enums::{ChainJobStatus, ChainJobType, MatchStatus, ResultOutcome};
+use crate::{
+    models::enums::{ChainJobStatus, ChainJobType, MatchStatus, ResultOutcome},
+    worker::liveness::FinalizerState,
+};
 
 // ── Finalize ────────────────────────────────────────────
 
@@ -12,24 +17,85 @@ pub struct FinalizeRequest {
     pub reason_code: String,
     pub reason_detail: Option<String>,
     pub idempotency_key: String,
+    /// Program environment of the game; the default program when omitted.
+    pub program_id: Option<String>,
+    /// Required when finalizing with the admin key instead of the assigned
+    /// server's; recorded in the security audit log.
+    pub override_reason: Option<String>,
+    /// For `broken` results: the players connected when the match broke, which
+    /// reasons mapped to `settle_to_present` pay out to.
+    pub present_wallets: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct FinalizeResponse {
     pub match_id: String,
     pub match_status: MatchStatus,
-    pub finalization_action: ChainJobType,
-    pub chain_job_status: ChainJobStatus,
+    /// Absent while the result is held for review.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finalization_action: Option<ChainJobType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_job_status: Option<ChainJobStatus>,
+}
+
+// ── Kickoff ─────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct KickoffRequest {
+    /// Players connected to the game server; one is enough to record it.
+    pub present_wallets: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KickoffResponse {
+    pub match_id: String,
+    pub match_status: MatchStatus,
+    /// Unix seconds, once both players have connected; the first kickoff's when
+    /// repeated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kicked_off_at: Option<i64>,
+    /// Every player reported connected so far, player1 first.
+    pub present_wallets: Vec<String>,
 }
 
 // ── Challenges ──────────────────────────────────────────
 
+#[derive(Debug, Deserialize)]
+pub struct ReserveMatchRequest {
+    pub creator_pubkey: String,
+    pub entry_amount: u64,
+    /// Program environment of the game; the default program when omitted.
+    pub program_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReserveMatchResponse {
+    pub match_id: i64,
+    pub join_code: String,
+    pub program_id: String,
+    pub authority_pubkey: String,
+    pub game_pda: String,
+    pub vault_pda: String,
+    pub match_status: MatchStatus,
+    /// Unix seconds; the reservation is dropped if `create_game` has not landed by then.
+    pub expires_at: i64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct RegisterChallengeRequest {
     pub game_pda: String,
     pub creator_pubkey: String,
     pub entry_amount: u64,
     pub match_id: u64,
+    /// Program environment of the game; the default program when omitted.
+    pub program_id: Option<String>,
+    /// Unix seconds, signed with `game_pda` by `creator_pubkey` in `signature`.
+    /// Without the proof the request gets no join ticket.
+    pub timestamp: Option<i64>,
+    /// Fresh for every request; a proof is accepted once.
+    pub nonce: Option<String>,
+    /// Base58 signature of `join-ticket:<game_pda>:<timestamp>:<nonce>` by the creator.
+    pub signature: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -52,11 +118,24 @@ pub struct RegisterChallengeResponse {
     pub ok: bool,
     pub server_ip: String,
     pub server_port: i32,
+    /// For the creator, if it sent a wallet proof; the game server refuses
+    /// connections without one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub join_ticket: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub join_ticket_expires_at: Option<i64>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AcceptChallengeRequest {
     pub acceptor_pubkey: String,
+    /// Unix seconds, signed with the game PDA by `acceptor_pubkey` in `signature`.
+    /// Without the proof the request gets no join ticket.
+    pub timestamp: Option<i64>,
+    /// Fresh for every request; a proof is accepted once.
+    pub nonce: Option<String>,
+    /// Base58 signature of `join-ticket:<game_pda>:<timestamp>:<nonce>` by the acceptor.
+    pub signature: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -64,6 +143,11 @@ pub struct AcceptChallengeResponse {
     pub server_ip: String,
     pub server_port: i32,
     pub status: String,
+    /// For the acceptor, if it sent a wallet proof.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub join_ticket: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub join_ticket_expires_at: Option<i64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -73,6 +157,87 @@ pub struct ChallengeStatusResponse {
     pub server_ip: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub server_port: Option<i32>,
+    /// Unix seconds; set once the game server reports both players connected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kicked_off_at: Option<i64>,
+}
+
+// ── Join tickets ────────────────────────────────────────
+
+/// Proof that the caller holds `wallet`: its ed25519 signature (base58) of
+/// `join-ticket:<game_pda>:<timestamp>:<nonce>`, with `timestamp` in unix seconds.
+#[derive(Debug, Deserialize)]
+pub struct RefreshJoinTicketRequest {
+    pub wallet: String,
+    pub timestamp: i64,
+    pub nonce: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JoinTicketResponse {
+    pub join_ticket: String,
+    pub expires_at: i64,
+    pub match_id: i64,
+    pub server_id: String,
+    pub server_ip: String,
+    pub server_port: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JoinTicketPublicKeyResponse {
+    pub algorithm: &'static str,
+    /// Base58, like a Solana pubkey.
+    pub public_key: String,
+    pub ttl_seconds: i64,
+}
+
+// ── Transactions ────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+pub struct CreateGameTxRequest {
+    pub match_id: i64,
+    /// Make the backend sponsor the fee payer; relay the signed transaction to use it.
+    #[serde(default)]
+    pub sponsored: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JoinGameTxRequest {
+    pub game_pda: String,
+    pub player_pubkey: String,
+    #[serde(default)]
+    pub sponsored: bool,
+    /// Program environment of the game; the default program when omitted.
+    pub program_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnsignedTxResponse {
+    /// Base64 of the wire-format transaction, with empty signature slots for the wallet.
+    pub transaction: String,
+    pub recent_blockhash: String,
+    pub fee_payer: String,
+    pub match_id: i64,
+    pub game_pda: String,
+    pub vault_pda: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RelayTxRequest {
+    /// Base64 of the wallet-signed transaction returned by the unsigned-tx endpoints.
+    pub transaction: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RelayTxResponse {
+    pub signature: String,
+    /// `create_game` or `join_game`.
+    pub instruction: String,
+    pub match_id: i64,
+    pub game_pda: String,
+    /// Whether the backend sponsor paid the fee.
+    pub sponsored: bool,
 }
 
 // ── Server Pool ─────────────────────────────────────────
@@ -89,3 +254,79 @@ pub struct RegisterServerRequest {
 pub struct HeartbeatRequest {
     pub status: String,
 }
+
+// ── Admin ───────────────────────────────────────────
+
+#[derive(Debug, Default, Deserialize)]
+pub struct IssueCredentialRequest {
+    /// Lifetime of the key; it never expires when omitted.
+    pub expires_in_seconds: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssuedCredentialResponse {
+    pub key_id: String,
+    pub server_id: String,
+    /// Hex HMAC key; returned only here, when the key is issued.
+    pub secret: String,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerCredentialResponse {
+    pub key_id: String,
+    pub server_id: String,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+    /// Neither revoked nor expired.
+    pub active: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ServerCredentialsResponse {
+    pub credentials: Vec<ServerCredentialResponse>,
+}
+
+// ── Health ──────────────────────────────────────────────
+
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    /// `ok`, or `unhealthy` when any check failed.
+    pub status: &'static str,
+    pub checks: Vec<HealthCheck>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthCheck {
+    pub name: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FinalizerStatusResponse {
+    pub environments: Vec<FinalizerEnvStatus>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FinalizerEnvStatus {
+    pub env: String,
+    pub program_id: String,
+    /// `starting`, `underfunded`, `running` or `disabled`.
+    pub state: FinalizerState,
+    pub stalled: bool,
+    /// Unix seconds of the last finalizer loop pass.
+    pub last_tick_at: Option<i64>,
+    /// Job count per `chain_jobs.status`.
+    pub queue: BTreeMap<String, i64>,
+    pub oldest_due_age_seconds: Option<i64>,
+    /// Last error of the finalizer loop itself (DB, RPC, disabled signers).
+    pub last_error: Option<String>,
+    pub last_error_at: Option<i64>,
+    /// Most recent `chain_jobs.last_error` of this program.
+    pub last_job_error: Option<String>,
+    pub last_job_error_at: Option<i64>,
+}
@@ -8,6 +8,8 @@ pub enum MatchStatus {
     JoinedOnChain,
     InProgress,
     ResultPendingFinalize,
+    /// A `broken` result whose reason is held for an operator.
+    HeldForReview,
     Finalizing,
     Settled,
     Refunded,
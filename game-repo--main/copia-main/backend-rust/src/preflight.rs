@@ -0,0 +1,216 @@
+//! Startup checks that the backend can do its job before it takes traffic.
+//!
+//! `main` runs them after migrating and refuses to start when a critical one
+//! fails; `backend-rust check` runs the same checks and prints the report.
+
+use std::collections::BTreeSet;
+
+use anyhow::{bail, ensure, Context, Result};
+use solana_sdk::hash::Hash;
+use sqlx::PgPool;
+
+use crate::{
+    app_state::{AppState, ProgramEnv},
+    db,
+    solana::{
+        fake_chain,
+        instructions::{verify_deployed_program, verify_idl},
+    },
+    worker::{balance_monitor, finalizer::FinalizerSigners},
+};
+
+const MAINNET_GENESIS: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+const DEVNET_GENESIS: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+const TESTNET_GENESIS: &str = "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY";
+
+#[derive(Debug)]
+pub struct CheckResult {
+    /// e.g. `database migrations` or `devnet: rpc`.
+    pub name: String,
+    /// A failed critical check stops startup; others are only reported.
+    pub critical: bool,
+    /// What was found on success, the reason on failure.
+    pub outcome: Result<String, String>,
+}
+
+#[derive(Debug, Default)]
+pub struct PreflightReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl PreflightReport {
+    fn record(&mut self, name: impl Into<String>, critical: bool, outcome: Result<String>) {
+        self.checks.push(CheckResult {
+            name: name.into(),
+            critical,
+            outcome: outcome.map_err(|e| format!("{e:#}")),
+        });
+    }
+
+    pub fn critical_failures(&self) -> impl Iterator<Item = &CheckResult> {
+        self.checks
+            .iter()
+            .filter(|check| check.critical && check.outcome.is_err())
+    }
+
+    pub fn passed(&self) -> bool {
+        self.critical_failures().next().is_none()
+    }
+
+    pub fn check(&self, name: &str) -> Option<&CheckResult> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+
+    pub fn log(&self) {
+        for check in &self.checks {
+            match &check.outcome {
+                Ok(detail) => tracing::info!(check = %check.name, "preflight ok: {detail}"),
+                Err(reason) if check.critical => {
+                    tracing::error!(check = %check.name, "preflight failed: {reason}")
+                }
+                Err(reason) => tracing::warn!(check = %check.name, "preflight warning: {reason}"),
+            }
+        }
+    }
+
+    /// One line per check, for `backend-rust check`.
+    pub fn render(&self) -> String {
+        self.checks
+            .iter()
+            .map(|check| match &check.outcome {
+                Ok(detail) => format!("ok    {}: {detail}\n", check.name),
+                Err(reason) if check.critical => format!("FAIL  {}: {reason}\n", check.name),
+                Err(reason) => format!("warn  {}: {reason}\n", check.name),
+            })
+            .collect()
+    }
+}
+
+/// Runs every check; never fails itself, each problem lands in the report.
+pub async fn run(state: &AppState) -> PreflightReport {
+    let mut report = PreflightReport::default();
+
+    report.record(
+        "hmac secret",
+        true,
+        if !state.config.shared_hmac_secret_enabled {
+            Ok("shared secret disabled; servers sign with their own credentials".into())
+        } else if state.config.internal_hmac_secret.trim().is_empty() {
+            Err(anyhow::anyhow!(
+                "INTERNAL_HMAC_SECRET is empty; set it or SHARED_HMAC_SECRET_ENABLED=false"
+            ))
+        } else {
+            Ok("set".into())
+        },
+    );
+    report.record(
+        "database migrations",
+        true,
+        check_migrations(&state.pool).await,
+    );
+
+    for env in state.envs.iter() {
+        check_environment(state, env, &mut report).await;
+    }
+    report
+}
+
+async fn check_migrations(pool: &PgPool) -> Result<String> {
+    let applied: BTreeSet<i64> =
+        sqlx::query_scalar("select version from _sqlx_migrations where success")
+            .fetch_all(pool)
+            .await
+            .context("failed to read applied migrations")?
+            .into_iter()
+            .collect();
+    let known: BTreeSet<i64> = db::MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| m.version)
+        .collect();
+
+    let pending: Vec<String> = known.difference(&applied).map(i64::to_string).collect();
+    ensure!(
+        pending.is_empty(),
+        "migrations not applied: {}",
+        pending.join(", ")
+    );
+    let unknown: Vec<String> = applied.difference(&known).map(i64::to_string).collect();
+    ensure!(
+        unknown.is_empty(),
+        "database has migrations this build does not know: {}",
+        unknown.join(", ")
+    );
+    Ok(format!("{} applied", applied.len()))
+}
+
+async fn check_environment(state: &AppState, env: &ProgramEnv, report: &mut PreflightReport) {
+    let name = &env.config.name;
+
+    let rpc = env
+        .chain
+        .get_genesis_hash()
+        .await
+        .context("RPC is unreachable")
+        .map(|genesis| format!("cluster {}", cluster_name(&genesis)));
+    let reachable = rpc.is_ok();
+    report.record(format!("{name}: rpc"), true, rpc);
+    if !reachable {
+        return;
+    }
+
+    let program = async {
+        verify_idl(&env.idl, &env.program_id)?;
+        verify_deployed_program(env.chain.as_ref(), &env.idl, &env.program_id).await?;
+        Ok(format!("{} deployed as {}", env.program_id, env.idl.name))
+    }
+    .await;
+    report.record(format!("{name}: program"), true, program);
+
+    let signers = FinalizerSigners::load(&state.config, &env.config);
+    let loaded = signers.as_ref().map(|signers| {
+        let authorities: Vec<String> = signers
+            .authorities
+            .iter()
+            .map(|signer| signer.pubkey().to_string())
+            .collect();
+        format!("authorities {}", authorities.join(","))
+    });
+    report.record(
+        format!("{name}: signers"),
+        true,
+        loaded.map_err(|e| anyhow::anyhow!("{e:#}")),
+    );
+    let Ok(signers) = signers else {
+        return;
+    };
+
+    let watched = balance_monitor::watched_accounts(&state.config, &signers);
+    let balances = async {
+        let short = balance_monitor::underfunded(env.chain.as_ref(), &watched).await?;
+        if let Some(s) = short.first() {
+            bail!(
+                "{} {} holds {} lamports, needs {}",
+                s.role,
+                s.pubkey,
+                s.balance,
+                s.min_balance
+            );
+        }
+        Ok(format!("{} account(s) funded", watched.len()))
+    }
+    .await;
+    report.record(format!("{name}: balances"), true, balances);
+}
+
+fn cluster_name(genesis: &Hash) -> String {
+    if *genesis == fake_chain::GENESIS_HASH {
+        return "mock".into();
+    }
+    match genesis.to_string().as_str() {
+        MAINNET_GENESIS => "mainnet-beta".into(),
+        DEVNET_GENESIS => "devnet".into(),
+        TESTNET_GENESIS => "testnet".into(),
+        other => format!("unknown (genesis {other})"),
+    }
+}
@@ -0,0 +1,125 @@
+//! What a `broken` result does, by its `reason_code`.
+//!
+//! Reasons the registry does not know get the fallback action, `refund` unless
+//! changed. The defaults can be changed, and reasons added, with
+//! `REASON_CODE_POLICIES`, e.g. `no_show=hold_for_review,lag_out=refund,*=hold_for_review`.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasonAction {
+    /// Pay the pot to the one player the server reports present, if the server
+    /// also reported it connected; refund otherwise, or when no second player joined.
+    SettleToPresent,
+    Refund,
+    /// Record the result without acting on it, for an operator to resolve with
+    /// an admin override.
+    HoldForReview,
+}
+
+impl FromStr for ReasonAction {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self> {
+        match raw {
+            "settle_to_present" => Ok(ReasonAction::SettleToPresent),
+            "refund" => Ok(ReasonAction::Refund),
+            "hold_for_review" => Ok(ReasonAction::HoldForReview),
+            other => Err(anyhow!(
+                "unknown reason action {other}; expected settle_to_present, refund or hold_for_review"
+            )),
+        }
+    }
+}
+
+const DEFAULT_ACTIONS: &[(&str, ReasonAction)] = &[
+    ("no_show", ReasonAction::SettleToPresent),
+    ("disconnect", ReasonAction::SettleToPresent),
+    ("rage_quit", ReasonAction::SettleToPresent),
+    ("server_crash", ReasonAction::Refund),
+    // Sent by the current Unity server build, which refunded every broken match.
+    ("disconnect_inconsistent_metadata", ReasonAction::Refund),
+    ("disconnect_unknown_winner", ReasonAction::Refund),
+    ("timer_end_draw", ReasonAction::Refund),
+    ("timer_end_missing_wallets", ReasonAction::Refund),
+    ("invalid_winner_metadata", ReasonAction::Refund),
+    ("invalid_winner_wallet", ReasonAction::Refund),
+    ("unspecified", ReasonAction::Refund),
+];
+
+/// The `reason` of a `REASON_CODE_POLICIES` entry that sets the fallback.
+const FALLBACK_CODE: &str = "*";
+
+/// The registry of `broken` reason codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReasonPolicy {
+    actions: BTreeMap<String, ReasonAction>,
+    /// For reasons not in `actions`. Never `settle_to_present`: a reason nobody
+    /// registered must not move the pot to one player.
+    fallback: ReasonAction,
+}
+
+impl Default for ReasonPolicy {
+    fn default() -> Self {
+        Self {
+            actions: DEFAULT_ACTIONS
+                .iter()
+                .map(|(code, action)| (code.to_string(), *action))
+                .collect(),
+            fallback: ReasonAction::Refund,
+        }
+    }
+}
+
+impl ReasonPolicy {
+    /// The defaults with `overrides` applied: comma-separated `reason=action` pairs,
+    /// where the reason `*` sets the fallback.
+    pub fn with_overrides(overrides: &str) -> Result<Self> {
+        let mut policy = Self::default();
+        for entry in overrides
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+        {
+            let Some((code, action)) = entry.split_once('=') else {
+                bail!("REASON_CODE_POLICIES entry {entry} is not reason=action");
+            };
+            let code = code.trim();
+            if code.is_empty() {
+                bail!("REASON_CODE_POLICIES entry {entry} has an empty reason");
+            }
+            let action = action.trim().parse()?;
+            if code == FALLBACK_CODE {
+                policy.set_fallback(action)?;
+            } else {
+                policy.set(code, action);
+            }
+        }
+        Ok(policy)
+    }
+
+    pub fn set(&mut self, code: &str, action: ReasonAction) {
+        self.actions.insert(code.to_string(), action);
+    }
+
+    pub fn set_fallback(&mut self, action: ReasonAction) -> Result<()> {
+        if action == ReasonAction::SettleToPresent {
+            bail!("the fallback for unregistered reasons must be refund or hold_for_review");
+        }
+        self.fallback = action;
+        Ok(())
+    }
+
+    pub fn is_registered(&self, code: &str) -> bool {
+        self.actions.contains_key(code)
+    }
+
+    /// The registered action for `code`, or the fallback.
+    pub fn action(&self, code: &str) -> ReasonAction {
+        self.actions.get(code).copied().unwrap_or(self.fallback)
+    }
+}
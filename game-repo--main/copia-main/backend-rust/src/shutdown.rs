@@ -0,0 +1,70 @@
+//! Coordinated shutdown of the server and the workers.
+//!
+//! `main` triggers it on SIGTERM/ctrl-c with a grace period. Workers stop at the
+//! next point where nothing is half-written: the finalizer never abandons a job
+//! between sending its transaction and recording the signature, and releases its
+//! lock if confirmation is still pending when the grace period runs out.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio::{sync::watch, time::Instant};
+
+#[derive(Clone)]
+pub struct Shutdown {
+    /// The deadline once triggered.
+    tx: Arc<watch::Sender<Option<Instant>>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self {
+            tx: Arc::new(watch::channel(None).0),
+        }
+    }
+}
+
+impl Shutdown {
+    /// Starts shutting down; in-flight work has `grace` to finish. Only the first
+    /// call sets the deadline.
+    pub fn trigger(&self, grace: Duration) {
+        self.tx.send_if_modified(|deadline| {
+            if deadline.is_some() {
+                return false;
+            }
+            *deadline = Some(Instant::now() + grace);
+            true
+        });
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.tx.borrow().is_some()
+    }
+
+    /// Resolves once shutdown is triggered.
+    pub async fn triggered(&self) {
+        self.deadline_instant().await;
+    }
+
+    /// Resolves when the grace period of a triggered shutdown is over.
+    pub async fn deadline(&self) {
+        tokio::time::sleep_until(self.deadline_instant().await).await;
+    }
+
+    /// Sleeps between worker passes. Returns `false`, early, when shutdown is
+    /// triggered and the worker should stop.
+    pub async fn sleep(&self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = self.triggered() => false,
+            _ = tokio::time::sleep(duration) => true,
+        }
+    }
+
+    async fn deadline_instant(&self) -> Instant {
+        let mut rx = self.tx.subscribe();
+        let deadline = *rx
+            .wait_for(Option::is_some)
+            .await
+            .expect("the sender lives as long as self");
+        deadline.expect("waited for Some")
+    }
+}
@@ -3,29 +3,24 @@
 use std::str::FromStr;
 
 use anyhow::{Context, Result};
-use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 
-use crate::solana::game_account::{decode_game_account, DecodedGameAccount};
+use crate::solana::{
+    game_account::{decode_game_account, DecodedGameAccount},
+    gateway::ChainGateway,
+    idl::Idl,
+};
 
 pub async fn fetch_and_decode_game_account(
-    rpc_url: &str,
-    program_id: &str,
-    game_pda: &str,
-) -> Result<DecodedGameAccount> {
-    let client = RpcClient::new(rpc_url.to_string());
-    fetch_and_decode_game_account_with_client(&client, program_id, game_pda).await
-}
-
-pub async fn fetch_and_decode_game_account_with_client(
-    client: &RpcClient,
+    chain: &dyn ChainGateway,
+    idl: &Idl,
     program_id: &str,
     game_pda: &str,
 ) -> Result<DecodedGameAccount> {
     let expected_program_id = Pubkey::from_str(program_id).context("invalid PROGRAM_ID")?;
     let game_pubkey = Pubkey::from_str(game_pda).context("invalid game_pda")?;
 
-    let account = client
+    let account = chain
         .get_account(&game_pubkey)
         .await
         .with_context(|| format!("failed to fetch game account {}", game_pubkey))?;
@@ -38,5 +33,5 @@ pub async fn fetch_and_decode_game_account_with_client(
         );
     }
 
-    decode_game_account(&account.data)
+    decode_game_account(idl, &account.data)
 }
@@ -0,0 +1,667 @@
+//! In-memory stand-in for a Solana cluster running the game program.
+//!
+//! Holds `Game` accounts and balances, and applies `create_game`, `join_game`,
+//! `settle_game`, `settle_game_v2` and `force_refund` with the same state checks
+//! as the program. Like the upgraded program, `create_game` writes V2 accounts;
+//! V1 accounts can still be inserted to exercise pre-upgrade games.
+//! Faults can be injected to exercise the finalizer's retry and recovery paths.
+
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use solana_sdk::{
+    account::Account,
+    hash::Hash,
+    instruction::{CompiledInstruction, InstructionError},
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{Transaction, TransactionError},
+};
+use solana_sdk_ids::{bpf_loader_upgradeable, system_program};
+use solana_transaction_status_client_types::{TransactionConfirmationStatus, TransactionStatus};
+
+use crate::solana::{
+    game_account::{
+        decode_game_account, encode_game_account, idl_game_layout, DecodedGameAccount,
+        DecodedGameState, GameLayout, GameMode,
+    },
+    gateway::ChainGateway,
+    idl::{DecodedInstruction, Idl},
+    pda::{game_address, program_data_address, vault_address},
+};
+
+/// Genesis hash the fake reports, so preflight can tell it from a real cluster.
+pub const GENESIS_HASH: Hash = Hash::new_from_array([0xfa; 32]);
+
+/// Custom program error codes reported by the fake, loosely mirroring Anchor's.
+const ERR_INVALID_STATE: u32 = 6000;
+const ERR_UNAUTHORIZED: u32 = 6001;
+const ERR_INVALID_ACCOUNT: u32 = 6002;
+const ERR_INSUFFICIENT_FUNDS: u32 = 6003;
+const ERR_INJECTED: u32 = 6999;
+
+pub struct FakeChain {
+    program_id: Pubkey,
+    /// The fake implements the program described by this IDL.
+    idl: Idl,
+    inner: Mutex<FakeChainInner>,
+}
+
+#[derive(Default)]
+struct FakeChainInner {
+    accounts: HashMap<Pubkey, Account>,
+    statuses: HashMap<Signature, TransactionStatus>,
+    slot: u64,
+    /// Charged to the fee payer per signature; `0` (free) unless a test opts in.
+    fee_per_signature: u64,
+    faults: FakeFaults,
+}
+
+#[derive(Default)]
+struct FakeFaults {
+    failing_sends: u32,
+    failing_landings: u32,
+    hide_statuses: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FakeGameSeed {
+    pub player1: String,
+    pub player2: Option<String>,
+    pub entry_amount: u64,
+    pub match_id: u64,
+    /// `1` seeds a pre-upgrade game; defaults to the IDL's newest layout.
+    #[serde(default)]
+    pub layout_version: Option<u8>,
+    #[serde(default)]
+    pub fee_bps: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct FakeGameParams {
+    pub player1: Pubkey,
+    pub player2: Option<Pubkey>,
+    pub authority: Pubkey,
+    pub entry_amount: u64,
+    pub match_id: u64,
+    pub layout: GameLayout,
+    /// Ignored for V1 games. The fee goes to `authority`.
+    pub fee_bps: u16,
+    /// Ignored for V1 games.
+    pub token_mint: Option<Pubkey>,
+}
+
+impl FakeChain {
+    /// A fake of the program described by the bundled IDL.
+    pub fn new(program_id: Pubkey) -> Self {
+        Self::with_idl(
+            program_id,
+            Idl::bundled().expect("bundled game IDL is valid"),
+        )
+    }
+
+    /// A fake of the program described by `idl`, e.g. one loaded from
+    /// `PROGRAM_IDL_PATH`.
+    pub fn with_idl(program_id: Pubkey, idl: Idl) -> Self {
+        let mut accounts = HashMap::new();
+        accounts.insert(
+            program_id,
+            Account {
+                lamports: 1,
+                data: Vec::new(),
+                owner: bpf_loader_upgradeable::id(),
+                executable: true,
+                rent_epoch: 0,
+            },
+        );
+        accounts.insert(
+            program_data_address(&program_id),
+            Account::new(1, 0, &bpf_loader_upgradeable::id()),
+        );
+
+        Self {
+            program_id,
+            idl,
+            inner: Mutex::new(FakeChainInner {
+                accounts,
+                slot: 1,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// The layout `create_game` writes: the newest one the IDL describes.
+    pub fn layout(&self) -> GameLayout {
+        idl_game_layout(&self.idl)
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    pub fn airdrop(&self, pubkey: &Pubkey, lamports: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        let account = inner
+            .accounts
+            .entry(*pubkey)
+            .or_insert_with(|| Account::new(0, 0, &system_program::id()));
+        account.lamports += lamports;
+    }
+
+    pub fn set_fee_per_signature(&self, lamports: u64) {
+        self.inner.lock().unwrap().fee_per_signature = lamports;
+    }
+
+    pub fn balance(&self, pubkey: &Pubkey) -> u64 {
+        let inner = self.inner.lock().unwrap();
+        inner.accounts.get(pubkey).map_or(0, |a| a.lamports)
+    }
+
+    /// Writes a funded `Game` account directly, skipping `create_game`/`join_game`.
+    /// Returns the game PDA.
+    pub fn insert_game(&self, params: &FakeGameParams) -> Pubkey {
+        let (game_pda, bump) = self.game_pda(&params.player1, &params.authority, params.match_id);
+        let (vault_pda, vault_bump) = self.vault_pda(&game_pda);
+        let now = unix_now();
+
+        let v2 = params.layout == GameLayout::V2;
+        let game = DecodedGameAccount {
+            layout: params.layout,
+            player1: params.player1,
+            player2: params.player2.unwrap_or_default(),
+            entry_amount: params.entry_amount,
+            authority: params.authority,
+            match_id: params.match_id,
+            state: if params.player2.is_some() {
+                DecodedGameState::Joined
+            } else {
+                DecodedGameState::Created
+            },
+            created_at: now,
+            joined_at: if params.player2.is_some() { now } else { 0 },
+            bump,
+            vault_bump,
+            game_mode: GameMode::Standard,
+            token_mint: params.token_mint.filter(|_| v2),
+            fee_bps: if v2 { params.fee_bps } else { 0 },
+            fee_recipient: if v2 {
+                params.authority
+            } else {
+                Pubkey::default()
+            },
+        };
+        let stake = if params.player2.is_some() { 2 } else { 1 };
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.accounts.insert(
+            game_pda,
+            self.game_account(&game, 1_000_000)
+                .expect("Game encodes with the program IDL"),
+        );
+        inner.accounts.insert(
+            vault_pda,
+            Account::new(params.entry_amount * stake, 0, &self.program_id),
+        );
+        game_pda
+    }
+
+    pub fn game(&self, game_pda: &Pubkey) -> Option<DecodedGameAccount> {
+        let inner = self.inner.lock().unwrap();
+        let account = inner.accounts.get(game_pda)?;
+        decode_game_account(&self.idl, &account.data).ok()
+    }
+
+    /// Seeds games from a JSON array of [`FakeGameSeed`] for `--mock-chain` dev mode.
+    pub fn seed_games_from_file(&self, path: &str, authority: &Pubkey) -> Result<Vec<Pubkey>> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read mock chain seed file {path}"))?;
+        let seeds: Vec<FakeGameSeed> =
+            serde_json::from_str(&raw).context("invalid mock chain seed file")?;
+
+        seeds
+            .iter()
+            .map(|seed| {
+                let player1 = Pubkey::from_str(&seed.player1).context("invalid seed player1")?;
+                let player2 = seed
+                    .player2
+                    .as_deref()
+                    .map(Pubkey::from_str)
+                    .transpose()
+                    .context("invalid seed player2")?;
+                Ok(self.insert_game(&FakeGameParams {
+                    player1,
+                    player2,
+                    authority: *authority,
+                    entry_amount: seed.entry_amount,
+                    match_id: seed.match_id,
+                    layout: match seed.layout_version {
+                        None => self.layout(),
+                        Some(1) => GameLayout::V1,
+                        Some(2) if self.layout() == GameLayout::V2 => GameLayout::V2,
+                        Some(2) => bail!("seed layout_version 2 needs a V2 program IDL"),
+                        Some(other) => bail!("unsupported seed layout_version {other}"),
+                    },
+                    fee_bps: seed.fee_bps,
+                    token_mint: None,
+                }))
+            })
+            .collect()
+    }
+
+    /// The next `count` sends fail before reaching the cluster.
+    pub fn fail_next_sends(&self, count: u32) {
+        self.inner.lock().unwrap().faults.failing_sends = count;
+    }
+
+    /// The next `count` sends are accepted but land with an instruction error.
+    pub fn fail_next_landings(&self, count: u32) {
+        self.inner.lock().unwrap().faults.failing_landings = count;
+    }
+
+    /// While hidden, signature statuses report `None`, as if RPC history were behind.
+    pub fn hide_signature_statuses(&self, hidden: bool) {
+        self.inner.lock().unwrap().faults.hide_statuses = hidden;
+    }
+
+    pub fn game_pda(&self, player1: &Pubkey, authority: &Pubkey, match_id: u64) -> (Pubkey, u8) {
+        game_address(&self.program_id, player1, authority, match_id)
+    }
+
+    pub fn vault_pda(&self, game_pda: &Pubkey) -> (Pubkey, u8) {
+        vault_address(&self.program_id, game_pda)
+    }
+
+    fn game_account(&self, game: &DecodedGameAccount, lamports: u64) -> Result<Account> {
+        Ok(Account {
+            lamports,
+            data: encode_game_account(&self.idl, game)?,
+            owner: self.program_id,
+            executable: false,
+            rent_epoch: 0,
+        })
+    }
+
+    fn load_game(
+        &self,
+        accounts: &HashMap<Pubkey, Account>,
+        game_pda: &Pubkey,
+    ) -> std::result::Result<DecodedGameAccount, u32> {
+        let account = accounts.get(game_pda).ok_or(ERR_INVALID_ACCOUNT)?;
+        decode_game_account(&self.idl, &account.data).map_err(|_| ERR_INVALID_ACCOUNT)
+    }
+
+    fn store_game(
+        &self,
+        accounts: &mut HashMap<Pubkey, Account>,
+        game_pda: &Pubkey,
+        game: &DecodedGameAccount,
+    ) -> std::result::Result<(), u32> {
+        let data = encode_game_account(&self.idl, game).map_err(|_| ERR_INVALID_STATE)?;
+        if let Some(account) = accounts.get_mut(game_pda) {
+            account.data = data;
+        }
+        Ok(())
+    }
+
+    fn execute(
+        &self,
+        accounts: &mut HashMap<Pubkey, Account>,
+        tx: &Transaction,
+        ix: &CompiledInstruction,
+    ) -> std::result::Result<(), u32> {
+        let keys = &tx.message.account_keys;
+        let program_id = keys
+            .get(ix.program_id_index as usize)
+            .ok_or(ERR_INVALID_ACCOUNT)?;
+        if *program_id != self.program_id {
+            // Compute budget and other helper programs are accepted as no-ops.
+            return Ok(());
+        }
+
+        let metas: Vec<(Pubkey, bool)> = ix
+            .accounts
+            .iter()
+            .map(|&i| {
+                let i = i as usize;
+                keys.get(i)
+                    .map(|k| (*k, tx.message.is_signer(i)))
+                    .ok_or(ERR_INVALID_ACCOUNT)
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        let ix = self
+            .idl
+            .decode_instruction(&ix.data, &metas)
+            .map_err(|_| ERR_INVALID_ACCOUNT)?;
+
+        match ix.name.as_str() {
+            "create_game" => self.create_game(accounts, &ix),
+            "join_game" => self.join_game(accounts, &ix),
+            "settle_game" => self.settle_game(accounts, &ix, GameLayout::V1),
+            "settle_game_v2" => self.settle_game(accounts, &ix, GameLayout::V2),
+            "force_refund" => self.force_refund(accounts, &ix),
+            _ => Err(ERR_INVALID_ACCOUNT),
+        }
+    }
+
+    fn create_game(
+        &self,
+        accounts: &mut HashMap<Pubkey, Account>,
+        ix: &DecodedInstruction,
+    ) -> std::result::Result<(), u32> {
+        let (player1, player1_signed) = account(ix, "player1")?;
+        let (authority, _) = account(ix, "authority")?;
+        let (game_pda, _) = account(ix, "game")?;
+        let (vault_pda, _) = account(ix, "vault")?;
+        require(player1_signed, ERR_UNAUTHORIZED)?;
+
+        let entry_amount = arg_u64(ix, "entry_amount")?;
+        let match_id = arg_u64(ix, "match_id")?;
+        require(entry_amount > 0, ERR_INVALID_STATE)?;
+
+        let (expected_game, bump) = self.game_pda(&player1, &authority, match_id);
+        let (expected_vault, vault_bump) = self.vault_pda(&expected_game);
+        require(
+            game_pda == expected_game && vault_pda == expected_vault,
+            ERR_INVALID_ACCOUNT,
+        )?;
+        require(!accounts.contains_key(&game_pda), ERR_INVALID_STATE)?;
+
+        debit(accounts, &player1, entry_amount)?;
+        credit(accounts, &vault_pda, entry_amount, &self.program_id);
+
+        let game = DecodedGameAccount {
+            layout: self.layout(),
+            player1,
+            player2: Pubkey::default(),
+            entry_amount,
+            authority,
+            match_id,
+            state: DecodedGameState::Created,
+            created_at: unix_now(),
+            joined_at: 0,
+            bump,
+            vault_bump,
+            game_mode: GameMode::Standard,
+            token_mint: None,
+            fee_bps: 0,
+            fee_recipient: authority,
+        };
+        let account = self
+            .game_account(&game, 1_000_000)
+            .map_err(|_| ERR_INVALID_STATE)?;
+        accounts.insert(game_pda, account);
+        Ok(())
+    }
+
+    fn join_game(
+        &self,
+        accounts: &mut HashMap<Pubkey, Account>,
+        ix: &DecodedInstruction,
+    ) -> std::result::Result<(), u32> {
+        let (player2, player2_signed) = account(ix, "player2")?;
+        let (game_pda, _) = account(ix, "game")?;
+        let (vault_pda, _) = account(ix, "vault")?;
+        require(player2_signed, ERR_UNAUTHORIZED)?;
+        require(
+            vault_pda == self.vault_pda(&game_pda).0,
+            ERR_INVALID_ACCOUNT,
+        )?;
+
+        let mut game = self.load_game(accounts, &game_pda)?;
+        require(game.state == DecodedGameState::Created, ERR_INVALID_STATE)?;
+        require(player2 != game.player1, ERR_INVALID_ACCOUNT)?;
+
+        debit(accounts, &player2, game.entry_amount)?;
+        credit(accounts, &vault_pda, game.entry_amount, &self.program_id);
+
+        game.player2 = player2;
+        game.state = DecodedGameState::Joined;
+        game.joined_at = unix_now();
+        self.store_game(accounts, &game_pda, &game)
+    }
+
+    /// `settle_game` for V1 accounts, `settle_game_v2` (with protocol fee) for V2.
+    fn settle_game(
+        &self,
+        accounts: &mut HashMap<Pubkey, Account>,
+        ix: &DecodedInstruction,
+        layout: GameLayout,
+    ) -> std::result::Result<(), u32> {
+        let (game_pda, _) = account(ix, "game")?;
+        let (vault_pda, _) = account(ix, "vault")?;
+        let (winner_account, _) = account(ix, "winner")?;
+        let (authority, authority_signed) = account(ix, "authority")?;
+        let winner = ix
+            .args
+            .field("winner")
+            .and_then(|v| v.as_pubkey())
+            .map_err(|_| ERR_INVALID_ACCOUNT)?;
+
+        let mut game = self.load_game(accounts, &game_pda)?;
+        require(
+            vault_pda == self.vault_pda(&game_pda).0,
+            ERR_INVALID_ACCOUNT,
+        )?;
+        require(
+            authority_signed && authority == game.authority,
+            ERR_UNAUTHORIZED,
+        )?;
+        require(game.layout == layout, ERR_INVALID_STATE)?;
+        require(game.state == DecodedGameState::Joined, ERR_INVALID_STATE)?;
+        require(
+            winner == winner_account && (winner == game.player1 || winner == game.player2),
+            ERR_INVALID_ACCOUNT,
+        )?;
+
+        let pot = accounts.get(&vault_pda).map_or(0, |a| a.lamports);
+        debit(accounts, &vault_pda, pot)?;
+        let fee = if layout == GameLayout::V2 {
+            let (fee_recipient, _) = account(ix, "fee_recipient")?;
+            require(fee_recipient == game.fee_recipient, ERR_INVALID_ACCOUNT)?;
+            let fee = pot * u64::from(game.fee_bps) / 10_000;
+            credit(accounts, &fee_recipient, fee, &system_program::id());
+            fee
+        } else {
+            0
+        };
+        credit(accounts, &winner, pot - fee, &system_program::id());
+
+        game.state = DecodedGameState::Settled;
+        self.store_game(accounts, &game_pda, &game)
+    }
+
+    fn force_refund(
+        &self,
+        accounts: &mut HashMap<Pubkey, Account>,
+        ix: &DecodedInstruction,
+    ) -> std::result::Result<(), u32> {
+        let (game_pda, _) = account(ix, "game")?;
+        let (vault_pda, _) = account(ix, "vault")?;
+        let (player1, _) = account(ix, "player1")?;
+        let (player2, _) = account(ix, "player2")?;
+        let (authority, authority_signed) = account(ix, "authority")?;
+
+        let mut game = self.load_game(accounts, &game_pda)?;
+        require(
+            vault_pda == self.vault_pda(&game_pda).0,
+            ERR_INVALID_ACCOUNT,
+        )?;
+        require(
+            authority_signed && authority == game.authority,
+            ERR_UNAUTHORIZED,
+        )?;
+        require(player1 == game.player1, ERR_INVALID_ACCOUNT)?;
+
+        match game.state {
+            DecodedGameState::Created => {
+                debit(accounts, &vault_pda, game.entry_amount)?;
+                credit(accounts, &player1, game.entry_amount, &system_program::id());
+            }
+            DecodedGameState::Joined => {
+                require(player2 == game.player2, ERR_INVALID_ACCOUNT)?;
+                debit(accounts, &vault_pda, game.entry_amount * 2)?;
+                credit(accounts, &player1, game.entry_amount, &system_program::id());
+                credit(accounts, &player2, game.entry_amount, &system_program::id());
+            }
+            _ => return Err(ERR_INVALID_STATE),
+        }
+
+        game.state = DecodedGameState::Refunded;
+        self.store_game(accounts, &game_pda, &game)
+    }
+}
+
+#[async_trait]
+impl ChainGateway for FakeChain {
+    async fn get_optional_account(&self, pubkey: &Pubkey) -> Result<Option<Account>> {
+        let inner = self.inner.lock().unwrap();
+        Ok(inner.accounts.get(pubkey).cloned())
+    }
+
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> Result<Vec<Option<TransactionStatus>>> {
+        let inner = self.inner.lock().unwrap();
+        if inner.faults.hide_statuses {
+            return Ok(vec![None; signatures.len()]);
+        }
+        Ok(signatures
+            .iter()
+            .map(|sig| inner.statuses.get(sig).cloned())
+            .collect())
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<Hash> {
+        let inner = self.inner.lock().unwrap();
+        let digest = Sha256::digest(inner.slot.to_le_bytes());
+        Ok(Hash::new_from_array(digest.into()))
+    }
+
+    async fn get_genesis_hash(&self) -> Result<Hash> {
+        Ok(GENESIS_HASH)
+    }
+
+    async fn send_transaction(&self, tx: &Transaction) -> Result<Signature> {
+        let mut inner = self.inner.lock().unwrap();
+
+        if inner.faults.failing_sends > 0 {
+            inner.faults.failing_sends -= 1;
+            bail!("fake chain: injected send failure");
+        }
+
+        tx.verify().map_err(|e| anyhow!("fake chain: {e}"))?;
+        let signature = *tx
+            .signatures
+            .first()
+            .ok_or_else(|| anyhow!("fake chain: transaction has no signatures"))?;
+        if inner.statuses.contains_key(&signature) {
+            return Ok(signature);
+        }
+
+        inner.slot += 1;
+        let slot = inner.slot;
+
+        let err = if inner.faults.failing_landings > 0 {
+            inner.faults.failing_landings -= 1;
+            Some(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(ERR_INJECTED),
+            ))
+        } else {
+            // Apply against a scratch copy so a failing instruction leaves no partial writes.
+            let mut scratch = inner.accounts.clone();
+            let fee = inner.fee_per_signature * tx.signatures.len() as u64;
+            if fee > 0 && debit(&mut scratch, &tx.message.account_keys[0], fee).is_err() {
+                bail!(
+                    "fake chain: transaction simulation failed: {:?}",
+                    TransactionError::InsufficientFundsForFee
+                );
+            }
+            for (index, ix) in tx.message.instructions.iter().enumerate() {
+                if let Err(code) = self.execute(&mut scratch, tx, ix) {
+                    // Mirrors preflight simulation rejecting the transaction.
+                    bail!(
+                        "fake chain: transaction simulation failed: {:?}",
+                        TransactionError::InstructionError(
+                            index as u8,
+                            InstructionError::Custom(code)
+                        )
+                    );
+                }
+            }
+            inner.accounts = scratch;
+            None
+        };
+
+        inner.statuses.insert(
+            signature,
+            TransactionStatus {
+                slot,
+                confirmations: None,
+                status: err.clone().map_or(Ok(()), Err),
+                err,
+                confirmation_status: Some(TransactionConfirmationStatus::Finalized),
+            },
+        );
+        Ok(signature)
+    }
+}
+
+fn debit(
+    accounts: &mut HashMap<Pubkey, Account>,
+    pubkey: &Pubkey,
+    lamports: u64,
+) -> std::result::Result<(), u32> {
+    let account = accounts.get_mut(pubkey).ok_or(ERR_INSUFFICIENT_FUNDS)?;
+    account.lamports = account
+        .lamports
+        .checked_sub(lamports)
+        .ok_or(ERR_INSUFFICIENT_FUNDS)?;
+    Ok(())
+}
+
+fn credit(accounts: &mut HashMap<Pubkey, Account>, pubkey: &Pubkey, lamports: u64, owner: &Pubkey) {
+    accounts
+        .entry(*pubkey)
+        .or_insert_with(|| Account::new(0, 0, owner))
+        .lamports += lamports;
+}
+
+fn require(condition: bool, code: u32) -> std::result::Result<(), u32> {
+    if condition {
+        Ok(())
+    } else {
+        Err(code)
+    }
+}
+
+fn account(ix: &DecodedInstruction, name: &str) -> std::result::Result<(Pubkey, bool), u32> {
+    ix.accounts
+        .iter()
+        .find(|(n, _, _)| n == name)
+        .map(|(_, key, signer)| (*key, *signer))
+        .ok_or(ERR_INVALID_ACCOUNT)
+}
+
+fn arg_u64(ix: &DecodedInstruction, name: &str) -> std::result::Result<u64, u32> {
+    ix.args
+        .field(name)
+        .and_then(|v| v.as_u64())
+        .map_err(|_| ERR_INVALID_ACCOUNT)
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
@@ -1,11 +1,45 @@
-//! Manual decoder for the Anchor `Game` account.
+//! `Game` account decoding driven by the program IDL.
 //!
-//! This keeps MVP integration simple without importing the program repo crate yet.
+//! Program upgrades append fields to `Game`, so accounts created before an
+//! upgrade keep their shorter layout. The layout of each account is detected
+//! from its size and, for newer layouts, the `version` byte.
 
-use anyhow::{bail, ensure, Result};
-use sha2::{Digest, Sha256};
+use anyhow::{bail, Result};
 use solana_sdk::pubkey::Pubkey;
 
+use crate::solana::idl::{Idl, IdlTypeDef, IdlValue};
+
+pub const GAME_ACCOUNT: &str = "Game";
+
+/// Fields every `Game` layout has; checked against the IDL at startup.
+pub const GAME_FIELDS: &[&str] = &[
+    "player1",
+    "player2",
+    "entry_amount",
+    "authority",
+    "match_id",
+    "state",
+    "created_at",
+    "joined_at",
+    "bump",
+    "vault_bump",
+];
+
+/// Fields appended by the V2 program, in order after [`GAME_FIELDS`].
+pub const GAME_V2_FIELDS: &[&str] = &[
+    "version",
+    "game_mode",
+    "token_mint",
+    "fee_bps",
+    "fee_recipient",
+];
+
+/// `GameState` variants the backend understands, in program order.
+pub const GAME_STATES: &[&str] = &["Created", "Joined", "Settled", "Refunded"];
+
+/// `GameMode` variants the backend understands, in program order.
+pub const GAME_MODES: &[&str] = &["Standard", "Ranked"];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DecodedGameState {
     Created,
@@ -14,8 +48,33 @@ pub enum DecodedGameState {
     Refunded,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameLayout {
+    /// Original layout without a version byte; settled with `settle_game`.
+    V1,
+    /// Adds mode, mint and fee fields; settled with `settle_game_v2`.
+    V2,
+}
+
+/// The newest layout `idl` describes: V2 when its `Game` has the `version` field.
+pub fn idl_game_layout(idl: &Idl) -> GameLayout {
+    match idl.type_def(GAME_ACCOUNT) {
+        Ok(IdlTypeDef::Struct(fields)) if fields.iter().any(|f| f.name == "version") => {
+            GameLayout::V2
+        }
+        _ => GameLayout::V1,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameMode {
+    Standard,
+    Ranked,
+}
+
 #[derive(Debug, Clone)]
 pub struct DecodedGameAccount {
+    pub layout: GameLayout,
     pub player1: Pubkey,
     pub player2: Pubkey,
     pub entry_amount: u64,
@@ -24,93 +83,112 @@ pub struct DecodedGameAccount {
     pub state: DecodedGameState,
     pub created_at: i64,
     pub joined_at: i64,
-    pub _bump: u8,
-    pub _vault_bump: u8,
+    pub bump: u8,
+    pub vault_bump: u8,
+    /// `Standard` for V1 games.
+    pub game_mode: GameMode,
+    /// `None` for games staked in SOL.
+    pub token_mint: Option<Pubkey>,
+    /// Protocol fee taken from the pot on settle; `0` for V1 games.
+    pub fee_bps: u16,
+    /// Receives the protocol fee; `Pubkey::default()` for V1 games.
+    pub fee_recipient: Pubkey,
 }
 
-pub fn decode_game_account(data: &[u8]) -> Result<DecodedGameAccount> {
-    const BODY_LEN: usize = 32 + 32 + 8 + 32 + 8 + 1 + 8 + 8 + 1 + 1;
-    ensure!(
-        data.len() >= 8 + BODY_LEN,
-        "game account data too short: {} bytes",
-        data.len()
-    );
-
-    let expected_discriminator = game_account_discriminator();
-    ensure!(
-        data[..8] == expected_discriminator,
-        "invalid Game discriminator"
-    );
-
-    let mut i = 8usize;
-    let player1 = read_pubkey(data, &mut i)?;
-    let player2 = read_pubkey(data, &mut i)?;
-    let entry_amount = read_u64(data, &mut i)?;
-    let authority = read_pubkey(data, &mut i)?;
-    let match_id = read_u64(data, &mut i)?;
-    let state = match read_u8(data, &mut i)? {
-        0 => DecodedGameState::Created,
-        1 => DecodedGameState::Joined,
-        2 => DecodedGameState::Settled,
-        3 => DecodedGameState::Refunded,
+pub fn decode_game_account(idl: &Idl, data: &[u8]) -> Result<DecodedGameAccount> {
+    let game = idl.decode_account_prefix(GAME_ACCOUNT, data)?;
+
+    let state = match game.field("state")?.variant_name()? {
+        "Created" => DecodedGameState::Created,
+        "Joined" => DecodedGameState::Joined,
+        "Settled" => DecodedGameState::Settled,
+        "Refunded" => DecodedGameState::Refunded,
         other => bail!("invalid GameState variant: {other}"),
     };
-    let created_at = read_i64(data, &mut i)?;
-    let joined_at = read_i64(data, &mut i)?;
-    let bump = read_u8(data, &mut i)?;
-    let vault_bump = read_u8(data, &mut i)?;
-
-    Ok(DecodedGameAccount {
-        player1,
-        player2,
-        entry_amount,
-        authority,
-        match_id,
+
+    // V1 accounts end after `vault_bump`, or carry zeroed reserved space there.
+    let version = game
+        .opt_field("version")
+        .map(IdlValue::as_u8)
+        .transpose()?
+        .unwrap_or(0);
+    let layout = match version {
+        0 | 1 => GameLayout::V1,
+        2 => GameLayout::V2,
+        other => bail!("unsupported Game layout version {other}"),
+    };
+
+    let mut decoded = DecodedGameAccount {
+        layout,
+        player1: game.field("player1")?.as_pubkey()?,
+        player2: game.field("player2")?.as_pubkey()?,
+        entry_amount: game.field("entry_amount")?.as_u64()?,
+        authority: game.field("authority")?.as_pubkey()?,
+        match_id: game.field("match_id")?.as_u64()?,
         state,
-        created_at,
-        joined_at,
-        _bump: bump,
-        _vault_bump: vault_bump,
-    })
-}
+        created_at: game.field("created_at")?.as_i64()?,
+        joined_at: game.field("joined_at")?.as_i64()?,
+        bump: game.field("bump")?.as_u8()?,
+        vault_bump: game.field("vault_bump")?.as_u8()?,
+        game_mode: GameMode::Standard,
+        token_mint: None,
+        fee_bps: 0,
+        fee_recipient: Pubkey::default(),
+    };
 
-fn game_account_discriminator() -> [u8; 8] {
-    let mut hasher = Sha256::new();
-    hasher.update(b"account:Game");
-    let hash = hasher.finalize();
-    let mut out = [0u8; 8];
-    out.copy_from_slice(&hash[..8]);
-    out
-}
+    if layout == GameLayout::V2 {
+        decoded.game_mode = match game.field("game_mode")?.variant_name()? {
+            "Standard" => GameMode::Standard,
+            "Ranked" => GameMode::Ranked,
+            other => bail!("invalid GameMode variant: {other}"),
+        };
+        let mint = game.field("token_mint")?.as_pubkey()?;
+        decoded.token_mint = (mint != Pubkey::default()).then_some(mint);
+        decoded.fee_bps = game.field("fee_bps")?.as_u16()?;
+        decoded.fee_recipient = game.field("fee_recipient")?.as_pubkey()?;
+    }
 
-fn read_pubkey(data: &[u8], i: &mut usize) -> Result<Pubkey> {
-    let bytes = read_fixed::<32>(data, i)?;
-    Ok(Pubkey::new_from_array(bytes))
+    Ok(decoded)
 }
 
-fn read_u64(data: &[u8], i: &mut usize) -> Result<u64> {
-    Ok(u64::from_le_bytes(read_fixed::<8>(data, i)?))
-}
+/// Inverse of [`decode_game_account`]; used by the in-memory fake cluster.
+pub fn encode_game_account(idl: &Idl, game: &DecodedGameAccount) -> Result<Vec<u8>> {
+    let state = match game.state {
+        DecodedGameState::Created => "Created",
+        DecodedGameState::Joined => "Joined",
+        DecodedGameState::Settled => "Settled",
+        DecodedGameState::Refunded => "Refunded",
+    };
 
-fn read_i64(data: &[u8], i: &mut usize) -> Result<i64> {
-    Ok(i64::from_le_bytes(read_fixed::<8>(data, i)?))
-}
+    let mut fields = vec![
+        ("player1".into(), game.player1.into()),
+        ("player2".into(), game.player2.into()),
+        ("entry_amount".into(), game.entry_amount.into()),
+        ("authority".into(), game.authority.into()),
+        ("match_id".into(), game.match_id.into()),
+        ("state".into(), IdlValue::unit_variant(state)),
+        ("created_at".into(), game.created_at.into()),
+        ("joined_at".into(), game.joined_at.into()),
+        ("bump".into(), game.bump.into()),
+        ("vault_bump".into(), game.vault_bump.into()),
+    ];
 
-fn read_u8(data: &[u8], i: &mut usize) -> Result<u8> {
-    let b = *data
-        .get(*i)
-        .ok_or_else(|| anyhow::anyhow!("read past end of account data"))?;
-    *i += 1;
-    Ok(b)
-}
+    if game.layout == GameLayout::V2 {
+        let mode = match game.game_mode {
+            GameMode::Standard => "Standard",
+            GameMode::Ranked => "Ranked",
+        };
+        fields.extend([
+            ("version".into(), 2_u8.into()),
+            ("game_mode".into(), IdlValue::unit_variant(mode)),
+            (
+                "token_mint".into(),
+                game.token_mint.unwrap_or_default().into(),
+            ),
+            ("fee_bps".into(), game.fee_bps.into()),
+            ("fee_recipient".into(), game.fee_recipient.into()),
+        ]);
+    }
 
-fn read_fixed<const N: usize>(data: &[u8], i: &mut usize) -> Result<[u8; N]> {
-    let end = *i + N;
-    let slice = data
-        .get(*i..end)
-        .ok_or_else(|| anyhow::anyhow!("read past end of account data"))?;
-    let mut out = [0u8; N];
-    out.copy_from_slice(slice);
-    *i = end;
-    Ok(out)
+    idl.encode_account_prefix(GAME_ACCOUNT, &IdlValue::Struct(fields))
 }
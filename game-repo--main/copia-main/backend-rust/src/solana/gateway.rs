@@ -0,0 +1,37 @@
+//! Chain access boundary used by the API and the workers.
+//!
+//! Production uses [`crate::solana::rpc_pool::RpcPool`]; tests and `--mock-chain`
+//! dev mode use [`crate::solana::fake_chain::FakeChain`].
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use solana_sdk::{
+    account::Account, hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction,
+};
+use solana_transaction_status_client_types::TransactionStatus;
+
+#[async_trait]
+pub trait ChainGateway: Send + Sync {
+    /// `None` if the account does not exist; errors are transport/RPC failures.
+    async fn get_optional_account(&self, pubkey: &Pubkey) -> Result<Option<Account>>;
+
+    /// Fails if the account does not exist.
+    async fn get_account(&self, pubkey: &Pubkey) -> Result<Account> {
+        self.get_optional_account(pubkey)
+            .await?
+            .ok_or_else(|| anyhow!("account {} not found", pubkey))
+    }
+
+    /// One entry per requested signature; `None` if the cluster has not seen it.
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> Result<Vec<Option<TransactionStatus>>>;
+
+    async fn get_latest_blockhash(&self) -> Result<Hash>;
+
+    /// Identifies the cluster the gateway talks to.
+    async fn get_genesis_hash(&self) -> Result<Hash>;
+
+    async fn send_transaction(&self, tx: &Transaction) -> Result<Signature>;
+}
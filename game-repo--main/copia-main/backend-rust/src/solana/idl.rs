@@ -0,0 +1,882 @@
+//! Anchor IDL loading plus borsh encoding/decoding driven by it.
+//!
+//! Supports both the current (0.30+) IDL spec, which carries explicit
+//! discriminators and a top-level `types` list, and the legacy spec with
+//! `isMut`/`isSigner` flags and inline account types.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+/// IDL of the game program this backend was built against.
+pub const BUNDLED_GAME_IDL: &str = include_str!("../../idl/game_program.json");
+
+#[derive(Debug, Clone)]
+pub struct Idl {
+    pub address: Option<Pubkey>,
+    pub name: String,
+    pub instructions: Vec<IdlInstruction>,
+    pub accounts: Vec<IdlAccountDef>,
+    types: HashMap<String, IdlTypeDef>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdlInstruction {
+    pub name: String,
+    pub discriminator: [u8; 8],
+    pub accounts: Vec<IdlAccountItem>,
+    pub args: Vec<IdlField>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdlAccountItem {
+    pub name: String,
+    pub writable: bool,
+    pub signer: bool,
+    /// Fixed address, e.g. the system program.
+    pub address: Option<Pubkey>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IdlAccountDef {
+    pub name: String,
+    pub discriminator: [u8; 8],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdlField {
+    pub name: String,
+    pub ty: IdlType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdlType {
+    Bool,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    U128,
+    I128,
+    Pubkey,
+    String,
+    Bytes,
+    Option(Box<IdlType>),
+    Vec(Box<IdlType>),
+    Array(Box<IdlType>, usize),
+    Defined(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum IdlTypeDef {
+    Struct(Vec<IdlField>),
+    Enum(Vec<IdlEnumVariant>),
+}
+
+#[derive(Debug, Clone)]
+pub struct IdlEnumVariant {
+    pub name: String,
+    pub fields: IdlVariantFields,
+}
+
+#[derive(Debug, Clone)]
+pub enum IdlVariantFields {
+    Unit,
+    Named(Vec<IdlField>),
+    Tuple(Vec<IdlType>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdlValue {
+    Bool(bool),
+    Uint(u128),
+    Int(i128),
+    Pubkey(Pubkey),
+    String(String),
+    Bytes(Vec<u8>),
+    Option(Option<Box<IdlValue>>),
+    List(Vec<IdlValue>),
+    Struct(Vec<(String, IdlValue)>),
+    Enum {
+        name: String,
+        index: u8,
+        fields: Option<Box<IdlValue>>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodedInstruction {
+    pub name: String,
+    /// `(idl account name, key, is_signer)` in IDL order.
+    pub accounts: Vec<(String, Pubkey, bool)>,
+    pub args: IdlValue,
+}
+
+impl Idl {
+    pub fn bundled() -> Result<Self> {
+        Self::parse(BUNDLED_GAME_IDL).context("bundled game IDL is invalid")
+    }
+
+    /// Loads the IDL from `path`, or the bundled one when no path is configured.
+    pub fn load(path: Option<&str>) -> Result<Self> {
+        match path {
+            Some(path) => {
+                let raw = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read program IDL {path}"))?;
+                Self::parse(&raw).with_context(|| format!("invalid program IDL {path}"))
+            }
+            None => Self::bundled(),
+        }
+    }
+
+    pub fn parse(raw: &str) -> Result<Self> {
+        let json: Value = serde_json::from_str(raw).context("IDL is not valid JSON")?;
+
+        let address = json
+            .get("address")
+            .and_then(Value::as_str)
+            .map(|s| s.parse::<Pubkey>())
+            .transpose()
+            .map_err(|e| anyhow!("invalid IDL address: {e}"))?;
+        let name = json
+            .pointer("/metadata/name")
+            .or_else(|| json.get("name"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+
+        let mut types = HashMap::new();
+        for def in array(&json, "types") {
+            let name = str_field(def, "name")?;
+            types.insert(name.to_string(), parse_type_def(&def["type"])?);
+        }
+
+        let mut accounts = Vec::new();
+        for def in array(&json, "accounts") {
+            let name = str_field(def, "name")?;
+            // Legacy IDLs define the account layout inline.
+            if let Some(ty) = def.get("type") {
+                types.insert(name.to_string(), parse_type_def(ty)?);
+            }
+            let discriminator = match def.get("discriminator") {
+                Some(d) => parse_discriminator(d)?,
+                None => sighash("account", name),
+            };
+            accounts.push(IdlAccountDef {
+                name: name.to_string(),
+                discriminator,
+            });
+        }
+
+        let mut instructions = Vec::new();
+        for def in array(&json, "instructions") {
+            let name = str_field(def, "name")?;
+            let discriminator = match def.get("discriminator") {
+                Some(d) => parse_discriminator(d)?,
+                None => sighash("global", &to_snake_case(name)),
+            };
+            let accounts = array(def, "accounts")
+                .map(parse_account_item)
+                .collect::<Result<_>>()
+                .with_context(|| format!("instruction {name}"))?;
+            let args = array(def, "args")
+                .map(parse_field)
+                .collect::<Result<_>>()
+                .with_context(|| format!("instruction {name}"))?;
+            instructions.push(IdlInstruction {
+                name: to_snake_case(name),
+                discriminator,
+                accounts,
+                args,
+            });
+        }
+
+        Ok(Self {
+            address,
+            name,
+            instructions,
+            accounts,
+            types,
+        })
+    }
+
+    pub fn instruction(&self, name: &str) -> Result<&IdlInstruction> {
+        self.instructions
+            .iter()
+            .find(|ix| ix.name == name)
+            .ok_or_else(|| anyhow!("IDL has no instruction {name}"))
+    }
+
+    pub fn account(&self, name: &str) -> Result<&IdlAccountDef> {
+        self.accounts
+            .iter()
+            .find(|a| a.name == name)
+            .ok_or_else(|| anyhow!("IDL has no account {name}"))
+    }
+
+    pub fn type_def(&self, name: &str) -> Result<&IdlTypeDef> {
+        self.types
+            .get(name)
+            .ok_or_else(|| anyhow!("IDL has no type {name}"))
+    }
+
+    /// Serialized size of a fixed-size type; `None` for types containing
+    /// strings, vectors or options.
+    pub fn fixed_size(&self, ty: &IdlType) -> Option<usize> {
+        Some(match ty {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 => 1,
+            IdlType::U16 | IdlType::I16 => 2,
+            IdlType::U32 | IdlType::I32 => 4,
+            IdlType::U64 | IdlType::I64 => 8,
+            IdlType::U128 | IdlType::I128 => 16,
+            IdlType::Pubkey => 32,
+            IdlType::Array(inner, len) => self.fixed_size(inner)? * len,
+            IdlType::Defined(name) => match self.types.get(name)? {
+                IdlTypeDef::Struct(fields) => fields
+                    .iter()
+                    .map(|f| self.fixed_size(&f.ty))
+                    .sum::<Option<usize>>()?,
+                IdlTypeDef::Enum(variants) => {
+                    if variants
+                        .iter()
+                        .all(|v| matches!(v.fields, IdlVariantFields::Unit))
+                    {
+                        1
+                    } else {
+                        return None;
+                    }
+                }
+            },
+            IdlType::String | IdlType::Bytes | IdlType::Option(_) | IdlType::Vec(_) => return None,
+        })
+    }
+
+    /// Checks the discriminator and decodes the account body into a struct value.
+    /// Trailing bytes (e.g. reserved space) are ignored.
+    pub fn decode_account(&self, name: &str, data: &[u8]) -> Result<IdlValue> {
+        let def = self.account(name)?;
+        ensure!(
+            data.len() >= 8,
+            "{name} account data too short: {} bytes",
+            data.len()
+        );
+        ensure!(
+            data[..8] == def.discriminator,
+            "invalid {name} discriminator"
+        );
+
+        let mut cursor = &data[8..];
+        self.decode_value(&IdlType::Defined(name.to_string()), &mut cursor)
+            .with_context(|| format!("failed to decode {name} account"))
+    }
+
+    /// Decodes the leading fields of a struct account that fit in `data`.
+    ///
+    /// Accounts created before a program upgrade appended fields are shorter than
+    /// the current layout; their missing trailing fields are left out of the result.
+    pub fn decode_account_prefix(&self, name: &str, data: &[u8]) -> Result<IdlValue> {
+        let def = self.account(name)?;
+        ensure!(
+            data.len() >= 8,
+            "{name} account data too short: {} bytes",
+            data.len()
+        );
+        ensure!(
+            data[..8] == def.discriminator,
+            "invalid {name} discriminator"
+        );
+        let IdlTypeDef::Struct(fields) = self.type_def(name)? else {
+            bail!("IDL type {name} is not a struct");
+        };
+
+        let mut cursor = &data[8..];
+        let mut out = Vec::with_capacity(fields.len());
+        for field in fields {
+            match self.fixed_size(&field.ty) {
+                Some(size) if cursor.len() < size => break,
+                None if cursor.is_empty() => break,
+                _ => {}
+            }
+            let value = self
+                .decode_value(&field.ty, &mut cursor)
+                .with_context(|| format!("failed to decode {name}.{}", field.name))?;
+            out.push((field.name.clone(), value));
+        }
+        Ok(IdlValue::Struct(out))
+    }
+
+    /// Inverse of [`Idl::decode_account_prefix`]: encodes the leading fields present
+    /// in `value`, which must not skip a field and then set a later one.
+    pub fn encode_account_prefix(&self, name: &str, value: &IdlValue) -> Result<Vec<u8>> {
+        let def = self.account(name)?;
+        let IdlTypeDef::Struct(fields) = self.type_def(name)? else {
+            bail!("IDL type {name} is not a struct");
+        };
+        let IdlValue::Struct(values) = value else {
+            bail!("{name} value is not a struct");
+        };
+
+        let mut out = def.discriminator.to_vec();
+        let mut encoded = 0;
+        for field in fields {
+            let Some((_, v)) = values.iter().find(|(n, _)| *n == field.name) else {
+                break;
+            };
+            self.encode_value(&field.ty, v, &mut out)
+                .with_context(|| format!("{name}.{}", field.name))?;
+            encoded += 1;
+        }
+        ensure!(
+            encoded == values.len(),
+            "{name} fields do not form a prefix of the IDL layout"
+        );
+        Ok(out)
+    }
+
+    pub fn encode_account(&self, name: &str, value: &IdlValue) -> Result<Vec<u8>> {
+        let def = self.account(name)?;
+        let mut out = def.discriminator.to_vec();
+        self.encode_value(&IdlType::Defined(name.to_string()), value, &mut out)
+            .with_context(|| format!("failed to encode {name} account"))?;
+        Ok(out)
+    }
+
+    /// Builds an instruction with accounts in IDL order. Accounts with a fixed
+    /// address in the IDL may be omitted from `accounts`.
+    pub fn build_instruction(
+        &self,
+        program_id: Pubkey,
+        name: &str,
+        accounts: &[(&str, Pubkey)],
+        args: &[(&str, IdlValue)],
+    ) -> Result<Instruction> {
+        let ix = self.instruction(name)?;
+
+        let metas = ix
+            .accounts
+            .iter()
+            .map(|item| {
+                let key = accounts
+                    .iter()
+                    .find(|(n, _)| *n == item.name)
+                    .map(|(_, k)| *k)
+                    .or(item.address)
+                    .ok_or_else(|| anyhow!("{name}: missing account {}", item.name))?;
+                Ok(if item.writable {
+                    AccountMeta::new(key, item.signer)
+                } else {
+                    AccountMeta::new_readonly(key, item.signer)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut data = ix.discriminator.to_vec();
+        for field in &ix.args {
+            let value = args
+                .iter()
+                .find(|(n, _)| *n == field.name)
+                .map(|(_, v)| v)
+                .ok_or_else(|| anyhow!("{name}: missing arg {}", field.name))?;
+            self.encode_value(&field.ty, value, &mut data)
+                .with_context(|| format!("{name}: invalid arg {}", field.name))?;
+        }
+
+        Ok(Instruction {
+            program_id,
+            accounts: metas,
+            data,
+        })
+    }
+
+    /// Inverse of [`Idl::build_instruction`] given the resolved account keys and
+    /// their signer flags.
+    pub fn decode_instruction(
+        &self,
+        data: &[u8],
+        accounts: &[(Pubkey, bool)],
+    ) -> Result<DecodedInstruction> {
+        ensure!(data.len() >= 8, "instruction data too short");
+        let ix = self
+            .instructions
+            .iter()
+            .find(|ix| ix.discriminator == data[..8])
+            .ok_or_else(|| anyhow!("unknown instruction discriminator"))?;
+        ensure!(
+            accounts.len() >= ix.accounts.len(),
+            "{}: expected {} accounts, got {}",
+            ix.name,
+            ix.accounts.len(),
+            accounts.len()
+        );
+
+        let mut cursor = &data[8..];
+        let mut args = Vec::with_capacity(ix.args.len());
+        for field in &ix.args {
+            args.push((
+                field.name.clone(),
+                self.decode_value(&field.ty, &mut cursor)?,
+            ));
+        }
+
+        Ok(DecodedInstruction {
+            name: ix.name.clone(),
+            accounts: ix
+                .accounts
+                .iter()
+                .zip(accounts)
+                .map(|(item, (key, signer))| (item.name.clone(), *key, *signer))
+                .collect(),
+            args: IdlValue::Struct(args),
+        })
+    }
+
+    fn decode_value(&self, ty: &IdlType, cursor: &mut &[u8]) -> Result<IdlValue> {
+        Ok(match ty {
+            IdlType::Bool => IdlValue::Bool(take::<1>(cursor)?[0] != 0),
+            IdlType::U8 => IdlValue::Uint(take::<1>(cursor)?[0].into()),
+            IdlType::I8 => IdlValue::Int(i8::from_le_bytes(take(cursor)?).into()),
+            IdlType::U16 => IdlValue::Uint(u16::from_le_bytes(take(cursor)?).into()),
+            IdlType::I16 => IdlValue::Int(i16::from_le_bytes(take(cursor)?).into()),
+            IdlType::U32 => IdlValue::Uint(u32::from_le_bytes(take(cursor)?).into()),
+            IdlType::I32 => IdlValue::Int(i32::from_le_bytes(take(cursor)?).into()),
+            IdlType::U64 => IdlValue::Uint(u64::from_le_bytes(take(cursor)?).into()),
+            IdlType::I64 => IdlValue::Int(i64::from_le_bytes(take(cursor)?).into()),
+            IdlType::U128 => IdlValue::Uint(u128::from_le_bytes(take(cursor)?)),
+            IdlType::I128 => IdlValue::Int(i128::from_le_bytes(take(cursor)?)),
+            IdlType::Pubkey => IdlValue::Pubkey(Pubkey::new_from_array(take(cursor)?)),
+            IdlType::String => {
+                let bytes = take_vec(cursor)?;
+                IdlValue::String(String::from_utf8(bytes).context("invalid utf-8 string")?)
+            }
+            IdlType::Bytes => IdlValue::Bytes(take_vec(cursor)?),
+            IdlType::Option(inner) => match take::<1>(cursor)?[0] {
+                0 => IdlValue::Option(None),
+                1 => IdlValue::Option(Some(Box::new(self.decode_value(inner, cursor)?))),
+                other => bail!("invalid option tag {other}"),
+            },
+            IdlType::Vec(inner) => {
+                let len = u32::from_le_bytes(take(cursor)?) as usize;
+                (0..len)
+                    .map(|_| self.decode_value(inner, cursor))
+                    .collect::<Result<_>>()
+                    .map(IdlValue::List)?
+            }
+            IdlType::Array(inner, len) => (0..*len)
+                .map(|_| self.decode_value(inner, cursor))
+                .collect::<Result<_>>()
+                .map(IdlValue::List)?,
+            IdlType::Defined(name) => match self.type_def(name)? {
+                IdlTypeDef::Struct(fields) => {
+                    let mut out = Vec::with_capacity(fields.len());
+                    for field in fields {
+                        let value = self
+                            .decode_value(&field.ty, cursor)
+                            .with_context(|| format!("{name}.{}", field.name))?;
+                        out.push((field.name.clone(), value));
+                    }
+                    IdlValue::Struct(out)
+                }
+                IdlTypeDef::Enum(variants) => {
+                    let index = take::<1>(cursor)?[0];
+                    let variant = variants
+                        .get(index as usize)
+                        .ok_or_else(|| anyhow!("invalid {name} variant: {index}"))?;
+                    let fields = match &variant.fields {
+                        IdlVariantFields::Unit => None,
+                        IdlVariantFields::Named(fields) => {
+                            let mut out = Vec::with_capacity(fields.len());
+                            for field in fields {
+                                out.push((
+                                    field.name.clone(),
+                                    self.decode_value(&field.ty, cursor)?,
+                                ));
+                            }
+                            Some(Box::new(IdlValue::Struct(out)))
+                        }
+                        IdlVariantFields::Tuple(types) => Some(Box::new(IdlValue::List(
+                            types
+                                .iter()
+                                .map(|ty| self.decode_value(ty, cursor))
+                                .collect::<Result<_>>()?,
+                        ))),
+                    };
+                    IdlValue::Enum {
+                        name: variant.name.clone(),
+                        index,
+                        fields,
+                    }
+                }
+            },
+        })
+    }
+
+    fn encode_value(&self, ty: &IdlType, value: &IdlValue, out: &mut Vec<u8>) -> Result<()> {
+        match (ty, value) {
+            (IdlType::Bool, IdlValue::Bool(b)) => out.push(u8::from(*b)),
+            (IdlType::U8, IdlValue::Uint(v)) => out.push(u8::try_from(*v)?),
+            (IdlType::I8, IdlValue::Int(v)) => out.extend(i8::try_from(*v)?.to_le_bytes()),
+            (IdlType::U16, IdlValue::Uint(v)) => out.extend(u16::try_from(*v)?.to_le_bytes()),
+            (IdlType::I16, IdlValue::Int(v)) => out.extend(i16::try_from(*v)?.to_le_bytes()),
+            (IdlType::U32, IdlValue::Uint(v)) => out.extend(u32::try_from(*v)?.to_le_bytes()),
+            (IdlType::I32, IdlValue::Int(v)) => out.extend(i32::try_from(*v)?.to_le_bytes()),
+            (IdlType::U64, IdlValue::Uint(v)) => out.extend(u64::try_from(*v)?.to_le_bytes()),
+            (IdlType::I64, IdlValue::Int(v)) => out.extend(i64::try_from(*v)?.to_le_bytes()),
+            (IdlType::U128, IdlValue::Uint(v)) => out.extend(v.to_le_bytes()),
+            (IdlType::I128, IdlValue::Int(v)) => out.extend(v.to_le_bytes()),
+            (IdlType::Pubkey, IdlValue::Pubkey(k)) => out.extend_from_slice(k.as_ref()),
+            (IdlType::String, IdlValue::String(s)) => {
+                out.extend(u32::try_from(s.len())?.to_le_bytes());
+                out.extend_from_slice(s.as_bytes());
+            }
+            (IdlType::Bytes, IdlValue::Bytes(b)) => {
+                out.extend(u32::try_from(b.len())?.to_le_bytes());
+                out.extend_from_slice(b);
+            }
+            (IdlType::Option(_), IdlValue::Option(None)) => out.push(0),
+            (IdlType::Option(inner), IdlValue::Option(Some(v))) => {
+                out.push(1);
+                self.encode_value(inner, v, out)?;
+            }
+            (IdlType::Vec(inner), IdlValue::List(items)) => {
+                out.extend(u32::try_from(items.len())?.to_le_bytes());
+                for item in items {
+                    self.encode_value(inner, item, out)?;
+                }
+            }
+            (IdlType::Array(inner, len), IdlValue::List(items)) => {
+                ensure!(
+                    items.len() == *len,
+                    "expected {len} array items, got {}",
+                    items.len()
+                );
+                for item in items {
+                    self.encode_value(inner, item, out)?;
+                }
+            }
+            (IdlType::Defined(name), value) => match (self.type_def(name)?, value) {
+                (IdlTypeDef::Struct(fields), IdlValue::Struct(values)) => {
+                    for field in fields {
+                        let v = values
+                            .iter()
+                            .find(|(n, _)| *n == field.name)
+                            .map(|(_, v)| v)
+                            .ok_or_else(|| anyhow!("missing field {name}.{}", field.name))?;
+                        self.encode_value(&field.ty, v, out)
+                            .with_context(|| format!("{name}.{}", field.name))?;
+                    }
+                }
+                (
+                    IdlTypeDef::Enum(variants),
+                    IdlValue::Enum {
+                        name: v, fields, ..
+                    },
+                ) => {
+                    let index = variants
+                        .iter()
+                        .position(|variant| variant.name == *v)
+                        .ok_or_else(|| anyhow!("unknown {name} variant {v}"))?;
+                    out.push(u8::try_from(index)?);
+                    match (&variants[index].fields, fields.as_deref()) {
+                        (IdlVariantFields::Unit, None) => {}
+                        (IdlVariantFields::Named(defs), Some(IdlValue::Struct(values))) => {
+                            for def in defs {
+                                let v = values
+                                    .iter()
+                                    .find(|(n, _)| *n == def.name)
+                                    .map(|(_, v)| v)
+                                    .ok_or_else(|| anyhow!("missing field {name}::{v}"))?;
+                                self.encode_value(&def.ty, v, out)?;
+                            }
+                        }
+                        (IdlVariantFields::Tuple(types), Some(IdlValue::List(values))) => {
+                            ensure!(types.len() == values.len(), "{name}::{v} arity mismatch");
+                            for (ty, v) in types.iter().zip(values) {
+                                self.encode_value(ty, v, out)?;
+                            }
+                        }
+                        _ => bail!("{name}::{v} fields do not match the IDL"),
+                    }
+                }
+                _ => bail!("value does not match IDL type {name}"),
+            },
+            (ty, value) => bail!("value {value:?} does not match IDL type {ty:?}"),
+        }
+        Ok(())
+    }
+}
+
+impl IdlValue {
+    pub fn field(&self, name: &str) -> Result<&IdlValue> {
+        match self {
+            IdlValue::Struct(fields) => fields
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v)
+                .ok_or_else(|| anyhow!("missing field {name}")),
+            _ => bail!("expected a struct value when reading {name}"),
+        }
+    }
+
+    /// Like [`IdlValue::field`], but `None` when the struct lacks the field.
+    pub fn opt_field(&self, name: &str) -> Option<&IdlValue> {
+        match self {
+            IdlValue::Struct(fields) => fields.iter().find(|(n, _)| n == name).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_pubkey(&self) -> Result<Pubkey> {
+        match self {
+            IdlValue::Pubkey(k) => Ok(*k),
+            other => bail!("expected pubkey, got {other:?}"),
+        }
+    }
+
+    pub fn as_u64(&self) -> Result<u64> {
+        match self {
+            IdlValue::Uint(v) => Ok(u64::try_from(*v)?),
+            other => bail!("expected unsigned integer, got {other:?}"),
+        }
+    }
+
+    pub fn as_u16(&self) -> Result<u16> {
+        Ok(u16::try_from(self.as_u64()?)?)
+    }
+
+    pub fn as_u8(&self) -> Result<u8> {
+        Ok(u8::try_from(self.as_u64()?)?)
+    }
+
+    pub fn as_i64(&self) -> Result<i64> {
+        match self {
+            IdlValue::Int(v) => Ok(i64::try_from(*v)?),
+            other => bail!("expected signed integer, got {other:?}"),
+        }
+    }
+
+    pub fn variant_name(&self) -> Result<&str> {
+        match self {
+            IdlValue::Enum { name, .. } => Ok(name),
+            other => bail!("expected enum, got {other:?}"),
+        }
+    }
+
+    pub fn unit_variant(name: &str) -> IdlValue {
+        IdlValue::Enum {
+            name: name.to_string(),
+            index: 0,
+            fields: None,
+        }
+    }
+}
+
+impl From<u64> for IdlValue {
+    fn from(v: u64) -> Self {
+        IdlValue::Uint(v.into())
+    }
+}
+
+impl From<u16> for IdlValue {
+    fn from(v: u16) -> Self {
+        IdlValue::Uint(v.into())
+    }
+}
+
+impl From<u8> for IdlValue {
+    fn from(v: u8) -> Self {
+        IdlValue::Uint(v.into())
+    }
+}
+
+impl From<i64> for IdlValue {
+    fn from(v: i64) -> Self {
+        IdlValue::Int(v.into())
+    }
+}
+
+impl From<Pubkey> for IdlValue {
+    fn from(v: Pubkey) -> Self {
+        IdlValue::Pubkey(v)
+    }
+}
+
+/// First 8 bytes of `sha256("<namespace>:<name>")`, as Anchor derives them.
+pub fn sighash(namespace: &str, name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("{namespace}:{name}").as_bytes());
+    let mut out = [0u8; 8];
+    out.copy_from_slice(&hash[..8]);
+    out
+}
+
+fn array<'a>(json: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    json.get(key)
+        .and_then(Value::as_array)
+        .map(|v| v.iter())
+        .into_iter()
+        .flatten()
+}
+
+fn str_field<'a>(json: &'a Value, key: &str) -> Result<&'a str> {
+    json.get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("IDL entry is missing `{key}`"))
+}
+
+fn bool_field(json: &Value, keys: &[&str]) -> bool {
+    keys.iter()
+        .find_map(|k| json.get(*k).and_then(Value::as_bool))
+        .unwrap_or(false)
+}
+
+fn parse_discriminator(json: &Value) -> Result<[u8; 8]> {
+    let bytes: Vec<u8> = serde_json::from_value(json.clone()).context("invalid discriminator")?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("discriminator must be 8 bytes"))
+}
+
+fn parse_account_item(json: &Value) -> Result<IdlAccountItem> {
+    ensure!(
+        json.get("accounts").is_none(),
+        "nested account groups are not supported"
+    );
+    Ok(IdlAccountItem {
+        name: to_snake_case(str_field(json, "name")?),
+        writable: bool_field(json, &["writable", "isMut"]),
+        signer: bool_field(json, &["signer", "isSigner"]),
+        address: json
+            .get("address")
+            .and_then(Value::as_str)
+            .map(|s| s.parse::<Pubkey>())
+            .transpose()
+            .map_err(|e| anyhow!("invalid fixed account address: {e}"))?,
+    })
+}
+
+fn parse_field(json: &Value) -> Result<IdlField> {
+    Ok(IdlField {
+        name: to_snake_case(str_field(json, "name")?),
+        ty: parse_type(&json["type"])?,
+    })
+}
+
+fn parse_type_def(json: &Value) -> Result<IdlTypeDef> {
+    match str_field(json, "kind")? {
+        "struct" => Ok(IdlTypeDef::Struct(
+            array(json, "fields")
+                .map(parse_field)
+                .collect::<Result<_>>()?,
+        )),
+        "enum" => Ok(IdlTypeDef::Enum(
+            array(json, "variants")
+                .map(|v| {
+                    let fields = match v.get("fields").and_then(Value::as_array) {
+                        None => IdlVariantFields::Unit,
+                        Some(fields) if fields.iter().all(|f| f.get("name").is_some()) => {
+                            IdlVariantFields::Named(
+                                fields.iter().map(parse_field).collect::<Result<_>>()?,
+                            )
+                        }
+                        Some(fields) => IdlVariantFields::Tuple(
+                            fields.iter().map(parse_type).collect::<Result<_>>()?,
+                        ),
+                    };
+                    Ok(IdlEnumVariant {
+                        name: str_field(v, "name")?.to_string(),
+                        fields,
+                    })
+                })
+                .collect::<Result<_>>()?,
+        )),
+        other => bail!("unsupported IDL type kind {other}"),
+    }
+}
+
+fn parse_type(json: &Value) -> Result<IdlType> {
+    if let Some(name) = json.as_str() {
+        return Ok(match name {
+            "bool" => IdlType::Bool,
+            "u8" => IdlType::U8,
+            "i8" => IdlType::I8,
+            "u16" => IdlType::U16,
+            "i16" => IdlType::I16,
+            "u32" => IdlType::U32,
+            "i32" => IdlType::I32,
+            "u64" => IdlType::U64,
+            "i64" => IdlType::I64,
+            "u128" => IdlType::U128,
+            "i128" => IdlType::I128,
+            "pubkey" | "publicKey" => IdlType::Pubkey,
+            "string" => IdlType::String,
+            "bytes" => IdlType::Bytes,
+            other => bail!("unsupported IDL type {other}"),
+        });
+    }
+
+    if let Some(inner) = json.get("option") {
+        return Ok(IdlType::Option(Box::new(parse_type(inner)?)));
+    }
+    if let Some(inner) = json.get("vec") {
+        return Ok(IdlType::Vec(Box::new(parse_type(inner)?)));
+    }
+    if let Some(array) = json.get("array").and_then(Value::as_array) {
+        ensure!(array.len() == 2, "array type must be [type, len]");
+        let len = array[1]
+            .as_u64()
+            .ok_or_else(|| anyhow!("array length must be a number"))?;
+        return Ok(IdlType::Array(
+            Box::new(parse_type(&array[0])?),
+            len as usize,
+        ));
+    }
+    if let Some(defined) = json.get("defined") {
+        let name = defined
+            .as_str()
+            .or_else(|| defined.get("name").and_then(Value::as_str))
+            .ok_or_else(|| anyhow!("invalid defined type"))?;
+        return Ok(IdlType::Defined(name.to_string()));
+    }
+
+    bail!("unsupported IDL type {json}")
+}
+
+fn take<const N: usize>(cursor: &mut &[u8]) -> Result<[u8; N]> {
+    ensure!(cursor.len() >= N, "read past end of data");
+    let (head, rest) = cursor.split_at(N);
+    *cursor = rest;
+    Ok(head.try_into().expect("length checked"))
+}
+
+fn take_vec(cursor: &mut &[u8]) -> Result<Vec<u8>> {
+    let len = u32::from_le_bytes(take(cursor)?) as usize;
+    ensure!(cursor.len() >= len, "read past end of data");
+    let (head, rest) = cursor.split_at(len);
+    *cursor = rest;
+    Ok(head.to_vec())
+}
+
+/// Legacy IDLs use camelCase names; the backend looks everything up in snake_case.
+fn to_snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
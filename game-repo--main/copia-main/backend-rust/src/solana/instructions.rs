@@ -0,0 +1,389 @@
+//! Game program instructions, encoded from the IDL.
+//!
+//! The backend fills accounts by IDL name, so an IDL is compatible as long as
+//! every account it lists is one the backend knows how to supply (or has a fixed
+//! address) and every arg is one the backend sets. [`verify_idl`] checks exactly
+//! that at startup.
+
+use std::io::Read;
+
+use anyhow::{bail, ensure, Context, Result};
+use flate2::read::ZlibDecoder;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::solana::{
+    game_account::{
+        idl_game_layout, GameLayout, GAME_ACCOUNT, GAME_FIELDS, GAME_MODES, GAME_STATES,
+        GAME_V2_FIELDS,
+    },
+    gateway::ChainGateway,
+    idl::IdlField,
+    idl::{Idl, IdlInstruction, IdlType, IdlTypeDef, IdlValue},
+    pda::{game_address, program_data_address, vault_address},
+};
+
+/// An instruction the backend builds, with the accounts it supplies and the
+/// args it sets.
+struct RequiredInstruction {
+    name: &'static str,
+    accounts: &'static [&'static str],
+    args: &'static [(&'static str, IdlType)],
+}
+
+const REQUIRED_INSTRUCTIONS: &[RequiredInstruction] = &[
+    RequiredInstruction {
+        name: "create_game",
+        accounts: &[
+            "player1",
+            "authority",
+            "program",
+            "program_data",
+            "game",
+            "vault",
+        ],
+        args: &[("entry_amount", IdlType::U64), ("match_id", IdlType::U64)],
+    },
+    RequiredInstruction {
+        name: "join_game",
+        accounts: &["player2", "game", "vault"],
+        args: &[],
+    },
+    RequiredInstruction {
+        name: "settle_game",
+        accounts: &[
+            "game",
+            "vault",
+            "winner",
+            "program",
+            "program_data",
+            "authority",
+        ],
+        args: &[("winner", IdlType::Pubkey)],
+    },
+    RequiredInstruction {
+        name: "force_refund",
+        accounts: &[
+            "game",
+            "vault",
+            "player1",
+            "player2",
+            "program",
+            "program_data",
+            "authority",
+        ],
+        args: &[],
+    },
+];
+
+/// Instructions only present once the program writes V2 `Game` accounts.
+const V2_INSTRUCTIONS: &[RequiredInstruction] = &[RequiredInstruction {
+    name: "settle_game_v2",
+    accounts: &[
+        "game",
+        "vault",
+        "winner",
+        "fee_recipient",
+        "program",
+        "program_data",
+        "authority",
+    ],
+    args: &[("winner", IdlType::Pubkey)],
+}];
+
+/// Whether the IDL's `Game` carries the V2 fields (and so V2 instructions).
+fn has_v2_layout(idl: &Idl) -> bool {
+    idl_game_layout(idl) == GameLayout::V2
+}
+
+fn expected_instructions(idl: &Idl) -> impl Iterator<Item = &'static RequiredInstruction> {
+    let v2: &[RequiredInstruction] = if has_v2_layout(idl) {
+        V2_INSTRUCTIONS
+    } else {
+        &[]
+    };
+    REQUIRED_INSTRUCTIONS.iter().chain(v2)
+}
+
+pub fn create_game_ix(
+    idl: &Idl,
+    program_id: Pubkey,
+    player1: Pubkey,
+    authority: Pubkey,
+    entry_amount: u64,
+    match_id: u64,
+) -> Result<Instruction> {
+    let (game, _) = game_address(&program_id, &player1, &authority, match_id);
+    let (vault, _) = vault_address(&program_id, &game);
+    idl.build_instruction(
+        program_id,
+        "create_game",
+        &[
+            ("player1", player1),
+            ("authority", authority),
+            ("program", program_id),
+            ("program_data", program_data_address(&program_id)),
+            ("game", game),
+            ("vault", vault),
+        ],
+        &[
+            ("entry_amount", entry_amount.into()),
+            ("match_id", match_id.into()),
+        ],
+    )
+}
+
+pub fn join_game_ix(
+    idl: &Idl,
+    program_id: Pubkey,
+    player2: Pubkey,
+    game: Pubkey,
+) -> Result<Instruction> {
+    let (vault, _) = vault_address(&program_id, &game);
+    idl.build_instruction(
+        program_id,
+        "join_game",
+        &[("player2", player2), ("game", game), ("vault", vault)],
+        &[],
+    )
+}
+
+pub fn settle_game_ix(
+    idl: &Idl,
+    program_id: Pubkey,
+    game: Pubkey,
+    vault: Pubkey,
+    winner: Pubkey,
+    authority: Pubkey,
+) -> Result<Instruction> {
+    idl.build_instruction(
+        program_id,
+        "settle_game",
+        &[
+            ("game", game),
+            ("vault", vault),
+            ("winner", winner),
+            ("program", program_id),
+            ("program_data", program_data_address(&program_id)),
+            ("authority", authority),
+        ],
+        &[("winner", IdlValue::Pubkey(winner))],
+    )
+}
+
+pub fn settle_game_v2_ix(
+    idl: &Idl,
+    program_id: Pubkey,
+    game: Pubkey,
+    vault: Pubkey,
+    winner: Pubkey,
+    fee_recipient: Pubkey,
+    authority: Pubkey,
+) -> Result<Instruction> {
+    idl.build_instruction(
+        program_id,
+        "settle_game_v2",
+        &[
+            ("game", game),
+            ("vault", vault),
+            ("winner", winner),
+            ("fee_recipient", fee_recipient),
+            ("program", program_id),
+            ("program_data", program_data_address(&program_id)),
+            ("authority", authority),
+        ],
+        &[("winner", IdlValue::Pubkey(winner))],
+    )
+}
+
+pub fn force_refund_ix(
+    idl: &Idl,
+    program_id: Pubkey,
+    game: Pubkey,
+    vault: Pubkey,
+    player1: Pubkey,
+    player2: Pubkey,
+    authority: Pubkey,
+) -> Result<Instruction> {
+    idl.build_instruction(
+        program_id,
+        "force_refund",
+        &[
+            ("game", game),
+            ("vault", vault),
+            ("player1", player1),
+            ("player2", player2),
+            ("program", program_id),
+            ("program_data", program_data_address(&program_id)),
+            ("authority", authority),
+        ],
+        &[],
+    )
+}
+
+/// Confirms the IDL describes the program the backend was written against.
+pub fn verify_idl(idl: &Idl, program_id: &Pubkey) -> Result<()> {
+    if let Some(address) = idl.address {
+        ensure!(
+            address == *program_id,
+            "IDL address {address} does not match PROGRAM_ID {program_id}"
+        );
+    }
+
+    for required in expected_instructions(idl) {
+        let (name, known_accounts, expected_args) =
+            (required.name, required.accounts, required.args);
+        let ix = idl.instruction(name)?;
+        for account in &ix.accounts {
+            ensure!(
+                account.address.is_some() || known_accounts.contains(&account.name.as_str()),
+                "IDL instruction {name} needs account `{}` the backend cannot supply",
+                account.name
+            );
+        }
+        ensure!(
+            ix.args.len() == expected_args.len(),
+            "IDL instruction {name} has {} args, backend expects {}",
+            ix.args.len(),
+            expected_args.len()
+        );
+        for (arg, (expected_name, expected_ty)) in ix.args.iter().zip(expected_args.iter()) {
+            ensure!(
+                arg.name == *expected_name && arg.ty == *expected_ty,
+                "IDL instruction {name} arg `{}: {:?}` does not match expected `{expected_name}: {expected_ty:?}`",
+                arg.name,
+                arg.ty
+            );
+        }
+    }
+
+    idl.account(GAME_ACCOUNT)?;
+    let IdlTypeDef::Struct(fields) = idl.type_def(GAME_ACCOUNT)? else {
+        bail!("IDL type {GAME_ACCOUNT} is not a struct");
+    };
+    for required in GAME_FIELDS {
+        ensure!(
+            fields.iter().any(|f| f.name == *required),
+            "IDL {GAME_ACCOUNT} is missing field `{required}`"
+        );
+    }
+
+    check_enum_field(idl, fields, "state", GAME_STATES)?;
+
+    if has_v2_layout(idl) {
+        // Older accounts are decoded as a prefix of the current layout, so V2
+        // fields must all come after the V1 ones.
+        ensure!(
+            fields.len() >= GAME_FIELDS.len() + GAME_V2_FIELDS.len(),
+            "IDL {GAME_ACCOUNT} must end with the V2 fields {GAME_V2_FIELDS:?}"
+        );
+        let first_v2 = fields.len() - GAME_V2_FIELDS.len();
+        let names: Vec<&str> = fields.iter().map(|f| f.name.as_str()).collect();
+        ensure!(
+            names[first_v2..] == *GAME_V2_FIELDS
+                && GAME_FIELDS.iter().all(|f| names[..first_v2].contains(f)),
+            "IDL {GAME_ACCOUNT} must end with the V2 fields {GAME_V2_FIELDS:?}"
+        );
+        check_enum_field(idl, fields, "game_mode", GAME_MODES)?;
+    }
+
+    Ok(())
+}
+
+fn check_enum_field(idl: &Idl, fields: &[IdlField], field: &str, expected: &[&str]) -> Result<()> {
+    let ty = fields
+        .iter()
+        .find(|f| f.name == field)
+        .map(|f| &f.ty)
+        .with_context(|| format!("IDL {GAME_ACCOUNT}.{field} is missing"))?;
+    let IdlType::Defined(enum_name) = ty else {
+        bail!("IDL {GAME_ACCOUNT}.{field} is not an enum");
+    };
+    let IdlTypeDef::Enum(variants) = idl.type_def(enum_name)? else {
+        bail!("IDL type {enum_name} is not an enum");
+    };
+    let variant_names: Vec<&str> = variants.iter().map(|v| v.name.as_str()).collect();
+    ensure!(
+        variant_names.starts_with(expected),
+        "IDL {enum_name} variants {variant_names:?} do not start with {expected:?}"
+    );
+    Ok(())
+}
+
+/// Confirms `program_id` is a deployed executable and, when the program publishes
+/// its IDL on-chain, that the instructions and `Game` account the backend uses are
+/// encoded the same way as in `idl`.
+pub async fn verify_deployed_program(
+    chain: &dyn ChainGateway,
+    idl: &Idl,
+    program_id: &Pubkey,
+) -> Result<()> {
+    let account = chain
+        .get_account(program_id)
+        .await
+        .with_context(|| format!("program {program_id} is not deployed"))?;
+    ensure!(
+        account.executable,
+        "program account {program_id} is not executable"
+    );
+
+    let idl_address = onchain_idl_address(program_id)?;
+    let Some(idl_account) = chain.get_optional_account(&idl_address).await? else {
+        tracing::info!(%program_id, "program has no on-chain IDL; skipping IDL comparison");
+        return Ok(());
+    };
+    let deployed = decode_onchain_idl(&idl_account.data)
+        .with_context(|| format!("failed to read on-chain IDL {idl_address}"))?;
+
+    for RequiredInstruction { name, .. } in expected_instructions(idl) {
+        let local = idl.instruction(name)?;
+        let remote = deployed
+            .instruction(name)
+            .with_context(|| format!("deployed program has no instruction {name}"))?;
+        ensure!(
+            local.discriminator == remote.discriminator,
+            "instruction {name} discriminator differs from the deployed program"
+        );
+        let layout = |ix: &IdlInstruction| {
+            ix.accounts
+                .iter()
+                .map(|a| (a.name.clone(), a.writable, a.signer))
+                .collect::<Vec<_>>()
+        };
+        ensure!(
+            layout(local) == layout(remote),
+            "instruction {name} accounts differ from the deployed program"
+        );
+        ensure!(
+            local.args == remote.args,
+            "instruction {name} args differ from the deployed program"
+        );
+    }
+    ensure!(
+        idl.account(GAME_ACCOUNT)?.discriminator == deployed.account(GAME_ACCOUNT)?.discriminator,
+        "{GAME_ACCOUNT} discriminator differs from the deployed program"
+    );
+
+    Ok(())
+}
+
+/// Address `anchor idl init` writes the program's IDL to.
+fn onchain_idl_address(program_id: &Pubkey) -> Result<Pubkey> {
+    let (base, _) = Pubkey::find_program_address(&[], program_id);
+    Pubkey::create_with_seed(&base, "anchor:idl", program_id)
+        .context("failed to derive on-chain IDL address")
+}
+
+/// Anchor `IdlAccount`: discriminator, authority, `u32` length, zlib-compressed JSON.
+fn decode_onchain_idl(data: &[u8]) -> Result<Idl> {
+    ensure!(data.len() >= 44, "IDL account data too short");
+    let len = u32::from_le_bytes(data[40..44].try_into().unwrap()) as usize;
+    let compressed = data
+        .get(44..44 + len)
+        .context("IDL account data shorter than its length prefix")?;
+
+    let mut json = String::new();
+    ZlibDecoder::new(compressed)
+        .read_to_string(&mut json)
+        .context("failed to inflate on-chain IDL")?;
+    Idl::parse(&json)
+}
@@ -0,0 +1,133 @@
+//! Passphrase-encrypted keypair files.
+//!
+//! The 64-byte keypair is sealed with AES-256-GCM under a key derived with
+//! PBKDF2-HMAC-SHA256; the pubkey is stored in the clear and authenticated as
+//! associated data, so a keystore can be identified without the passphrase.
+
+use std::num::NonZeroU32;
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::{Keypair, Signer};
+
+const KEYSTORE_VERSION: u32 = 1;
+const KDF: &str = "pbkdf2-hmac-sha256";
+const CIPHER: &str = "aes-256-gcm";
+const PBKDF2_ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    pub pubkey: String,
+    pub kdf: String,
+    pub iterations: u32,
+    pub salt: String,
+    pub cipher: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+pub fn encrypt_keypair(keypair: &Keypair, passphrase: &str) -> Result<Keystore> {
+    if passphrase.is_empty() {
+        bail!("keystore passphrase must not be empty");
+    }
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .map_err(|_| anyhow!("failed to generate salt"))?;
+    rng.fill(&mut nonce)
+        .map_err(|_| anyhow!("failed to generate nonce"))?;
+
+    let pubkey = keypair.pubkey().to_string();
+    let key = derive_key(passphrase, &salt, PBKDF2_ITERATIONS)?;
+    let mut sealed = keypair.to_bytes().to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(pubkey.as_bytes()),
+        &mut sealed,
+    )
+    .map_err(|_| anyhow!("failed to encrypt keypair"))?;
+
+    Ok(Keystore {
+        version: KEYSTORE_VERSION,
+        pubkey,
+        kdf: KDF.into(),
+        iterations: PBKDF2_ITERATIONS,
+        salt: BASE64.encode(salt),
+        cipher: CIPHER.into(),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(sealed),
+    })
+}
+
+pub fn decrypt_keystore(keystore: &Keystore, passphrase: &str) -> Result<Keypair> {
+    if keystore.version != KEYSTORE_VERSION || keystore.kdf != KDF || keystore.cipher != CIPHER {
+        bail!(
+            "unsupported keystore (version {}, {}, {})",
+            keystore.version,
+            keystore.kdf,
+            keystore.cipher
+        );
+    }
+    let salt = BASE64
+        .decode(&keystore.salt)
+        .context("invalid keystore salt")?;
+    let nonce: [u8; NONCE_LEN] = BASE64
+        .decode(&keystore.nonce)
+        .context("invalid keystore nonce")?
+        .try_into()
+        .map_err(|_| anyhow!("invalid keystore nonce length"))?;
+    let mut sealed = BASE64
+        .decode(&keystore.ciphertext)
+        .context("invalid keystore ciphertext")?;
+
+    let key = derive_key(passphrase, &salt, keystore.iterations)?;
+    let plain = key
+        .open_in_place(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(keystore.pubkey.as_bytes()),
+            &mut sealed,
+        )
+        .map_err(|_| anyhow!("wrong passphrase or corrupted keystore"))?;
+    let keypair = Keypair::try_from(&plain[..]).context("keystore holds an invalid keypair")?;
+    if keypair.pubkey().to_string() != keystore.pubkey {
+        bail!("keystore pubkey does not match its keypair");
+    }
+    Ok(keypair)
+}
+
+pub fn read_keystore(path: &str, passphrase: &str) -> Result<Keypair> {
+    let raw =
+        std::fs::read_to_string(path).with_context(|| format!("failed to read keystore {path}"))?;
+    let keystore: Keystore =
+        serde_json::from_str(&raw).with_context(|| format!("invalid keystore {path}"))?;
+    decrypt_keystore(&keystore, passphrase).with_context(|| format!("failed to unlock {path}"))
+}
+
+pub fn write_keystore(path: &str, keystore: &Keystore) -> Result<()> {
+    std::fs::write(path, serde_json::to_vec_pretty(keystore)?)
+        .with_context(|| format!("failed to write keystore {path}"))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey> {
+    let iterations =
+        NonZeroU32::new(iterations).ok_or_else(|| anyhow!("keystore iterations must be > 0"))?;
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| anyhow!("invalid derived key"))?;
+    Ok(LessSafeKey::new(key))
+}
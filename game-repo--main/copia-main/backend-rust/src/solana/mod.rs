@@ -1,3 +1,11 @@
 pub mod client;
+pub mod fake_chain;
 pub mod game_account;
+pub mod gateway;
+pub mod idl;
+pub mod instructions;
+pub mod keystore;
 pub mod pda;
+pub mod remote_signer;
+pub mod rpc_pool;
+pub mod signer;
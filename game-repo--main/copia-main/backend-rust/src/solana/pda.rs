@@ -4,6 +4,7 @@ use std::str::FromStr;
 
 use anyhow::{bail, Context, Result};
 use solana_sdk::pubkey::Pubkey;
+use solana_sdk_ids::bpf_loader_upgradeable;
 
 const GAME_SEED: &[u8] = b"game";
 const VAULT_SEED: &[u8] = b"vault";
@@ -27,23 +28,38 @@ pub fn derive_match_pdas(
     let program_id = Pubkey::from_str(program_id).context("invalid PROGRAM_ID")?;
     let authority = Pubkey::from_str(authority_pubkey).context("invalid AUTHORITY_PUBKEY")?;
     let player1 = Pubkey::from_str(player1_pubkey).context("invalid player1_pubkey")?;
-    let match_id_u64 = match_id as u64;
-    let match_id_bytes = match_id_u64.to_le_bytes();
 
-    let (game_pda, _) = Pubkey::find_program_address(
+    let (game_pda, _) = game_address(&program_id, &player1, &authority, match_id as u64);
+    let (vault_pda, _) = vault_address(&program_id, &game_pda);
+
+    Ok(MatchPdas {
+        game_pda: game_pda.to_string(),
+        vault_pda: vault_pda.to_string(),
+    })
+}
+
+pub fn game_address(
+    program_id: &Pubkey,
+    player1: &Pubkey,
+    authority: &Pubkey,
+    match_id: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
         &[
             GAME_SEED,
             player1.as_ref(),
             authority.as_ref(),
-            &match_id_bytes,
+            &match_id.to_le_bytes(),
         ],
-        &program_id,
-    );
-    let (vault_pda, _) =
-        Pubkey::find_program_address(&[VAULT_SEED, game_pda.as_ref()], &program_id);
+        program_id,
+    )
+}
 
-    Ok(MatchPdas {
-        game_pda: game_pda.to_string(),
-        vault_pda: vault_pda.to_string(),
-    })
+pub fn vault_address(program_id: &Pubkey, game_pda: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_SEED, game_pda.as_ref()], program_id)
+}
+
+/// ProgramData account of an upgradeable program.
+pub fn program_data_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0
 }
@@ -0,0 +1,297 @@
+//! Signing through a separate process that holds the key.
+//!
+//! The protocol is one JSON request and one JSON response:
+//! `{"pubkey": "<base58>", "message": "<base64>"}` →
+//! `{"signature": "<base58>"}` or `{"error": "..."}`. Over HTTP it is
+//! `POST <endpoint>/v1/sign`; over a Unix socket (`unix:/path`) each request and
+//! response is a single line. [`serve`] is a reference signer process.
+
+use std::{net::SocketAddr, str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{message::Message, pubkey::Pubkey, signature::Signature};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+
+use crate::solana::{idl::Idl, signer::TransactionSigner};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const UNIX_PREFIX: &str = "unix:";
+
+/// The only instructions the authority signs: settling and refunding games.
+pub const SIGNABLE_INSTRUCTIONS: &[&str] = &["settle_game", "settle_game_v2", "force_refund"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignRequest {
+    pub pubkey: String,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SignResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+enum Transport {
+    Http {
+        client: reqwest::Client,
+        url: String,
+    },
+    Unix {
+        path: String,
+    },
+}
+
+pub struct RemoteSigner {
+    pubkey: Pubkey,
+    transport: Transport,
+}
+
+impl RemoteSigner {
+    pub fn new(endpoint: &str, pubkey: Pubkey) -> Result<Self> {
+        let transport = match endpoint.strip_prefix(UNIX_PREFIX) {
+            Some(path) => Transport::Unix { path: path.into() },
+            None => Transport::Http {
+                client: reqwest::Client::builder()
+                    .timeout(REQUEST_TIMEOUT)
+                    .build()?,
+                url: format!("{}/v1/sign", endpoint.trim_end_matches('/')),
+            },
+        };
+        Ok(Self { pubkey, transport })
+    }
+
+    async fn request(&self, request: &SignRequest) -> Result<SignResponse> {
+        match &self.transport {
+            Transport::Http { client, url } => {
+                let response = client.post(url).json(request).send().await?;
+                let status = response.status();
+                let body: SignResponse = response
+                    .json()
+                    .await
+                    .with_context(|| format!("remote signer answered {status}"))?;
+                Ok(body)
+            }
+            Transport::Unix { path } => {
+                tokio::time::timeout(REQUEST_TIMEOUT, unix_request(path, request))
+                    .await
+                    .map_err(|_| anyhow!("remote signer timed out"))?
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for RemoteSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        let response = self
+            .request(&SignRequest {
+                pubkey: self.pubkey.to_string(),
+                message: BASE64.encode(message),
+            })
+            .await?;
+        if let Some(error) = response.error {
+            bail!("remote signer refused: {error}");
+        }
+        let signature = response
+            .signature
+            .ok_or_else(|| anyhow!("remote signer returned no signature"))?;
+        let signature =
+            Signature::from_str(&signature).context("remote signer returned a bad signature")?;
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            bail!("remote signer returned a signature that does not verify");
+        }
+        Ok(signature)
+    }
+}
+
+async fn unix_request(path: &str, request: &SignRequest) -> Result<SignResponse> {
+    let stream = UnixStream::connect(path)
+        .await
+        .with_context(|| format!("failed to connect to signer socket {path}"))?;
+    let (read, mut write) = stream.into_split();
+    let mut line = serde_json::to_vec(request)?;
+    line.push(b'\n');
+    write.write_all(&line).await?;
+
+    let mut response = String::new();
+    BufReader::new(read).read_line(&mut response).await?;
+    serde_json::from_str(&response).context("invalid response from signer socket")
+}
+
+/// What the reference signer agrees to sign.
+#[derive(Clone)]
+struct SignerService {
+    signer: Arc<dyn TransactionSigner>,
+    /// Every instruction of a signed message must call this program...
+    program_id: Pubkey,
+    /// ...decode through its IDL as one of [`SIGNABLE_INSTRUCTIONS`], and name
+    /// the signer as its `authority`.
+    idl: Arc<Idl>,
+}
+
+impl SignerService {
+    async fn handle(&self, request: &SignRequest) -> SignResponse {
+        match self.sign(request).await {
+            Ok(signature) => SignResponse {
+                signature: Some(signature.to_string()),
+                error: None,
+            },
+            Err(e) => {
+                tracing::warn!("signer refused request: {e:#}");
+                SignResponse {
+                    signature: None,
+                    error: Some(format!("{e:#}")),
+                }
+            }
+        }
+    }
+
+    async fn sign(&self, request: &SignRequest) -> Result<Signature> {
+        if request.pubkey != self.signer.pubkey().to_string() {
+            bail!("this signer does not hold {}", request.pubkey);
+        }
+        let bytes = BASE64
+            .decode(&request.message)
+            .context("message is not base64")?;
+        let message: Message =
+            bincode::deserialize(&bytes).context("message is not a legacy transaction message")?;
+        if message.instructions.is_empty() {
+            bail!("message has no instructions");
+        }
+        for ix in &message.instructions {
+            let program = message
+                .account_keys
+                .get(ix.program_id_index as usize)
+                .ok_or_else(|| anyhow!("malformed message"))?;
+            if *program != self.program_id {
+                bail!("message calls program {program}, which this signer does not sign for");
+            }
+            let accounts = ix
+                .accounts
+                .iter()
+                .map(|&index| {
+                    let key = message
+                        .account_keys
+                        .get(index as usize)
+                        .ok_or_else(|| anyhow!("malformed message"))?;
+                    Ok((*key, message.is_signer(index as usize)))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let decoded = self
+                .idl
+                .decode_instruction(&ix.data, &accounts)
+                .context("instruction does not decode through the program IDL")?;
+            if !SIGNABLE_INSTRUCTIONS.contains(&decoded.name.as_str()) {
+                bail!("this signer does not sign {} instructions", decoded.name);
+            }
+            let authority = decoded
+                .accounts
+                .iter()
+                .find(|(name, _, _)| name == "authority")
+                .map(|(_, key, _)| *key);
+            if authority != Some(self.signer.pubkey()) {
+                bail!(
+                    "{} does not name this signer as its authority",
+                    decoded.name
+                );
+            }
+        }
+        self.signer.sign_message(&bytes).await
+    }
+}
+
+/// Runs a signer process for `signer` on `listen` (`unix:/path`, or a loopback
+/// `host:port`: the HTTP endpoint is unauthenticated), signing only settle and
+/// refund instructions of `program_id` as described by `idl`.
+pub async fn serve(
+    listen: &str,
+    signer: Arc<dyn TransactionSigner>,
+    program_id: Pubkey,
+    idl: Idl,
+) -> Result<()> {
+    let service = SignerService {
+        signer,
+        program_id,
+        idl: Arc::new(idl),
+    };
+
+    if let Some(path) = listen.strip_prefix(UNIX_PREFIX) {
+        // A stale socket from a previous run would make bind fail.
+        let _ = std::fs::remove_file(path);
+        let listener =
+            UnixListener::bind(path).with_context(|| format!("failed to bind {path}"))?;
+        tracing::info!(socket = path, pubkey = %service.signer.pubkey(), "signer listening");
+        return serve_unix(listener, service).await;
+    }
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host(listen)
+        .await
+        .with_context(|| format!("invalid signer address {listen}"))?
+        .collect();
+    if addrs.is_empty() || !addrs.iter().all(|addr| addr.ip().is_loopback()) {
+        bail!("signer must listen on a loopback address or a Unix socket, not {listen}");
+    }
+    let listener = tokio::net::TcpListener::bind(addrs.as_slice())
+        .await
+        .with_context(|| format!("failed to bind {listen}"))?;
+    tracing::info!(addr = listen, pubkey = %service.signer.pubkey(), "signer listening");
+    let app = Router::new()
+        .route("/v1/sign", post(http_sign))
+        .with_state(service);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn http_sign(
+    State(service): State<SignerService>,
+    Json(request): Json<SignRequest>,
+) -> (StatusCode, Json<SignResponse>) {
+    let response = service.handle(&request).await;
+    let status = if response.error.is_some() {
+        StatusCode::FORBIDDEN
+    } else {
+        StatusCode::OK
+    };
+    (status, Json(response))
+}
+
+async fn serve_unix(listener: UnixListener, service: SignerService) -> Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let service = service.clone();
+        tokio::spawn(async move {
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let response = match serde_json::from_str::<SignRequest>(&line) {
+                    Ok(request) => service.handle(&request).await,
+                    Err(e) => SignResponse {
+                        signature: None,
+                        error: Some(format!("invalid request: {e}")),
+                    },
+                };
+                let Ok(mut out) = serde_json::to_vec(&response) else {
+                    break;
+                };
+                out.push(b'\n');
+                if write.write_all(&out).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,354 @@
+//! Shared Solana RPC gateway with multiple endpoints.
+//!
+//! Every call is routed to the healthiest endpoint first and fails over to the next
+//! one on transport errors, timeouts or unhealthy-node responses. Errors that come
+//! back as a well-formed RPC response (e.g. preflight failure) are returned to the
+//! caller as-is, since another endpoint would answer the same way.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    nonblocking::rpc_client::RpcClient,
+    rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+    rpc_request::RpcError,
+};
+use solana_sdk::{
+    account::Account, hash::Hash, pubkey::Pubkey, signature::Signature, transaction::Transaction,
+};
+use solana_transaction_status_client_types::TransactionStatus;
+
+use crate::{metrics::metrics, solana::gateway::ChainGateway};
+
+const SCORE_EWMA_ALPHA: f64 = 0.3;
+const LATENCY_EWMA_ALPHA: f64 = 0.3;
+const FAILURES_BEFORE_COOLDOWN: u32 = 3;
+const MAX_COOLDOWN_SECONDS: u64 = 60;
+/// Endpoints this many slots behind the best known slot are treated as degraded.
+const MAX_SLOT_LAG: u64 = 50;
+
+type RpcFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, ClientError>> + Send + 'a>>;
+
+#[derive(Debug, Clone)]
+pub struct RpcPoolOptions {
+    pub call_timeout: Duration,
+    pub slow_call_threshold: Duration,
+}
+
+pub struct RpcPool {
+    endpoints: Vec<RpcEndpoint>,
+    options: RpcPoolOptions,
+}
+
+struct RpcEndpoint {
+    url: String,
+    client: RpcClient,
+    health: Mutex<EndpointHealth>,
+}
+
+#[derive(Debug, Clone)]
+struct EndpointHealth {
+    /// Exponentially weighted success rate in `[0, 1]`.
+    score: f64,
+    latency_ms: f64,
+    consecutive_failures: u32,
+    cooldown_until: Option<Instant>,
+    last_slot: Option<u64>,
+    last_error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RpcEndpointStatus {
+    pub url: String,
+    pub score: f64,
+    pub latency_ms: f64,
+    pub consecutive_failures: u32,
+    pub cooling_down: bool,
+    pub last_slot: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl Default for EndpointHealth {
+    fn default() -> Self {
+        Self {
+            score: 1.0,
+            latency_ms: 0.0,
+            consecutive_failures: 0,
+            cooldown_until: None,
+            last_slot: None,
+            last_error: None,
+        }
+    }
+}
+
+impl RpcPool {
+    pub fn new(urls: &[String], options: RpcPoolOptions) -> Result<Self> {
+        if urls.is_empty() {
+            bail!("at least one Solana RPC endpoint is required");
+        }
+
+        let endpoints = urls
+            .iter()
+            .map(|url| RpcEndpoint {
+                url: url.clone(),
+                client: RpcClient::new_with_timeout(url.clone(), options.call_timeout),
+                health: Mutex::new(EndpointHealth::default()),
+            })
+            .collect();
+
+        Ok(Self { endpoints, options })
+    }
+
+    /// Probes every endpoint with `getSlot` and updates its health, independent of
+    /// regular traffic, so a recovered endpoint is picked up again.
+    pub async fn check_health(&self) {
+        for endpoint in &self.endpoints {
+            let started = Instant::now();
+            let result =
+                tokio::time::timeout(self.options.call_timeout, endpoint.client.get_slot()).await;
+            let elapsed = started.elapsed();
+
+            match result {
+                Ok(Ok(slot)) => {
+                    self.record_success(endpoint, elapsed);
+                    endpoint.health.lock().unwrap().last_slot = Some(slot);
+                }
+                Ok(Err(e)) => self.record_failure(endpoint, &format!("getSlot failed: {e}")),
+                Err(_) => self.record_failure(endpoint, "getSlot timed out"),
+            }
+        }
+
+        let best_slot = self
+            .endpoints
+            .iter()
+            .filter_map(|e| e.health.lock().unwrap().last_slot)
+            .max();
+        if let Some(best_slot) = best_slot {
+            for endpoint in &self.endpoints {
+                let mut health = endpoint.health.lock().unwrap();
+                if let Some(slot) = health.last_slot {
+                    let lag = best_slot.saturating_sub(slot);
+                    if lag > MAX_SLOT_LAG {
+                        health.score *= 1.0 - SCORE_EWMA_ALPHA;
+                        health.last_error = Some(format!("endpoint is {lag} slots behind"));
+                        tracing::warn!(url = %endpoint.url, lag, "rpc endpoint is lagging");
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn status(&self) -> Vec<RpcEndpointStatus> {
+        let now = Instant::now();
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let health = endpoint.health.lock().unwrap().clone();
+                RpcEndpointStatus {
+                    url: endpoint.url.clone(),
+                    score: health.score,
+                    latency_ms: health.latency_ms,
+                    consecutive_failures: health.consecutive_failures,
+                    cooling_down: health.cooldown_until.is_some_and(|until| until > now),
+                    last_slot: health.last_slot,
+                    last_error: health.last_error,
+                }
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "rpc", skip_all, fields(method))]
+    async fn call<'a, T, F>(&'a self, method: &'static str, f: F) -> Result<T>
+    where
+        F: Fn(&'a RpcClient) -> RpcFuture<'a, T>,
+    {
+        let mut last_error = None;
+
+        for endpoint in self.ranked_endpoints() {
+            let started = Instant::now();
+            let result = tokio::time::timeout(self.options.call_timeout, f(&endpoint.client)).await;
+            let elapsed = started.elapsed();
+            let m = metrics();
+            m.rpc_call_duration
+                .with_label_values(&[method])
+                .observe(elapsed.as_secs_f64());
+
+            match result {
+                Ok(Ok(value)) => {
+                    self.record_success(endpoint, elapsed);
+                    return Ok(value);
+                }
+                Ok(Err(e)) if is_endpoint_failure(&e) => {
+                    m.rpc_call_errors
+                        .with_label_values(&[method, "transport"])
+                        .inc();
+                    let message = format!("{method} via {} failed: {e}", endpoint.url);
+                    self.record_failure(endpoint, &message);
+                    last_error = Some(anyhow!(message));
+                }
+                Ok(Err(e)) => {
+                    // The endpoint answered; the request itself was rejected.
+                    m.rpc_call_errors
+                        .with_label_values(&[method, "rejected"])
+                        .inc();
+                    self.record_success(endpoint, elapsed);
+                    return Err(e)
+                        .with_context(|| format!("{method} rejected by {}", endpoint.url));
+                }
+                Err(_) => {
+                    m.rpc_call_errors
+                        .with_label_values(&[method, "timeout"])
+                        .inc();
+                    let message = format!(
+                        "{method} via {} timed out after {}ms",
+                        endpoint.url,
+                        self.options.call_timeout.as_millis()
+                    );
+                    self.record_failure(endpoint, &message);
+                    last_error = Some(anyhow!(message));
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("no Solana RPC endpoints configured")))
+            .with_context(|| format!("{method} failed on all RPC endpoints"))
+    }
+
+    /// Endpoints out of cooldown first, then by score and latency.
+    fn ranked_endpoints(&self) -> Vec<&RpcEndpoint> {
+        let now = Instant::now();
+        let mut ranked: Vec<(&RpcEndpoint, EndpointHealth)> = self
+            .endpoints
+            .iter()
+            .map(|e| (e, e.health.lock().unwrap().clone()))
+            .collect();
+
+        ranked.sort_by(|(_, a), (_, b)| {
+            let a_cooling = a.cooldown_until.is_some_and(|until| until > now);
+            let b_cooling = b.cooldown_until.is_some_and(|until| until > now);
+            a_cooling
+                .cmp(&b_cooling)
+                .then_with(|| b.score.total_cmp(&a.score))
+                .then_with(|| a.latency_ms.total_cmp(&b.latency_ms))
+        });
+
+        ranked.into_iter().map(|(e, _)| e).collect()
+    }
+
+    fn record_success(&self, endpoint: &RpcEndpoint, elapsed: Duration) {
+        let elapsed_ms = elapsed.as_secs_f64() * 1000.0;
+        // Slow answers count as half a success so a sluggish endpoint sinks in the ranking.
+        let sample = if elapsed > self.options.slow_call_threshold {
+            0.5
+        } else {
+            1.0
+        };
+
+        let mut health = endpoint.health.lock().unwrap();
+        health.score = ewma(health.score, sample, SCORE_EWMA_ALPHA);
+        health.latency_ms = if health.latency_ms == 0.0 {
+            elapsed_ms
+        } else {
+            ewma(health.latency_ms, elapsed_ms, LATENCY_EWMA_ALPHA)
+        };
+        health.consecutive_failures = 0;
+        health.cooldown_until = None;
+    }
+
+    fn record_failure(&self, endpoint: &RpcEndpoint, message: &str) {
+        let mut health = endpoint.health.lock().unwrap();
+        health.score = ewma(health.score, 0.0, SCORE_EWMA_ALPHA);
+        health.consecutive_failures += 1;
+        health.last_error = Some(message.to_string());
+
+        if health.consecutive_failures >= FAILURES_BEFORE_COOLDOWN {
+            let exp = (health.consecutive_failures - FAILURES_BEFORE_COOLDOWN).min(6);
+            let secs = (1_u64 << exp).min(MAX_COOLDOWN_SECONDS);
+            health.cooldown_until = Some(Instant::now() + Duration::from_secs(secs));
+        }
+
+        tracing::warn!(
+            url = %endpoint.url,
+            consecutive_failures = health.consecutive_failures,
+            "rpc endpoint failure: {}",
+            message
+        );
+    }
+}
+
+#[async_trait]
+impl ChainGateway for RpcPool {
+    async fn get_optional_account(&self, pubkey: &Pubkey) -> Result<Option<Account>> {
+        let pubkey = *pubkey;
+        let response = self
+            .call("getAccountInfo", move |client| {
+                Box::pin(async move {
+                    client
+                        .get_account_with_commitment(&pubkey, client.commitment())
+                        .await
+                })
+            })
+            .await?;
+
+        Ok(response.value)
+    }
+
+    async fn get_signature_statuses(
+        &self,
+        signatures: &[Signature],
+    ) -> Result<Vec<Option<TransactionStatus>>> {
+        let response = self
+            .call("getSignatureStatuses", |client| {
+                Box::pin(client.get_signature_statuses(signatures))
+            })
+            .await?;
+        Ok(response.value)
+    }
+
+    async fn get_latest_blockhash(&self) -> Result<Hash> {
+        self.call("getLatestBlockhash", |client| {
+            Box::pin(client.get_latest_blockhash())
+        })
+        .await
+    }
+
+    async fn get_genesis_hash(&self) -> Result<Hash> {
+        self.call("getGenesisHash", |client| {
+            Box::pin(client.get_genesis_hash())
+        })
+        .await
+    }
+
+    async fn send_transaction(&self, tx: &Transaction) -> Result<Signature> {
+        // Resending the same signed transaction to another endpoint is safe: the
+        // cluster deduplicates by signature.
+        self.call("sendTransaction", |client| {
+            Box::pin(client.send_transaction(tx))
+        })
+        .await
+    }
+}
+
+fn is_endpoint_failure(err: &ClientError) -> bool {
+    match &err.kind {
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) | ClientErrorKind::Middleware(_) => {
+            true
+        }
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(_) | RpcError::ParseError(_)) => true,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            *code == JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY
+        }
+        _ => false,
+    }
+}
+
+fn ewma(current: f64, sample: f64, alpha: f64) -> f64 {
+    current * (1.0 - alpha) + sample * alpha
+}
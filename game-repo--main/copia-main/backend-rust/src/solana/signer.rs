@@ -0,0 +1,120 @@
+//! Signing keys behind a trait, so a key can live in a file, an encrypted
+//! keystore or a separate signer process.
+
+use std::{fmt, sync::Arc};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+
+use crate::solana::{keystore, remote_signer::RemoteSigner};
+
+#[async_trait]
+pub trait TransactionSigner: Send + Sync {
+    fn pubkey(&self) -> Pubkey;
+
+    /// Signs serialized message bytes.
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature>;
+}
+
+/// A keypair held in this process.
+pub struct LocalSigner(Keypair);
+
+impl LocalSigner {
+    pub fn new(keypair: Keypair) -> Self {
+        Self(keypair)
+    }
+}
+
+#[async_trait]
+impl TransactionSigner for LocalSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.0.pubkey()
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        Ok(self.0.sign_message(message))
+    }
+}
+
+/// Where a signing key comes from.
+#[derive(Clone)]
+pub enum SignerConfig {
+    /// Plaintext Solana CLI keypair JSON.
+    KeypairFile { path: String },
+    /// Keypair encrypted with a passphrase; see [`crate::solana::keystore`].
+    Keystore { path: String, passphrase: String },
+    /// Key held by a signer process at `http://host:port` or `unix:/path`.
+    Remote { endpoint: String },
+}
+
+impl fmt::Debug for SignerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeypairFile { path } => {
+                f.debug_struct("KeypairFile").field("path", path).finish()
+            }
+            Self::Keystore { path, .. } => f
+                .debug_struct("Keystore")
+                .field("path", path)
+                .field("passphrase", &"<redacted>")
+                .finish(),
+            Self::Remote { endpoint } => f
+                .debug_struct("Remote")
+                .field("endpoint", endpoint)
+                .finish(),
+        }
+    }
+}
+
+/// Loads the signer for `expected`; a local key for any other pubkey is an error.
+/// Remote signers are not contacted here; each signature is checked instead.
+pub fn load_signer(config: &SignerConfig, expected: &Pubkey) -> Result<Arc<dyn TransactionSigner>> {
+    let keypair = match config {
+        SignerConfig::KeypairFile { path } => {
+            read_keypair_file(path).map_err(|e| anyhow!("failed to read keypair {path}: {e}"))?
+        }
+        SignerConfig::Keystore { path, passphrase } => keystore::read_keystore(path, passphrase)?,
+        SignerConfig::Remote { endpoint } => {
+            return Ok(Arc::new(RemoteSigner::new(endpoint, *expected)?));
+        }
+    };
+    if keypair.pubkey() != *expected {
+        bail!(
+            "keypair pubkey {} does not match expected {}",
+            keypair.pubkey(),
+            expected
+        );
+    }
+    Ok(Arc::new(LocalSigner::new(keypair)))
+}
+
+/// Fills in the signature of every signer in `signers`, then requires the
+/// transaction to be fully signed.
+pub async fn sign_transaction(
+    tx: &mut Transaction,
+    signers: &[&dyn TransactionSigner],
+) -> Result<()> {
+    let message = tx.message_data();
+    for signer in signers {
+        let pubkey = signer.pubkey();
+        let position = tx
+            .get_signing_keypair_positions(&[pubkey])?
+            .first()
+            .copied()
+            .flatten()
+            .ok_or_else(|| anyhow!("{pubkey} is not a signer of this transaction"))?;
+        let signature = signer
+            .sign_message(&message)
+            .await
+            .with_context(|| format!("failed to sign with {pubkey}"))?;
+        tx.signatures[position] = signature;
+    }
+    tx.verify()
+        .context("transaction signatures do not verify")?;
+    Ok(())
+}
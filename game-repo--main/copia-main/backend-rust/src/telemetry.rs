@@ -0,0 +1,134 @@
+//! Tracing setup: log lines plus OpenTelemetry spans, and the W3C trace context
+//! that carries a request's trace into work done after it returns.
+//!
+//! Spans are exported over OTLP/HTTP when `OTEL_EXPORTER_OTLP_ENDPOINT` is set.
+//! Without it they still get trace ids, so request spans and stored
+//! `traceparent`s stay meaningful in the logs.
+
+use std::collections::HashMap;
+
+use anyhow::{Context as _, Result};
+use axum::http::{HeaderMap, Request};
+use opentelemetry::{
+    propagation::{Extractor, TextMapPropagator},
+    trace::{SpanContext, TraceContextExt, TracerProvider as _},
+    Context,
+};
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::SdkTracerProvider, Resource};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, registry::LookupSpan, util::SubscriberInitExt};
+
+const TRACEPARENT: &str = "traceparent";
+
+/// Flushes buffered spans on shutdown; keep it alive for the life of the process.
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        if let Err(e) = self.provider.shutdown() {
+            eprintln!("failed to flush traces: {e}");
+        }
+    }
+}
+
+/// Installs the global subscriber: `RUST_LOG`-filtered logs and OpenTelemetry spans.
+pub fn init() -> Result<Telemetry> {
+    let mut builder = SdkTracerProvider::builder().with_resource(
+        Resource::builder()
+            .with_service_name(
+                std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "backend-rust".into()),
+            )
+            .build(),
+    );
+    let endpoint = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .ok()
+        .filter(|endpoint| !endpoint.trim().is_empty());
+    if let Some(endpoint) = &endpoint {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+            .build()
+            .context("failed to build the OTLP span exporter")?;
+        builder = builder.with_batch_exporter(exporter);
+    }
+    let provider = builder.build();
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "backend_rust=debug,axum=info,sqlx=warn".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .with(layer(&provider))
+        .init();
+    if let Some(endpoint) = endpoint {
+        tracing::info!(%endpoint, "exporting traces over OTLP");
+    }
+    Ok(Telemetry { provider })
+}
+
+/// The `tracing` layer that turns spans into OpenTelemetry spans of `provider`.
+pub fn layer<S>(
+    provider: &SdkTracerProvider,
+) -> tracing_opentelemetry::OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer("backend-rust"))
+}
+
+/// Span of one HTTP request, continuing the caller's trace when it sent a
+/// `traceparent`. Runs after the request id layer, so `x-request-id` is set.
+pub fn request_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id,
+    );
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(request.headers()));
+    if parent.span().span_context().is_valid() {
+        let _ = span.set_parent(parent);
+    }
+    span
+}
+
+/// `traceparent` of the current span, to store with work that outlives the request.
+pub fn current_traceparent() -> Option<String> {
+    let cx = Span::current().context();
+    if !cx.span().span_context().is_valid() {
+        return None;
+    }
+    let mut carrier = HashMap::new();
+    TraceContextPropagator::new().inject_context(&cx, &mut carrier);
+    carrier.remove(TRACEPARENT)
+}
+
+/// The span a stored `traceparent` refers to, to link later work back to it.
+pub fn span_context(traceparent: &str) -> Option<SpanContext> {
+    let carrier = HashMap::from([(TRACEPARENT.to_string(), traceparent.to_string())]);
+    let cx: Context = TraceContextPropagator::new().extract(&carrier);
+    let span_context = cx.span().span_context().clone();
+    span_context.is_valid().then_some(span_context)
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
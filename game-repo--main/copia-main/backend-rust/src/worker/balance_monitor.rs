@@ -0,0 +1,120 @@
+//! Watches the balances of the keys that pay for finalizer transactions.
+//!
+//! The finalizer checks them once before it starts; this worker keeps warning
+//! while any of them stays below its configured minimum.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use solana_sdk::pubkey::Pubkey;
+use tokio::task::JoinHandle;
+
+use crate::{
+    app_state::{AppState, ProgramEnv},
+    config::Config,
+    solana::gateway::ChainGateway,
+    worker::finalizer::FinalizerSigners,
+};
+
+#[derive(Debug, Clone)]
+pub struct WatchedAccount {
+    /// `fee_payer` or `authority`, for logs.
+    pub role: &'static str,
+    pub pubkey: Pubkey,
+    pub min_balance: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BalanceShortfall {
+    pub role: &'static str,
+    pub pubkey: Pubkey,
+    pub balance: u64,
+    pub min_balance: u64,
+}
+
+/// The fee payer, plus each authority that is a separate key when a minimum is
+/// set. Without a fee payer every authority pays for its own games.
+pub fn watched_accounts(config: &Config, signers: &FinalizerSigners) -> Vec<WatchedAccount> {
+    let Some(fee_payer) = &signers.fee_payer else {
+        return signers
+            .authorities
+            .iter()
+            .map(|authority| WatchedAccount {
+                role: "fee_payer",
+                pubkey: authority.pubkey(),
+                min_balance: config.fee_payer_min_balance_lamports,
+            })
+            .collect();
+    };
+
+    let mut watched = vec![WatchedAccount {
+        role: "fee_payer",
+        pubkey: fee_payer.pubkey(),
+        min_balance: config.fee_payer_min_balance_lamports,
+    }];
+    if config.authority_min_balance_lamports > 0 {
+        watched.extend(
+            signers
+                .authorities
+                .iter()
+                .filter(|authority| authority.pubkey() != fee_payer.pubkey())
+                .map(|authority| WatchedAccount {
+                    role: "authority",
+                    pubkey: authority.pubkey(),
+                    min_balance: config.authority_min_balance_lamports,
+                }),
+        );
+    }
+    watched
+}
+
+/// Accounts below their minimum; a missing account has a balance of zero.
+pub async fn underfunded(
+    chain: &dyn ChainGateway,
+    watched: &[WatchedAccount],
+) -> Result<Vec<BalanceShortfall>> {
+    let mut short = Vec::new();
+    for account in watched {
+        let balance = chain
+            .get_optional_account(&account.pubkey)
+            .await?
+            .map_or(0, |a| a.lamports);
+        if balance < account.min_balance {
+            short.push(BalanceShortfall {
+                role: account.role,
+                pubkey: account.pubkey,
+                balance,
+                min_balance: account.min_balance,
+            });
+        }
+    }
+    Ok(short)
+}
+
+pub fn spawn(state: AppState, env: ProgramEnv, watched: Vec<WatchedAccount>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = Duration::from_millis(state.config.balance_check_ms);
+        tracing::info!(env = %env.config.name, "balance monitor started");
+
+        loop {
+            match underfunded(env.chain.as_ref(), &watched).await {
+                Ok(short) => {
+                    for s in short {
+                        tracing::warn!(
+                            env = %env.config.name,
+                            role = s.role,
+                            pubkey = %s.pubkey,
+                            balance = s.balance,
+                            min_balance = s.min_balance,
+                            "signer balance below minimum; top it up"
+                        );
+                    }
+                }
+                Err(e) => tracing::error!(env = %env.config.name, "balance monitor error: {e:#}"),
+            }
+            if !state.shutdown.sleep(interval).await {
+                break;
+            }
+        }
+    })
+}
@@ -0,0 +1,132 @@
+//! Moves reserved matches from `waiting_create_tx` to `created_on_chain` once the
+//! creator's `create_game` transaction has landed, and drops reservations that
+//! expired without one.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use solana_sdk::pubkey::Pubkey;
+use tokio::task::JoinHandle;
+
+use crate::{
+    app_state::{AppState, ProgramEnv},
+    db::matches as matches_db,
+    solana::client::fetch_and_decode_game_account,
+};
+
+const WATCH_BATCH_SIZE: i64 = 100;
+
+pub fn spawn(state: AppState, env: ProgramEnv) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = Duration::from_millis(state.config.create_watch_poll_ms);
+        tracing::info!(env = %env.config.name, "create transaction watcher started");
+
+        loop {
+            if let Err(e) = process_waiting_matches(&state, &env).await {
+                tracing::error!(env = %env.config.name, "create watcher error: {e:#}");
+            }
+            if !state.shutdown.sleep(interval).await {
+                break;
+            }
+        }
+    })
+}
+
+/// Checks one batch of the environment's reservations. Returns how many changed state.
+pub async fn process_waiting_matches(state: &AppState, env: &ProgramEnv) -> Result<usize> {
+    let chain = env.chain.as_ref();
+    let waiting =
+        matches_db::list_waiting_create_tx(&state.pool, &env.config.program_id, WATCH_BATCH_SIZE)
+            .await?;
+    let mut changed = 0;
+
+    for reservation in waiting {
+        let decoded = fetch_and_decode_game_account(
+            chain,
+            &env.idl,
+            &env.config.program_id,
+            &reservation.game_pda,
+        )
+        .await;
+
+        match decoded {
+            Ok(game) => {
+                let player1_matches = reservation
+                    .player1_pubkey
+                    .parse::<Pubkey>()
+                    .is_ok_and(|p| p == game.player1);
+                if !player1_matches || game.match_id != reservation.match_id as u64 {
+                    // Unreachable unless the PDA derivation changed under us.
+                    tracing::error!(
+                        match_id = reservation.match_id,
+                        game_pda = %reservation.game_pda,
+                        "on-chain game does not match its reservation"
+                    );
+                    continue;
+                }
+
+                let entry_lamports = i64::try_from(game.entry_amount).unwrap_or(i64::MAX);
+                if entry_lamports != reservation.entry_lamports {
+                    tracing::warn!(
+                        match_id = reservation.match_id,
+                        reserved = reservation.entry_lamports,
+                        on_chain = entry_lamports,
+                        "game was created with a different stake than reserved"
+                    );
+                }
+                let Some(created_at) = Utc.timestamp_opt(game.created_at, 0).single() else {
+                    tracing::error!(
+                        match_id = reservation.match_id,
+                        created_at = game.created_at,
+                        "on-chain game has an invalid created_at"
+                    );
+                    continue;
+                };
+                if matches_db::mark_created_on_chain(
+                    &state.pool,
+                    reservation.match_id,
+                    entry_lamports,
+                    created_at,
+                )
+                .await?
+                {
+                    tracing::info!(
+                        match_id = reservation.match_id,
+                        "reserved match created on-chain"
+                    );
+                    changed += 1;
+                }
+            }
+            Err(e) => {
+                let expired = reservation
+                    .create_expires_at
+                    .is_some_and(|at| at <= Utc::now());
+                if !expired {
+                    tracing::trace!(
+                        match_id = reservation.match_id,
+                        "create tx not seen yet: {e:#}"
+                    );
+                    continue;
+                }
+                // Only drop the reservation once the account is known to be absent,
+                // not because the RPC call failed.
+                let game_pda: Pubkey = reservation.game_pda.parse()?;
+                if chain.get_optional_account(&game_pda).await?.is_some() {
+                    tracing::warn!(
+                        match_id = reservation.match_id,
+                        "expired reservation has an undecodable game account: {e:#}"
+                    );
+                    continue;
+                }
+                if matches_db::delete_expired_reservation(&state.pool, reservation.match_id).await?
+                {
+                    tracing::info!(match_id = reservation.match_id, "match reservation expired");
+                    changed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(changed)
+}
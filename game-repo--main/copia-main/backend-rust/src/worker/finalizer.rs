@@ -1,125 +1,238 @@
-use std::{str::FromStr, time::Duration};
+use std::{str::FromStr, sync::Arc, time::Duration};
 
 use anyhow::{anyhow, bail, Context, Result};
-use sha2::{Digest, Sha256};
-use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
-    bpf_loader_upgradeable,
     hash::Hash,
-    instruction::{AccountMeta, Instruction},
+    instruction::Instruction,
+    message::Message,
     pubkey::Pubkey,
-    signature::{read_keypair_file, Keypair, Signature, Signer},
-    system_program,
+    signature::{read_keypair_file, Signature},
     transaction::Transaction,
 };
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 
 use crate::{
-    app_state::AppState,
+    app_state::{AppState, ProgramEnv},
+    config::{Config, ProgramEnvConfig},
     db::chain_jobs as chain_jobs_db,
+    metrics::metrics,
     models::enums::{ChainJobStatus, ChainJobType, MatchStatus},
     solana::{
-        client::fetch_and_decode_game_account_with_client,
-        game_account::{DecodedGameAccount, DecodedGameState},
+        client::fetch_and_decode_game_account,
+        game_account::{DecodedGameAccount, DecodedGameState, GameLayout},
+        gateway::ChainGateway,
+        idl::Idl,
+        instructions::{force_refund_ix, settle_game_ix, settle_game_v2_ix},
+        signer::{load_signer, sign_transaction, LocalSigner, TransactionSigner},
     },
+    telemetry,
+    worker::{balance_monitor, liveness::FinalizerState},
 };
 
-const MAX_FINALIZER_ATTEMPTS: i32 = 10;
+pub const MAX_FINALIZER_ATTEMPTS: i32 = 10;
 const CONFIRM_POLL_INTERVAL_MS: u64 = 500;
 const CONFIRM_POLL_ATTEMPTS: usize = 40;
 const MAX_BACKOFF_SECONDS: i64 = 60;
+/// How often a token game's job is looked at again while they are unsupported.
+const TOKEN_GAME_RETRY_SECONDS: i64 = 3600;
+
+/// Keys the finalizer signs with.
+#[derive(Clone)]
+pub struct FinalizerSigners {
+    /// Every configured authority; each game is signed by its own.
+    pub authorities: Vec<Arc<dyn TransactionSigner>>,
+    /// Pays the transaction fee; the game's authority pays when `None`.
+    pub fee_payer: Option<Arc<dyn TransactionSigner>>,
+}
+
+impl FinalizerSigners {
+    /// The environment's authorities, plus the fee payer shared by all environments.
+    pub fn load(config: &Config, env: &ProgramEnvConfig) -> Result<Self> {
+        let authorities = env
+            .authorities
+            .iter()
+            .map(|authority| {
+                let pubkey = Pubkey::from_str(&authority.pubkey)
+                    .with_context(|| format!("invalid authority pubkey {}", authority.pubkey))?;
+                load_signer(&authority.signer, &pubkey)
+                    .with_context(|| format!("failed to load the signer of authority {pubkey}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let fee_payer = config
+            .fee_payer_keypair_path
+            .as_deref()
+            .map(|path| {
+                read_keypair_file(path)
+                    .map_err(|e| anyhow!("failed to read FEE_PAYER_KEYPAIR_PATH: {e}"))
+            })
+            .transpose()?
+            .map(|fee_payer| Arc::new(LocalSigner::new(fee_payer)) as Arc<dyn TransactionSigner>);
+
+        Ok(Self {
+            authorities,
+            fee_payer,
+        })
+    }
+
+    /// The signer of the authority a game was created under.
+    pub fn authority(&self, pubkey: &Pubkey) -> Option<&dyn TransactionSigner> {
+        self.authorities
+            .iter()
+            .find(|signer| signer.pubkey() == *pubkey)
+            .map(Arc::as_ref)
+    }
 
-pub fn spawn(state: AppState) {
+    pub fn fee_payer_for<'a>(
+        &'a self,
+        authority: &'a dyn TransactionSigner,
+    ) -> &'a dyn TransactionSigner {
+        self.fee_payer.as_deref().unwrap_or(authority)
+    }
+}
+
+pub fn spawn(state: AppState, env: ProgramEnv, signers: FinalizerSigners) -> JoinHandle<()> {
     tokio::spawn(async move {
         let idle_interval = Duration::from_millis(state.config.finalizer_poll_ms);
 
-        let program_id = match Pubkey::from_str(&state.config.program_id) {
-            Ok(v) => v,
-            Err(e) => {
-                tracing::error!("finalizer disabled: invalid PROGRAM_ID: {}", e);
-                return;
+        // Refuse to start underfunded: every settlement would fail on fees.
+        let watched = balance_monitor::watched_accounts(&state.config, &signers);
+        let recheck = Duration::from_millis(state.config.balance_check_ms);
+        loop {
+            match balance_monitor::underfunded(env.chain.as_ref(), &watched).await {
+                Ok(short) if short.is_empty() => break,
+                Ok(short) => {
+                    env.finalizer.set_state(FinalizerState::Underfunded);
+                    for s in &short {
+                        tracing::error!(
+                            env = %env.config.name,
+                            role = s.role,
+                            pubkey = %s.pubkey,
+                            balance = s.balance,
+                            min_balance = s.min_balance,
+                            "finalizer not started: account is underfunded"
+                        );
+                    }
+                }
+                Err(e) => tracing::warn!("finalizer startup balance check failed: {e:#}"),
             }
-        };
-
-        let authority = match load_authority_keypair(&state) {
-            Ok(kp) => kp,
-            Err(e) => {
-                tracing::error!("finalizer disabled: failed to load authority keypair: {e:#}");
+            if !state.shutdown.sleep(recheck).await {
                 return;
             }
-        };
-
-        if authority.pubkey().to_string() != state.config.authority_pubkey {
-            tracing::error!(
-                "finalizer disabled: authority keypair pubkey {} does not match AUTHORITY_PUBKEY {}",
-                authority.pubkey(),
-                state.config.authority_pubkey
-            );
-            return;
         }
 
-        let rpc = RpcClient::new(state.config.solana_rpc_url.clone());
-        tracing::info!("finalizer worker started");
+        let authorities: Vec<String> = signers
+            .authorities
+            .iter()
+            .map(|signer| signer.pubkey().to_string())
+            .collect();
+        tracing::info!(
+            env = %env.config.name,
+            program_id = %env.program_id,
+            authorities = %authorities.join(","),
+            fee_payer = ?signers.fee_payer.as_ref().map(|signer| signer.pubkey()),
+            "finalizer worker started"
+        );
+        env.finalizer.set_state(FinalizerState::Running);
 
         loop {
-            match process_one_job(&state, &rpc, &program_id, &authority).await {
-                Ok(true) => {}
-                Ok(false) => tokio::time::sleep(idle_interval).await,
+            let busy = match process_one_job(&state, &env, &signers).await {
+                Ok(busy) => busy,
                 Err(e) => {
-                    tracing::error!("finalizer loop error: {e:#}");
-                    tokio::time::sleep(idle_interval).await;
+                    tracing::error!(env = %env.config.name, "finalizer loop error: {e:#}");
+                    env.finalizer.record_error(format!("{e:#}"));
+                    false
                 }
+            };
+            if state.shutdown.is_triggered()
+                || (!busy && !state.shutdown.sleep(idle_interval).await)
+            {
+                break;
             }
         }
-    });
+        tracing::info!(env = %env.config.name, "finalizer worker stopped");
+    })
 }
 
-async fn process_one_job(
+/// Claims and processes at most one due job of the environment. Returns `false`
+/// when its queue is idle or shutdown has begun.
+pub async fn process_one_job(
     state: &AppState,
-    rpc: &RpcClient,
-    program_id: &Pubkey,
-    authority: &Keypair,
+    env: &ProgramEnv,
+    signers: &FinalizerSigners,
 ) -> Result<bool> {
-    let Some(job) = chain_jobs_db::claim_next_due_finalizer_job(&state.pool).await? else {
+    env.finalizer.tick();
+    if state.shutdown.is_triggered() {
+        return Ok(false);
+    }
+    let Some(job) =
+        chain_jobs_db::claim_next_due_finalizer_job(&state.pool, &env.config.program_id).await?
+    else {
         tracing::trace!("finalizer idle");
         return Ok(false);
     };
 
-    tracing::info!(
+    // A new trace per attempt, linked to the `/v1/finalize` request that enqueued it.
+    let span = tracing::info_span!(
+        parent: None,
+        "chain_job",
+        env = %env.config.name,
         match_id = job.match_id,
         job_type = ?job.job_type,
-        job_status = ?job.chain_job_status,
         attempt_count = job.attempt_count,
-        "processing chain job"
     );
+    if let Some(origin) = job
+        .trace_parent
+        .as_deref()
+        .and_then(telemetry::span_context)
+    {
+        span.add_link(origin);
+    }
 
-    let outcome = process_claimed_job(state, rpc, program_id, authority, &job).await;
-    match outcome {
-        Ok(()) => {}
-        Err(e) => {
-            let error_text = format!("{e:#}");
-            // Unexpected processing failures (decode/build/DB) should eventually trip max attempts.
-            let increment_attempt = true;
-            schedule_retry_or_fail(state, &job, &error_text, increment_attempt).await?;
+    async {
+        tracing::info!(job_status = ?job.chain_job_status, "processing chain job");
+
+        let outcome = process_claimed_job(state, env, signers, &job).await;
+        match outcome {
+            Ok(()) => {}
+            Err(e) => {
+                let error_text = format!("{e:#}");
+                // Unexpected processing failures (decode/build/DB) should eventually trip max attempts.
+                let increment_attempt = true;
+                schedule_retry_or_fail(
+                    state,
+                    &job,
+                    ErrorClass::Processing,
+                    &error_text,
+                    increment_attempt,
+                )
+                .await?;
+            }
         }
-    }
 
-    Ok(true)
+        Ok(true)
+    }
+    .instrument(span)
+    .await
 }
 
 async fn process_claimed_job(
     state: &AppState,
-    rpc: &RpcClient,
-    program_id: &Pubkey,
-    authority: &Keypair,
+    env: &ProgramEnv,
+    signers: &FinalizerSigners,
     job: &chain_jobs_db::ClaimedFinalizerJob,
 ) -> Result<()> {
-    if try_recover_submitted_job(state, rpc, job).await? {
+    let chain = env.chain.as_ref();
+    if try_recover_submitted_job(state, chain, job).await? {
         return Ok(());
     }
 
-    let decoded = match fetch_and_decode_game_account_with_client(
-        rpc,
-        &state.config.program_id,
+    let decoded = match fetch_and_decode_game_account(
+        chain,
+        &env.idl,
+        &env.config.program_id,
         &job.game_pda,
     )
     .await
@@ -136,7 +249,8 @@ async fn process_claimed_job(
                     "{}",
                     error_text
                 );
-                schedule_retry_or_fail(state, job, &error_text, false).await?;
+                schedule_retry_or_fail(state, job, ErrorClass::Processing, &error_text, false)
+                    .await?;
                 return Ok(());
             }
 
@@ -149,17 +263,21 @@ async fn process_claimed_job(
         }
     };
 
-    if decoded.authority != authority.pubkey() {
+    let Some(authority) = signers.authority(&decoded.authority) else {
+        ErrorClass::Authority.record();
         chain_jobs_db::mark_job_failed(
             &state.pool,
             job.match_id,
             job.lock_token,
-            "on-chain game.authority does not match backend authority keypair",
+            &format!(
+                "on-chain game.authority {} is not a configured authority",
+                decoded.authority
+            ),
             false,
         )
         .await?;
         return Ok(());
-    }
+    };
 
     match (job.job_type, decoded.state) {
         (ChainJobType::Settle, DecodedGameState::Settled) => {
@@ -185,6 +303,7 @@ async fn process_claimed_job(
             return Ok(());
         }
         (ChainJobType::Settle, DecodedGameState::Refunded) => {
+            ErrorClass::GameState.record();
             chain_jobs_db::mark_job_failed(
                 &state.pool,
                 job.match_id,
@@ -196,6 +315,7 @@ async fn process_claimed_job(
             return Ok(());
         }
         (ChainJobType::ForceRefund, DecodedGameState::Settled) => {
+            ErrorClass::GameState.record();
             chain_jobs_db::mark_job_failed(
                 &state.pool,
                 job.match_id,
@@ -209,8 +329,25 @@ async fn process_claimed_job(
         _ => {}
     }
 
+    if let Some(mint) = decoded.token_mint {
+        // Token games need token accounts the finalizer does not derive yet.
+        // Finalize holds them for review; a job enqueued anyway stays retryable,
+        // so a release that supports them picks it up.
+        ErrorClass::TokenMint.record();
+        chain_jobs_db::mark_job_retrying(
+            &state.pool,
+            job.match_id,
+            job.lock_token,
+            &format!("game is staked in token mint {mint}; token games cannot be finalized yet"),
+            TOKEN_GAME_RETRY_SECONDS,
+            false,
+        )
+        .await?;
+        return Ok(());
+    }
+
     let (instruction, final_match_status) =
-        build_finalization_instruction(*program_id, authority.pubkey(), &decoded, job)
+        build_finalization_instruction(&env.idl, env.program_id, authority.pubkey(), &decoded, job)
             .with_context(|| {
                 format!(
                     "failed to build finalization instruction for match {}",
@@ -218,10 +355,21 @@ async fn process_claimed_job(
                 )
             })?;
 
-    let signature = match send_instruction(rpc, authority, instruction).await {
+    // Last point to back out before a transaction exists that must be recorded.
+    if state.shutdown.is_triggered() {
+        tracing::info!(
+            match_id = job.match_id,
+            "shutting down; releasing chain job"
+        );
+        chain_jobs_db::clear_job_lock(&state.pool, job.match_id, job.lock_token).await?;
+        return Ok(());
+    }
+
+    let fee_payer = signers.fee_payer_for(authority);
+    let signature = match send_instruction(chain, fee_payer, authority, instruction).await {
         Ok(sig) => sig,
         Err(e) => {
-            schedule_retry_or_fail(state, job, &format!("{e:#}"), true).await?;
+            schedule_retry_or_fail(state, job, ErrorClass::Send, &format!("{e:#}"), true).await?;
             return Ok(());
         }
     };
@@ -240,8 +388,28 @@ async fn process_claimed_job(
         return Err(anyhow!(e.to_string()));
     }
 
-    if let Err(e) = wait_for_signature_confirmation(rpc, &signature).await {
-        schedule_retry_or_fail(state, job, &format!("{e:#}"), false).await?;
+    let confirmation = tokio::select! {
+        result = wait_for_signature_confirmation(chain, &signature) => result,
+        _ = state.shutdown.deadline() => {
+            // Still `submitted` with its signature: the next start recovers it.
+            tracing::warn!(
+                match_id = job.match_id,
+                signature = %sig_text,
+                "shutdown deadline reached before confirmation; releasing chain job"
+            );
+            chain_jobs_db::clear_job_lock(&state.pool, job.match_id, job.lock_token).await?;
+            return Ok(());
+        }
+    };
+    if let Err(e) = confirmation {
+        schedule_retry_or_fail(
+            state,
+            job,
+            ErrorClass::Confirmation,
+            &format!("{e:#}"),
+            false,
+        )
+        .await?;
         return Ok(());
     }
 
@@ -265,7 +433,7 @@ async fn process_claimed_job(
 
 async fn try_recover_submitted_job(
     state: &AppState,
-    rpc: &RpcClient,
+    chain: &dyn ChainGateway,
     job: &chain_jobs_db::ClaimedFinalizerJob,
 ) -> Result<bool> {
     if job.chain_job_status != ChainJobStatus::Submitted {
@@ -288,12 +456,12 @@ async fn try_recover_submitted_job(
         )
     })?;
 
-    let statuses = rpc
+    let statuses = chain
         .get_signature_statuses(&[signature])
         .await
         .with_context(|| format!("failed to fetch signature status for {}", last_tx_sig))?;
 
-    let Some(status_opt) = statuses.value.first() else {
+    let Some(status_opt) = statuses.first() else {
         return Ok(false);
     };
 
@@ -306,6 +474,7 @@ async fn try_recover_submitted_job(
         schedule_retry_or_fail(
             state,
             job,
+            ErrorClass::Confirmation,
             &format!("previous submitted transaction failed on-chain: {err:?}"),
             false,
         )
@@ -335,9 +504,11 @@ async fn try_recover_submitted_job(
 async fn schedule_retry_or_fail(
     state: &AppState,
     job: &chain_jobs_db::ClaimedFinalizerJob,
+    class: ErrorClass,
     error_message: &str,
     increment_attempt_count: bool,
 ) -> Result<()> {
+    class.record();
     let projected_attempts = job.attempt_count + i32::from(increment_attempt_count);
 
     if projected_attempts >= MAX_FINALIZER_ATTEMPTS {
@@ -378,17 +549,44 @@ async fn schedule_retry_or_fail(
     Ok(())
 }
 
+/// Where a chain job attempt went wrong, the `class` of `chain_job_failures_total`.
+#[derive(Debug, Clone, Copy)]
+enum ErrorClass {
+    /// Fetching, decoding or building, or a database error.
+    Processing,
+    Send,
+    /// The transaction failed on-chain or never confirmed.
+    Confirmation,
+    /// The game is already finalized the other way.
+    GameState,
+    /// The game's authority is not configured here.
+    Authority,
+    TokenMint,
+}
+
+impl ErrorClass {
+    fn record(self) {
+        let class = match self {
+            ErrorClass::Processing => "processing",
+            ErrorClass::Send => "send",
+            ErrorClass::Confirmation => "confirmation",
+            ErrorClass::GameState => "game_state",
+            ErrorClass::Authority => "authority",
+            ErrorClass::TokenMint => "token_mint",
+        };
+        metrics()
+            .chain_job_failures
+            .with_label_values(&[class])
+            .inc();
+    }
+}
+
 fn retry_backoff_seconds(attempts: i32) -> i64 {
     let exp = attempts.clamp(1, 6) as u32;
     let secs = 1_i64.checked_shl(exp).unwrap_or(MAX_BACKOFF_SECONDS);
     secs.min(MAX_BACKOFF_SECONDS)
 }
 
-fn load_authority_keypair(state: &AppState) -> Result<Keypair> {
-    read_keypair_file(&state.config.authority_keypair_path)
-        .map_err(|e| anyhow!("read_keypair_file failed: {e}"))
-}
-
 fn final_match_status_for_job_type(job_type: ChainJobType) -> MatchStatus {
     match job_type {
         ChainJobType::Settle => MatchStatus::Settled,
@@ -396,7 +594,8 @@ fn final_match_status_for_job_type(job_type: ChainJobType) -> MatchStatus {
     }
 }
 
-fn build_finalization_instruction(
+pub fn build_finalization_instruction(
+    idl: &Idl,
     program_id: Pubkey,
     authority_pubkey: Pubkey,
     game: &DecodedGameAccount,
@@ -404,7 +603,6 @@ fn build_finalization_instruction(
 ) -> Result<(Instruction, MatchStatus)> {
     let game_pda = Pubkey::from_str(&job.game_pda).context("invalid game_pda in DB")?;
     let vault_pda = Pubkey::from_str(&job.vault_pda).context("invalid vault_pda in DB")?;
-    let program_data_pda = bpf_loader_upgradeable::get_program_data_address(&program_id);
 
     match job.job_type {
         ChainJobType::Settle => {
@@ -424,21 +622,25 @@ fn build_finalization_instruction(
                 bail!("winner_pubkey in chain job does not match on-chain players");
             }
 
-            let mut data = anchor_ix_discriminator("settle_game").to_vec();
-            data.extend_from_slice(winner.as_ref());
-
-            let ix = Instruction {
-                program_id,
-                accounts: vec![
-                    AccountMeta::new(game_pda, false),
-                    AccountMeta::new(vault_pda, false),
-                    AccountMeta::new(winner, false),
-                    AccountMeta::new_readonly(program_id, false),
-                    AccountMeta::new_readonly(program_data_pda, false),
-                    AccountMeta::new_readonly(authority_pubkey, true),
-                    AccountMeta::new_readonly(system_program::id(), false),
-                ],
-                data,
+            // Pre-upgrade games keep the original settle; V2 games pay the protocol fee.
+            let ix = match game.layout {
+                GameLayout::V1 => settle_game_ix(
+                    idl,
+                    program_id,
+                    game_pda,
+                    vault_pda,
+                    winner,
+                    authority_pubkey,
+                )?,
+                GameLayout::V2 => settle_game_v2_ix(
+                    idl,
+                    program_id,
+                    game_pda,
+                    vault_pda,
+                    winner,
+                    game.fee_recipient,
+                    authority_pubkey,
+                )?,
             };
             Ok((ix, MatchStatus::Settled))
         }
@@ -459,61 +661,62 @@ fn build_finalization_instruction(
                     game.player2
                 };
 
-            let ix = Instruction {
+            let ix = force_refund_ix(
+                idl,
                 program_id,
-                accounts: vec![
-                    AccountMeta::new(game_pda, false),
-                    AccountMeta::new(vault_pda, false),
-                    AccountMeta::new(game.player1, false),
-                    AccountMeta::new(player2_for_accounts, false),
-                    AccountMeta::new_readonly(program_id, false),
-                    AccountMeta::new_readonly(program_data_pda, false),
-                    AccountMeta::new_readonly(authority_pubkey, true),
-                    AccountMeta::new_readonly(system_program::id(), false),
-                ],
-                data: anchor_ix_discriminator("force_refund").to_vec(),
-            };
+                game_pda,
+                vault_pda,
+                game.player1,
+                player2_for_accounts,
+                authority_pubkey,
+            )?;
             Ok((ix, MatchStatus::Refunded))
         }
     }
 }
 
+#[tracing::instrument(skip_all, fields(authority = %authority.pubkey()))]
 async fn send_instruction(
-    rpc: &RpcClient,
-    authority: &Keypair,
+    chain: &dyn ChainGateway,
+    fee_payer: &dyn TransactionSigner,
+    authority: &dyn TransactionSigner,
     ix: Instruction,
 ) -> Result<Signature> {
-    let recent_blockhash: Hash = rpc
+    let recent_blockhash: Hash = chain
         .get_latest_blockhash()
         .await
         .context("failed to fetch latest blockhash")?;
 
-    let tx = Transaction::new_signed_with_payer(
-        &[ix],
-        Some(&authority.pubkey()),
-        &[authority],
-        recent_blockhash,
-    );
+    let message = Message::new_with_blockhash(&[ix], Some(&fee_payer.pubkey()), &recent_blockhash);
+    let mut tx = Transaction::new_unsigned(message);
+    if fee_payer.pubkey() == authority.pubkey() {
+        sign_transaction(&mut tx, &[fee_payer]).await?;
+    } else {
+        sign_transaction(&mut tx, &[fee_payer, authority]).await?;
+    }
 
-    rpc.send_transaction(&tx)
+    chain
+        .send_transaction(&tx)
         .await
         .context("failed to send transaction")
 }
 
-async fn wait_for_signature_confirmation(rpc: &RpcClient, signature: &Signature) -> Result<()> {
+#[tracing::instrument(skip(chain), fields(signature = %signature))]
+async fn wait_for_signature_confirmation(
+    chain: &dyn ChainGateway,
+    signature: &Signature,
+) -> Result<()> {
     for _ in 0..CONFIRM_POLL_ATTEMPTS {
-        let statuses = rpc
+        let statuses = chain
             .get_signature_statuses(&[*signature])
             .await
             .context("failed to fetch signature status")?;
 
-        if let Some(status_opt) = statuses.value.first() {
-            if let Some(status) = status_opt {
-                if let Some(err) = &status.err {
-                    bail!("transaction failed on-chain: {err:?}");
-                }
-                return Ok(());
+        if let Some(Some(status)) = statuses.first() {
+            if let Some(err) = &status.err {
+                bail!("transaction failed on-chain: {err:?}");
             }
+            return Ok(());
         }
 
         tokio::time::sleep(Duration::from_millis(CONFIRM_POLL_INTERVAL_MS)).await;
@@ -521,12 +724,3 @@ async fn wait_for_signature_confirmation(rpc: &RpcClient, signature: &Signature)
 
     bail!("timed out waiting for transaction confirmation");
 }
-
-fn anchor_ix_discriminator(method_name: &str) -> [u8; 8] {
-    let mut hasher = Sha256::new();
-    hasher.update(format!("global:{method_name}").as_bytes());
-    let hash = hasher.finalize();
-    let mut out = [0u8; 8];
-    out.copy_from_slice(&hash[..8]);
-    out
-}
@@ -0,0 +1,91 @@
+//! In-process view of whether an environment's finalizer is doing its job.
+//!
+//! The finalizer task updates it; `/healthz` and `/v1/finalizer/status` read it.
+
+use std::sync::Mutex;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// A running finalizer that has not ticked for this long is considered stuck.
+/// Generous: one job may spend ~20s confirming on top of slow RPC calls.
+const STALL_AFTER_SECONDS: i64 = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinalizerState {
+    /// Not spawned (yet).
+    Starting,
+    /// Spawned, waiting for its fee payer or authorities to be topped up.
+    Underfunded,
+    Running,
+    /// Its signers could not be loaded; nothing will be settled.
+    Disabled,
+}
+
+#[derive(Debug, Clone)]
+pub struct FinalizerSnapshot {
+    pub state: FinalizerState,
+    pub last_tick_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub struct FinalizerLiveness {
+    inner: Mutex<FinalizerSnapshot>,
+}
+
+impl Default for FinalizerLiveness {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(FinalizerSnapshot {
+                state: FinalizerState::Starting,
+                last_tick_at: None,
+                last_error: None,
+                last_error_at: None,
+            }),
+        }
+    }
+}
+
+impl FinalizerLiveness {
+    pub fn set_state(&self, state: FinalizerState) {
+        self.inner.lock().unwrap().state = state;
+    }
+
+    pub fn disable(&self, reason: String) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.state = FinalizerState::Disabled;
+        inner.last_error = Some(reason);
+        inner.last_error_at = Some(Utc::now());
+    }
+
+    /// One pass of the finalizer loop, whether or not it found a job.
+    pub fn tick(&self) {
+        self.inner.lock().unwrap().last_tick_at = Some(Utc::now());
+    }
+
+    pub fn record_error(&self, error: String) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.last_error = Some(error);
+        inner.last_error_at = Some(Utc::now());
+    }
+
+    pub fn snapshot(&self) -> FinalizerSnapshot {
+        self.inner.lock().unwrap().clone()
+    }
+
+    /// Running, but no loop pass for a while: the task is hung or gone.
+    pub fn is_stalled(&self, poll_interval_ms: u64) -> bool {
+        let inner = self.inner.lock().unwrap();
+        if inner.state != FinalizerState::Running {
+            return false;
+        }
+        let poll_interval = Duration::milliseconds(poll_interval_ms.min(u32::MAX.into()) as i64);
+        let threshold = Duration::seconds(STALL_AFTER_SECONDS) + poll_interval;
+        inner
+            .last_tick_at
+            .is_none_or(|tick| Utc::now() - tick > threshold)
+    }
+}
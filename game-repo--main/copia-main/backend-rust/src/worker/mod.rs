@@ -1,7 +1,38 @@
+pub mod balance_monitor;
+pub mod create_watcher;
 pub mod finalizer;
+pub mod liveness;
+pub mod nonce_pruner;
+pub mod relay_watcher;
+pub mod rpc_health;
+
+use tokio::task::JoinHandle;
 
 use crate::app_state::AppState;
 
-pub fn spawn_workers(state: AppState) {
-    finalizer::spawn(state);
+/// Starts every worker once per program environment. Each task ends after
+/// `state.shutdown` is triggered; await the handles to drain them.
+pub fn spawn_workers(state: AppState) -> Vec<JoinHandle<()>> {
+    let mut handles = vec![nonce_pruner::spawn(state.clone())];
+    for env in state.envs.iter() {
+        handles.extend(rpc_health::spawn(state.clone(), env.clone()));
+        handles.push(create_watcher::spawn(state.clone(), env.clone()));
+        handles.push(relay_watcher::spawn(state.clone(), env.clone()));
+
+        match finalizer::FinalizerSigners::load(&state.config, &env.config) {
+            Ok(signers) => {
+                handles.push(balance_monitor::spawn(
+                    state.clone(),
+                    env.clone(),
+                    balance_monitor::watched_accounts(&state.config, &signers),
+                ));
+                handles.push(finalizer::spawn(state.clone(), env.clone(), signers));
+            }
+            Err(e) => {
+                tracing::error!(env = %env.config.name, "finalizer disabled: {e:#}");
+                env.finalizer.disable(format!("{e:#}"));
+            }
+        }
+    }
+    handles
 }
@@ -0,0 +1,63 @@
+//! Deletes HMAC nonces that can no longer be replayed.
+//!
+//! A request is accepted while its `X-Timestamp` is within
+//! [`MAX_CLOCK_SKEW_SECONDS`] of now, and that timestamp may itself be up to the
+//! skew in the future. So a nonce guards against replay for at most twice the
+//! skew after it was recorded; past that the timestamp check alone rejects it.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::task::JoinHandle;
+
+use crate::{
+    api::internal_auth::MAX_CLOCK_SKEW_SECONDS, app_state::AppState, db::used_nonces,
+    metrics::metrics,
+};
+
+/// How long a nonce is kept: the replay window plus a margin for clock drift
+/// between this host and the database.
+pub const NONCE_RETENTION_SECONDS: i64 = 2 * MAX_CLOCK_SKEW_SECONDS + 60;
+
+/// Rows deleted per statement, so a large backlog does not hold long locks.
+const PRUNE_BATCH_SIZE: i64 = 5_000;
+
+pub fn spawn(state: AppState) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = Duration::from_millis(state.config.nonce_prune_ms);
+        tracing::info!("nonce pruner started");
+
+        loop {
+            match prune_expired_nonces(&state).await {
+                Ok(0) => {}
+                Ok(pruned) => tracing::debug!(pruned, "pruned expired nonces"),
+                Err(e) => tracing::error!("nonce pruner error: {e:#}"),
+            }
+            if !state.shutdown.sleep(interval).await {
+                break;
+            }
+        }
+    })
+}
+
+/// Deletes every expired nonce, then refreshes the table size metrics. Returns
+/// how many were deleted.
+pub async fn prune_expired_nonces(state: &AppState) -> Result<u64> {
+    let m = metrics();
+    let mut pruned = 0;
+    loop {
+        let deleted =
+            used_nonces::prune_nonces(&state.pool, NONCE_RETENTION_SECONDS, PRUNE_BATCH_SIZE)
+                .await?;
+        pruned += deleted;
+        m.used_nonces_pruned.inc_by(deleted);
+        if deleted < PRUNE_BATCH_SIZE as u64 || state.shutdown.is_triggered() {
+            break;
+        }
+    }
+
+    let (rows, bytes) = used_nonces::table_size(&state.pool).await?;
+    m.used_nonces_rows.set(rows);
+    m.used_nonces_bytes.set(bytes);
+    Ok(pruned)
+}
@@ -0,0 +1,124 @@
+//! Follows `create_game`/`join_game` transactions submitted through the relay.
+//!
+//! A landed `join_game` moves its match to `joined_on_chain`; landed creates are
+//! picked up by the create watcher from the game account. Signatures that fail
+//! on-chain, or are not seen before their blockhash expires, are cleared with
+//! `last_error` set so the player can sign a fresh transaction.
+
+use std::{str::FromStr, time::Duration};
+
+use anyhow::Result;
+use chrono::Utc;
+use solana_sdk::signature::Signature;
+use tokio::task::JoinHandle;
+
+use crate::{
+    app_state::{AppState, ProgramEnv},
+    db::matches::{self as matches_db, PendingRelayedTx, RelayedTxKind},
+    solana::{client::fetch_and_decode_game_account, game_account::DecodedGameState},
+};
+
+/// How long a relayed signature may stay unseen; longer than a blockhash lives.
+pub const RELAYED_TX_TIMEOUT_SECONDS: i64 = 120;
+
+const WATCH_BATCH_SIZE: i64 = 100;
+
+pub fn spawn(state: AppState, env: ProgramEnv) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let interval = Duration::from_millis(state.config.create_watch_poll_ms);
+        tracing::info!(env = %env.config.name, "relayed transaction watcher started");
+
+        loop {
+            if let Err(e) = process_relayed_txs(&state, &env).await {
+                tracing::error!(env = %env.config.name, "relay watcher error: {e:#}");
+            }
+            if !state.shutdown.sleep(interval).await {
+                break;
+            }
+        }
+    })
+}
+
+/// Checks one batch of the environment's relayed signatures. Returns how many
+/// matches changed.
+pub async fn process_relayed_txs(state: &AppState, env: &ProgramEnv) -> Result<usize> {
+    let pending =
+        matches_db::list_pending_relayed_txs(&state.pool, &env.config.program_id, WATCH_BATCH_SIZE)
+            .await?;
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let signatures = pending
+        .iter()
+        .map(|tx| Signature::from_str(&tx.signature))
+        .collect::<Result<Vec<_>, _>>()?;
+    let statuses = env.chain.get_signature_statuses(&signatures).await?;
+
+    let mut changed = 0;
+    for (tx, status) in pending.iter().zip(statuses) {
+        let outcome = match status {
+            Some(status) => match status.err {
+                Some(err) => Err(format!("relayed transaction failed on-chain: {err:?}")),
+                None => Ok(()),
+            },
+            None if (Utc::now() - tx.sent_at).num_seconds() > RELAYED_TX_TIMEOUT_SECONDS => {
+                Err("relayed transaction was not seen on-chain before its blockhash expired".into())
+            }
+            None => continue,
+        };
+
+        let updated = match outcome {
+            Ok(()) if tx.kind == RelayedTxKind::Join => mark_joined(state, env, tx).await?,
+            // The create watcher moves the match once the game account is readable.
+            Ok(()) => false,
+            Err(error) => {
+                tracing::warn!(
+                    match_id = tx.match_id,
+                    signature = %tx.signature,
+                    kind = ?tx.kind,
+                    "{error}"
+                );
+                matches_db::clear_relayed_tx(
+                    &state.pool,
+                    tx.match_id,
+                    tx.kind,
+                    &tx.signature,
+                    &error,
+                )
+                .await?
+            }
+        };
+        if updated {
+            changed += 1;
+        }
+    }
+
+    Ok(changed)
+}
+
+async fn mark_joined(state: &AppState, env: &ProgramEnv, tx: &PendingRelayedTx) -> Result<bool> {
+    let game = fetch_and_decode_game_account(
+        env.chain.as_ref(),
+        &env.idl,
+        &env.config.program_id,
+        &tx.game_pda,
+    )
+    .await?;
+    if game.state == DecodedGameState::Created {
+        // Confirmed but not yet visible through this RPC node.
+        return Ok(false);
+    }
+
+    let joined =
+        matches_db::mark_joined_on_chain(&state.pool, tx.match_id, &game.player2.to_string())
+            .await?;
+    if joined {
+        tracing::info!(
+            match_id = tx.match_id,
+            player2 = %game.player2,
+            "relayed join landed"
+        );
+    }
+    Ok(joined)
+}
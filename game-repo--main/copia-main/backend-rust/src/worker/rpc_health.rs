@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use crate::app_state::{AppState, ProgramEnv};
+
+pub fn spawn(state: AppState, env: ProgramEnv) -> Option<JoinHandle<()>> {
+    let Some(rpc) = env.rpc.clone() else {
+        tracing::info!(env = %env.config.name, "rpc health checker not started: no live RPC pool");
+        return None;
+    };
+
+    Some(tokio::spawn(async move {
+        let interval = Duration::from_millis(state.config.rpc_health_check_ms);
+        tracing::info!(
+            env = %env.config.name,
+            endpoints = env.config.solana_rpc_urls.len(),
+            "rpc health checker started"
+        );
+
+        loop {
+            rpc.check_health().await;
+
+            let statuses = rpc.status();
+            for status in &statuses {
+                tracing::debug!(
+                    env = %env.config.name,
+                    url = %status.url,
+                    score = status.score,
+                    latency_ms = status.latency_ms,
+                    consecutive_failures = status.consecutive_failures,
+                    last_slot = ?status.last_slot,
+                    cooling_down = status.cooling_down,
+                    "rpc endpoint health"
+                );
+            }
+            if statuses.iter().all(|s| s.cooling_down) {
+                tracing::error!(
+                    env = %env.config.name,
+                    last_errors = ?statuses.iter().map(|s| s.last_error.as_deref()).collect::<Vec<_>>(),
+                    "all rpc endpoints are failing"
+                );
+            }
+
+            if !state.shutdown.sleep(interval).await {
+                break;
+            }
+        }
+    }))
+}
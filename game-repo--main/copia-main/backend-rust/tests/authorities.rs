@@ -0,0 +1,112 @@
+//! Authority rotation: a new active authority beside a settle-only old one.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+use backend_rust::{
+    config::{AuthorityConfig, AuthorityStatus},
+    solana::{
+        game_account::GameLayout,
+        signer::{LocalSigner, SignerConfig},
+    },
+    worker::finalizer::FinalizerSigners,
+};
+use common::{winner_body, TestApp};
+
+/// The harness authority becomes settle-only and `new_authority` takes new games.
+async fn spawn_rotated(new_authority: &Keypair) -> Option<TestApp> {
+    let new_pubkey = new_authority.pubkey().to_string();
+    TestApp::spawn_with(|config| {
+        let env = &mut config.environments[0];
+        env.authorities[0].status = AuthorityStatus::SettleOnly;
+        env.authorities.insert(
+            0,
+            AuthorityConfig {
+                pubkey: new_pubkey.clone(),
+                signer: SignerConfig::KeypairFile {
+                    path: String::new(),
+                },
+                status: AuthorityStatus::Active,
+            },
+        );
+        env.authority_pubkey = new_pubkey;
+    })
+    .await
+}
+
+#[tokio::test]
+async fn games_of_a_settle_only_authority_are_still_finalized() {
+    let new_authority = Keypair::new();
+    let Some(app) = spawn_rotated(&new_authority).await else {
+        return;
+    };
+    let signers = FinalizerSigners {
+        authorities: vec![
+            Arc::new(LocalSigner::new(new_authority.insecure_clone())),
+            Arc::new(LocalSigner::new(app.authority.insecure_clone())),
+        ],
+        fee_payer: None,
+    };
+    let old_game = app.create_game(1, true);
+    let new_game = app.create_game_under(&new_authority.pubkey(), 2, true, GameLayout::V1, 0);
+
+    for (game, key) in [(&old_game, "rotate-old"), (&new_game, "rotate-new")] {
+        let (status, body) = app.finalize(&winner_body(game, &game.player1, key)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert!(app.run_finalizer_with(&signers).await);
+    }
+    assert_eq!(app.match_status(1).await, "settled");
+    assert_eq!(app.match_status(2).await, "settled");
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn new_games_use_the_active_authority_only() {
+    let new_authority = Keypair::new();
+    let Some(app) = spawn_rotated(&new_authority).await else {
+        return;
+    };
+
+    let reserved = app.reserve_match(&Pubkey::new_unique()).await;
+    assert_eq!(
+        reserved["authority_pubkey"],
+        new_authority.pubkey().to_string()
+    );
+
+    let open_old_game = app.create_game(3, false);
+    let (status, body) = app
+        .post_json(
+            "/v1/transactions/join-game",
+            &serde_json::json!({
+                "game_pda": open_old_game.game_pda.to_string(),
+                "player_pubkey": Pubkey::new_unique().to_string(),
+            }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn unknown_authorities_are_rejected() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let stranger = app.create_game_under(&Pubkey::new_unique(), 4, true, GameLayout::V1, 0);
+
+    let (status, body) = app
+        .finalize(&winner_body(&stranger, &stranger.player1, "stranger"))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+    app.cleanup().await;
+}
@@ -0,0 +1,599 @@
+//! Shared harness for the integration tests.
+//!
+//! Each test gets its own throwaway Postgres database (created from
+//! `TEST_DATABASE_URL`, migrated with `sqlx::migrate!` and dropped in
+//! [`TestApp::cleanup`]) and an in-memory [`FakeChain`]. Tests are skipped when
+//! `TEST_DATABASE_URL` is not set.
+
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Method, Request, StatusCode},
+    Router,
+};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, write_keypair_file, Keypair, Signer},
+};
+use sqlx::{postgres::PgPoolOptions, Connection, PgConnection, PgPool, Row};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+use backend_rust::{
+    api::{join_tickets::join_ticket_message, request_signing::RequestSigner},
+    app_state::{AppState, ProgramEnv},
+    build_router,
+    config::{AuthorityConfig, AuthorityStatus, Config, ProgramEnvConfig},
+    db::matches as matches_db,
+    models::enums::MatchStatus,
+    reason_policy::ReasonPolicy,
+    solana::{
+        fake_chain::{FakeChain, FakeGameParams},
+        game_account::GameLayout,
+        idl::Idl,
+        pda::derive_match_pdas,
+        signer::{LocalSigner, SignerConfig, TransactionSigner},
+    },
+    worker::finalizer::{self, FinalizerSigners},
+};
+
+pub const HMAC_SECRET: &str = "integration-test-secret";
+pub const ADMIN_HMAC_SECRET: &str = "integration-admin-secret";
+pub const ENTRY_LAMPORTS: u64 = 100_000_000;
+/// IDL of the upgraded program, whose `Game` adds the V2 fields and which
+/// settles them with `settle_game_v2`. Set as `program_idl_path` to test it.
+pub const V2_IDL_PATH: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/game_program_v2.json"
+);
+
+pub struct TestApp {
+    pub state: AppState,
+    pub router: Router,
+    /// Fake chain of the default environment.
+    pub chain: Arc<FakeChain>,
+    /// One fake chain per configured environment, in config order.
+    pub chains: Vec<Arc<FakeChain>>,
+    pub authority: Keypair,
+    /// Set when the test configured `FEE_PAYER_KEYPAIR_PATH`.
+    pub fee_payer: Option<Keypair>,
+    pub program_id: Pubkey,
+    admin_url: String,
+    db_name: String,
+}
+
+pub struct TestGame {
+    pub game_pda: Pubkey,
+    pub match_id: u64,
+    pub player1: Pubkey,
+    pub player2: Option<Pubkey>,
+}
+
+#[derive(Debug)]
+pub struct JobRow {
+    pub status: String,
+    pub attempt_count: i32,
+    pub last_tx_sig: Option<String>,
+    pub last_error: Option<String>,
+}
+
+impl TestApp {
+    /// Returns `None` (and the caller should return early) without `TEST_DATABASE_URL`.
+    pub async fn spawn() -> Option<Self> {
+        Self::spawn_with(|_| {}).await
+    }
+
+    /// Like [`TestApp::spawn`], with `configure` applied to the test config first.
+    pub async fn spawn_with(configure: impl FnOnce(&mut Config)) -> Option<Self> {
+        let Ok(admin_url) = std::env::var("TEST_DATABASE_URL") else {
+            eprintln!("skipping integration test: TEST_DATABASE_URL is not set");
+            return None;
+        };
+
+        let db_name = format!("backend_test_{}", Uuid::new_v4().simple());
+        let mut admin = PgConnection::connect(&admin_url)
+            .await
+            .expect("connect to TEST_DATABASE_URL");
+        sqlx::query(&format!("create database {db_name}"))
+            .execute(&mut admin)
+            .await
+            .expect("create test database");
+        admin.close().await.ok();
+
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(&database_url_for(&admin_url, &db_name))
+            .await
+            .expect("connect to test database");
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .expect("run migrations");
+
+        let program_id = Pubkey::new_unique();
+        let authority = Keypair::new();
+        let mut config = test_config(&program_id, &authority);
+        configure(&mut config);
+        let fee_payer = config
+            .fee_payer_keypair_path
+            .as_deref()
+            .map(|path| read_keypair_file(path).expect("read fee payer keypair"));
+        let chains: Vec<Arc<FakeChain>> = config
+            .environments
+            .iter()
+            .map(|env| {
+                let idl = Idl::load(env.program_idl_path.as_deref()).expect("load program IDL");
+                Arc::new(FakeChain::with_idl(env.program_id.parse().unwrap(), idl))
+            })
+            .collect();
+        let mut next_chain = chains.iter().cloned();
+        let state = AppState::with_chains(config, pool, |_| next_chain.next().unwrap())
+            .expect("build app state");
+
+        Some(Self {
+            router: build_router(state.clone()),
+            state,
+            chain: chains[0].clone(),
+            chains,
+            authority,
+            fee_payer,
+            program_id,
+            admin_url,
+            db_name,
+        })
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.state.pool
+    }
+
+    pub fn default_env(&self) -> &ProgramEnv {
+        self.state.default_env()
+    }
+
+    /// Writes a funded game, in the program IDL's newest layout, for two fresh
+    /// players onto the fake chain; `Joined` when `joined` is set, else `Created`.
+    pub fn create_game(&self, match_id: u64, joined: bool) -> TestGame {
+        self.create_game_with_layout(match_id, joined, self.chain.layout(), 0)
+    }
+
+    pub fn create_game_with_layout(
+        &self,
+        match_id: u64,
+        joined: bool,
+        layout: GameLayout,
+        fee_bps: u16,
+    ) -> TestGame {
+        self.create_game_under(&self.authority.pubkey(), match_id, joined, layout, fee_bps)
+    }
+
+    /// Like [`TestApp::create_game_with_layout`], for a game of another authority.
+    pub fn create_game_under(
+        &self,
+        authority: &Pubkey,
+        match_id: u64,
+        joined: bool,
+        layout: GameLayout,
+        fee_bps: u16,
+    ) -> TestGame {
+        let player1 = Pubkey::new_unique();
+        let player2 = joined.then(Pubkey::new_unique);
+        let game_pda = self.chain.insert_game(&FakeGameParams {
+            player1,
+            player2,
+            authority: *authority,
+            entry_amount: ENTRY_LAMPORTS,
+            match_id,
+            layout,
+            fee_bps,
+            token_mint: None,
+        });
+        TestGame {
+            game_pda,
+            match_id,
+            player1,
+            player2,
+        }
+    }
+
+    pub async fn finalize(&self, body: &Value) -> (StatusCode, Value) {
+        let nonce = Uuid::new_v4().to_string();
+        self.post_signed("/v1/finalize", body, &nonce).await
+    }
+
+    /// POST signed with the shared secret, in the canonical (version 2) format.
+    pub async fn post_signed(&self, path: &str, body: &Value, nonce: &str) -> (StatusCode, Value) {
+        let raw = serde_json::to_vec(body).unwrap();
+        let timestamp = Utc::now().timestamp().to_string();
+        let headers = RequestSigner::new(HMAC_SECRET, None).sign_with(
+            &Method::POST,
+            path,
+            &raw,
+            &timestamp,
+            nonce,
+        );
+        self.send(signed_request(Method::POST, path, headers, raw))
+            .await
+    }
+
+    pub async fn post_json(&self, path: &str, body: &Value) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(path)
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_vec(body).unwrap()))
+            .unwrap();
+        self.send(request).await
+    }
+
+    pub async fn get(&self, path: &str) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap();
+        self.send(request).await
+    }
+
+    /// GET returning the raw body, for non-JSON endpoints such as `/metrics`.
+    pub async fn get_text(&self, path: &str) -> (StatusCode, String) {
+        let request = Request::builder()
+            .method(Method::GET)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap();
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(bytes.to_vec()).unwrap())
+    }
+
+    /// GET signed with the shared secret.
+    pub async fn get_signed(&self, path: &str) -> (StatusCode, Value) {
+        let headers = RequestSigner::new(HMAC_SECRET, None).sign(&Method::GET, path, b"");
+        self.send(signed_request(Method::GET, path, headers, Vec::new()))
+            .await
+    }
+
+    /// A request signed with the key `key_id`, e.g. a server credential or `admin`.
+    pub async fn send_with_key(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+        key_id: &str,
+        secret: &str,
+    ) -> (StatusCode, Value) {
+        let raw = body.map_or_else(Vec::new, |body| serde_json::to_vec(body).unwrap());
+        let headers =
+            RequestSigner::new(secret, Some(key_id.to_string())).sign(&method, path, &raw);
+        self.send(signed_request(method, path, headers, raw)).await
+    }
+
+    pub async fn admin(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> (StatusCode, Value) {
+        self.send_with_key(method, path, body, "admin", ADMIN_HMAC_SECRET)
+            .await
+    }
+
+    /// Reserves a match for `creator` through the API and returns the response body.
+    pub async fn reserve_match(&self, creator: &Pubkey) -> Value {
+        let (status, body) = self
+            .post_json(
+                "/v1/challenges/reserve",
+                &serde_json::json!({
+                    "creator_pubkey": creator.to_string(),
+                    "entry_amount": ENTRY_LAMPORTS,
+                }),
+            )
+            .await;
+        assert_eq!(status, StatusCode::CREATED, "{body}");
+        body
+    }
+
+    pub async fn send(&self, request: Request<Body>) -> (StatusCode, Value) {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+        (status, json)
+    }
+
+    pub async fn run_finalizer_once(&self) -> bool {
+        self.run_finalizer_with(&self.signers()).await
+    }
+
+    pub async fn run_finalizer_with(&self, signers: &FinalizerSigners) -> bool {
+        self.run_finalizer_in(self.default_env(), signers).await
+    }
+
+    pub async fn run_finalizer_in(&self, env: &ProgramEnv, signers: &FinalizerSigners) -> bool {
+        finalizer::process_one_job(&self.state, env, signers)
+            .await
+            .expect("finalizer step")
+    }
+
+    pub fn signers(&self) -> FinalizerSigners {
+        FinalizerSigners {
+            authorities: vec![Arc::new(LocalSigner::new(self.authority.insecure_clone()))],
+            fee_payer: self.fee_payer.as_ref().map(|fee_payer| {
+                Arc::new(LocalSigner::new(fee_payer.insecure_clone())) as Arc<dyn TransactionSigner>
+            }),
+        }
+    }
+
+    /// Skips retry backoff so the next finalizer step picks the job up again.
+    pub async fn make_jobs_due(&self) {
+        sqlx::query("update chain_jobs set next_attempt_at = now()")
+            .execute(self.pool())
+            .await
+            .unwrap();
+    }
+
+    pub async fn job(&self, match_id: u64) -> JobRow {
+        let row = sqlx::query(
+            "select status, attempt_count, last_tx_sig, last_error from chain_jobs where match_id = $1",
+        )
+        .bind(match_id as i64)
+        .fetch_one(self.pool())
+        .await
+        .unwrap();
+        JobRow {
+            status: row.get("status"),
+            attempt_count: row.get("attempt_count"),
+            last_tx_sig: row.get("last_tx_sig"),
+            last_error: row.get("last_error"),
+        }
+    }
+
+    pub async fn match_status(&self, match_id: u64) -> String {
+        sqlx::query_scalar("select match_status from matches where match_id = $1")
+            .bind(match_id as i64)
+            .fetch_one(self.pool())
+            .await
+            .unwrap()
+    }
+
+    /// Records `game` as hosted by `server_id`, as the challenge flow does,
+    /// adding the server to the pool if it is not there yet.
+    pub async fn assign_server(&self, game: &TestGame, server_id: &str) {
+        sqlx::query(
+            "insert into server_pool (server_id, ip, port) values ($1, '10.0.0.1', 7777)
+             on conflict (server_id) do nothing",
+        )
+        .bind(server_id)
+        .execute(self.pool())
+        .await
+        .unwrap();
+
+        let program_id = self.default_env().config.program_id.clone();
+        let authority = self.authority.pubkey().to_string();
+        let player1 = game.player1.to_string();
+        let player2 = game.player2.map(|p| p.to_string());
+        let match_status = match game.player2 {
+            Some(_) => MatchStatus::JoinedOnChain,
+            None => MatchStatus::CreatedOnChain,
+        };
+        let pdas =
+            derive_match_pdas(&program_id, &authority, &player1, game.match_id as i64).unwrap();
+        matches_db::upsert_match_from_chain(
+            self.pool(),
+            &matches_db::UpsertMatchFromChainParams {
+                match_id: game.match_id as i64,
+                program_id: &program_id,
+                authority_pubkey: &authority,
+                game_pda: &game.game_pda.to_string(),
+                vault_pda: &pdas.vault_pda,
+                player1_pubkey: &player1,
+                player2_pubkey: player2.as_deref(),
+                entry_lamports: ENTRY_LAMPORTS as i64,
+                match_status,
+                created_onchain_at: Utc::now(),
+                joined_onchain_at: game.player2.map(|_| Utc::now()),
+            },
+        )
+        .await
+        .unwrap();
+
+        sqlx::query("update matches set assigned_server_id = $2 where match_id = $1")
+            .bind(game.match_id as i64)
+            .bind(server_id)
+            .execute(self.pool())
+            .await
+            .unwrap();
+    }
+
+    /// Assigns `server_id` and reports, with the shared secret, that both players
+    /// connected to it.
+    pub async fn kick_off(&self, game: &TestGame, server_id: &str) {
+        self.assign_server(game, server_id).await;
+        let players = [Some(game.player1), game.player2]
+            .iter()
+            .flatten()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        let (status, body) = self
+            .post_signed(
+                &format!("/v1/matches/{}/kickoff", game.match_id),
+                &serde_json::json!({ "present_wallets": players }),
+                &Uuid::new_v4().to_string(),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+    }
+
+    /// A nullable text column of the match row.
+    pub async fn match_column(&self, match_id: u64, column: &str) -> Option<String> {
+        sqlx::query_scalar(&format!("select {column} from matches where match_id = $1"))
+            .bind(match_id as i64)
+            .fetch_one(self.pool())
+            .await
+            .unwrap()
+    }
+
+    pub async fn cleanup(self) {
+        let Self {
+            state,
+            router,
+            admin_url,
+            db_name,
+            ..
+        } = self;
+        drop(router);
+        state.pool.close().await;
+        drop(state);
+
+        let mut admin = PgConnection::connect(&admin_url).await.unwrap();
+        sqlx::query(&format!("drop database if exists {db_name} with (force)"))
+            .execute(&mut admin)
+            .await
+            .unwrap();
+    }
+}
+
+pub fn winner_body(game: &TestGame, winner: &Pubkey, idempotency_key: &str) -> Value {
+    serde_json::json!({
+        "game_pda": game.game_pda.to_string(),
+        "outcome": "winner",
+        "winner_pubkey": winner.to_string(),
+        "reason_code": "match_complete",
+        "idempotency_key": idempotency_key,
+    })
+}
+
+pub fn broken_body(game: &TestGame, idempotency_key: &str) -> Value {
+    serde_json::json!({
+        "game_pda": game.game_pda.to_string(),
+        "outcome": "broken",
+        "reason_code": "server_crash",
+        "idempotency_key": idempotency_key,
+    })
+}
+
+/// Adds `timestamp`, a fresh `nonce` and `wallet`'s `signature` of the
+/// join-ticket message for `game_pda` to a request body.
+pub fn wallet_proof(body: &mut Value, wallet: &Keypair, game_pda: &str) {
+    let timestamp = Utc::now().timestamp();
+    let nonce = Uuid::new_v4().to_string();
+    let message = join_ticket_message(game_pda, timestamp, &nonce);
+    body["timestamp"] = timestamp.into();
+    body["nonce"] = nonce.into();
+    body["signature"] = wallet.sign_message(message.as_bytes()).to_string().into();
+}
+
+/// Writes `keypair` to a fresh file under the temp dir and returns its path.
+pub fn write_temp_keypair(keypair: &Keypair) -> String {
+    let path = std::env::temp_dir().join(format!("keypair-{}.json", Uuid::new_v4()));
+    write_keypair_file(keypair, &path).expect("write keypair file");
+    path.to_string_lossy().into_owned()
+}
+
+fn signed_request(
+    method: Method,
+    path: &str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+) -> Request<Body> {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(path)
+        .header("content-type", "application/json");
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    request.body(Body::from(body)).unwrap()
+}
+
+/// A version 1 (`timestamp.nonce.body`) signature with the shared secret.
+pub fn sign(timestamp: &str, nonce: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(HMAC_SECRET.as_bytes()).unwrap();
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(nonce.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn test_config(program_id: &Pubkey, authority: &Keypair) -> Config {
+    Config {
+        app_bind_addr: "127.0.0.1:0".into(),
+        database_url: String::new(),
+        environments: vec![ProgramEnvConfig {
+            authorities: vec![AuthorityConfig {
+                pubkey: authority.pubkey().to_string(),
+                signer: SignerConfig::KeypairFile {
+                    path: write_temp_keypair(authority),
+                },
+                status: AuthorityStatus::Active,
+            }],
+            ..test_environment("default", program_id, &authority.pubkey())
+        }],
+        rpc_timeout_ms: 1_000,
+        rpc_slow_call_ms: 500,
+        rpc_health_check_ms: 1_000,
+        fee_payer_keypair_path: None,
+        fee_payer_min_balance_lamports: 0,
+        authority_min_balance_lamports: 0,
+        balance_check_ms: 1_000,
+        internal_hmac_secret: HMAC_SECRET.into(),
+        shared_hmac_secret_enabled: true,
+        hmac_v1_signatures_enabled: true,
+        admin_hmac_secret: Some(ADMIN_HMAC_SECRET.into()),
+        finalizer_poll_ms: 50,
+        match_reservation_ttl_seconds: 300,
+        create_watch_poll_ms: 50,
+        nonce_prune_ms: 1_000,
+        sponsor_keypair_path: None,
+        sponsor_wallet_daily_limit: 5,
+        sponsor_daily_limit: 500,
+        mock_chain_seed_path: None,
+        shutdown_grace_ms: 1_000,
+        join_ticket_keypair_path: None,
+        join_ticket_ttl_seconds: 120,
+        reason_policy: ReasonPolicy::default(),
+    }
+}
+
+/// An environment served from a fake chain, with `authority` as its only authority.
+pub fn test_environment(name: &str, program_id: &Pubkey, authority: &Pubkey) -> ProgramEnvConfig {
+    ProgramEnvConfig {
+        name: name.into(),
+        program_id: program_id.to_string(),
+        program_idl_path: None,
+        solana_rpc_urls: vec!["http://127.0.0.1:8899".into()],
+        authority_pubkey: authority.to_string(),
+        authorities: vec![AuthorityConfig {
+            pubkey: authority.to_string(),
+            signer: SignerConfig::KeypairFile {
+                path: String::new(),
+            },
+            status: AuthorityStatus::Active,
+        }],
+    }
+}
+
+fn database_url_for(admin_url: &str, db_name: &str) -> String {
+    let (base, query) = match admin_url.split_once('?') {
+        Some((base, query)) => (base, Some(query)),
+        None => (admin_url, None),
+    };
+    let (prefix, _) = base
+        .rsplit_once('/')
+        .expect("TEST_DATABASE_URL must include a path");
+    match query {
+        Some(query) => format!("{prefix}/{db_name}?{query}"),
+        None => format!("{prefix}/{db_name}"),
+    }
+}
@@ -0,0 +1,132 @@
+//! Several program environments served from one backend.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+use backend_rust::{
+    config::validate_environments,
+    solana::{fake_chain::FakeGameParams, game_account::GameLayout, signer::LocalSigner},
+    worker::finalizer::FinalizerSigners,
+};
+use common::{test_environment, winner_body, TestApp, TestGame, ENTRY_LAMPORTS};
+
+/// The harness environment plus `second`, a separate program with its own authority.
+async fn spawn_two_envs(second_program: &Pubkey, second_authority: &Keypair) -> Option<TestApp> {
+    let env = test_environment("second", second_program, &second_authority.pubkey());
+    TestApp::spawn_with(|config| config.environments.push(env)).await
+}
+
+#[tokio::test]
+async fn jobs_are_finalized_by_the_environment_of_their_program() {
+    let second_program = Pubkey::new_unique();
+    let second_authority = Keypair::new();
+    let Some(app) = spawn_two_envs(&second_program, &second_authority).await else {
+        return;
+    };
+    let player1 = Pubkey::new_unique();
+    let game_pda = app.chains[1].insert_game(&FakeGameParams {
+        player1,
+        player2: Some(Pubkey::new_unique()),
+        authority: second_authority.pubkey(),
+        entry_amount: ENTRY_LAMPORTS,
+        match_id: 7,
+        layout: GameLayout::V1,
+        fee_bps: 0,
+        token_mint: None,
+    });
+    let game = TestGame {
+        game_pda,
+        match_id: 7,
+        player1,
+        player2: None,
+    };
+
+    let mut body = winner_body(&game, &player1, "second-env");
+    body["program_id"] = second_program.to_string().into();
+    let (status, body) = app.finalize(&body).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        app.match_column(7, "program_id").await.as_deref(),
+        Some(second_program.to_string().as_str())
+    );
+
+    // The default environment's finalizer does not see the other program's job.
+    assert!(!app.run_finalizer_once().await);
+
+    let second_env = &app.state.envs[1];
+    let signers = FinalizerSigners {
+        authorities: vec![Arc::new(LocalSigner::new(
+            second_authority.insecure_clone(),
+        ))],
+        fee_payer: None,
+    };
+    assert!(app.run_finalizer_in(second_env, &signers).await);
+    assert_eq!(app.match_status(7).await, "settled");
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn reservations_are_issued_per_program() {
+    let second_program = Pubkey::new_unique();
+    let second_authority = Keypair::new();
+    let Some(app) = spawn_two_envs(&second_program, &second_authority).await else {
+        return;
+    };
+    let request = |program_id: String| {
+        serde_json::json!({
+            "creator_pubkey": Pubkey::new_unique().to_string(),
+            "entry_amount": ENTRY_LAMPORTS,
+            "program_id": program_id,
+        })
+    };
+
+    let (status, body) = app
+        .post_json(
+            "/v1/challenges/reserve",
+            &request(second_program.to_string()),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    assert_eq!(body["program_id"], second_program.to_string());
+    assert_eq!(
+        body["authority_pubkey"],
+        second_authority.pubkey().to_string()
+    );
+
+    let reserved = app.reserve_match(&Pubkey::new_unique()).await;
+    assert_eq!(reserved["program_id"], app.program_id.to_string());
+
+    let (status, _) = app
+        .post_json(
+            "/v1/challenges/reserve",
+            &request(Pubkey::new_unique().to_string()),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    app.cleanup().await;
+}
+
+#[test]
+fn a_program_may_only_be_configured_once() {
+    let program = Pubkey::new_unique();
+    let environments = [
+        test_environment("devnet", &program, &Pubkey::new_unique()),
+        test_environment("mainnet", &program, &Pubkey::new_unique()),
+    ];
+    assert!(validate_environments(&environments).is_err());
+
+    let environments = [
+        test_environment("devnet", &program, &Pubkey::new_unique()),
+        test_environment("mainnet", &Pubkey::new_unique(), &Pubkey::new_unique()),
+    ];
+    assert!(validate_environments(&environments).is_ok());
+}
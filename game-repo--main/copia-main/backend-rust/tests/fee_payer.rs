@@ -0,0 +1,78 @@
+//! Finalizer fees paid by a dedicated fee payer instead of the authority.
+
+mod common;
+
+use axum::http::StatusCode;
+use solana_sdk::signature::{Keypair, Signer};
+
+use backend_rust::worker::balance_monitor::{underfunded, watched_accounts};
+use common::{winner_body, write_temp_keypair, TestApp};
+
+const FEE_PER_SIGNATURE: u64 = 5_000;
+const FEE_PAYER_FUNDS: u64 = 1_000_000;
+
+async fn spawn_with_fee_payer(min_balance: u64) -> Option<TestApp> {
+    let path = write_temp_keypair(&Keypair::new());
+    let app = TestApp::spawn_with(|config| {
+        config.fee_payer_keypair_path = Some(path.clone());
+        config.fee_payer_min_balance_lamports = min_balance;
+        config.authority_min_balance_lamports = 1;
+    })
+    .await;
+    std::fs::remove_file(&path).ok();
+    app
+}
+
+#[tokio::test]
+async fn fee_payer_pays_settlement_fees_and_authority_only_signs() {
+    let Some(app) = spawn_with_fee_payer(0).await else {
+        return;
+    };
+    let fee_payer = app.fee_payer.as_ref().unwrap().pubkey();
+    app.chain.set_fee_per_signature(FEE_PER_SIGNATURE);
+    app.chain.airdrop(&fee_payer, FEE_PAYER_FUNDS);
+    let game = app.create_game(1, true);
+
+    let (status, body) = app
+        .finalize(&winner_body(&game, &game.player1, "fee-payer-1"))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(app.run_finalizer_once().await);
+
+    assert_eq!(app.job(1).await.status, "confirmed");
+    assert_eq!(app.chain.balance(&app.authority.pubkey()), 0);
+    assert_eq!(
+        app.chain.balance(&fee_payer),
+        FEE_PAYER_FUNDS - 2 * FEE_PER_SIGNATURE
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn underfunded_signers_are_reported() {
+    let Some(app) = spawn_with_fee_payer(FEE_PAYER_FUNDS).await else {
+        return;
+    };
+    let signers = app.signers();
+    let watched = watched_accounts(&app.state.config, &signers);
+    assert_eq!(watched.len(), 2, "separate authority is watched too");
+
+    let fee_payer = signers.fee_payer.as_ref().unwrap().pubkey();
+    app.chain.airdrop(&fee_payer, FEE_PAYER_FUNDS - 1);
+    let short = underfunded(app.default_env().chain.as_ref(), &watched)
+        .await
+        .unwrap();
+    let roles: Vec<_> = short.iter().map(|s| s.role).collect();
+    assert_eq!(roles, ["fee_payer", "authority"]);
+    assert_eq!(short[0].balance, FEE_PAYER_FUNDS - 1);
+
+    app.chain.airdrop(&fee_payer, 1);
+    app.chain.airdrop(&app.authority.pubkey(), 1);
+    assert!(underfunded(app.default_env().chain.as_ref(), &watched)
+        .await
+        .unwrap()
+        .is_empty());
+
+    app.cleanup().await;
+}
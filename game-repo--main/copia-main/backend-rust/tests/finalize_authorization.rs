@@ -0,0 +1,155 @@
+//! Finalize is reserved to the match's assigned server, with an audited admin override.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+
+use common::{winner_body, TestApp};
+
+async fn issue(app: &TestApp, server_id: &str) -> (String, String) {
+    let (status, body) = app
+        .admin(
+            Method::POST,
+            &format!("/v1/admin/servers/{server_id}/credentials"),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    (
+        body["key_id"].as_str().unwrap().to_string(),
+        body["secret"].as_str().unwrap().to_string(),
+    )
+}
+
+async fn audit_events(app: &TestApp) -> Vec<(String, Option<String>, Option<i64>)> {
+    sqlx::query_as("select event, caller_server_id, match_id from security_audit_log order by id")
+        .fetch_all(app.pool())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn only_the_assigned_server_may_finalize() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (key_a, secret_a) = issue(&app, "server-a").await;
+    let (key_b, secret_b) = issue(&app, "server-b").await;
+    let game = app.create_game(1, true);
+    app.assign_server(&game, "server-a").await;
+    let body = winner_body(&game, &game.player1, "assigned-1");
+
+    let (status, _) = app
+        .send_with_key(Method::POST, "/v1/finalize", Some(&body), &key_b, &secret_b)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    // Nor the shared secret, now that server-a has its own credentials.
+    let (status, _) = app.finalize(&body).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(app.match_column(1, "result_reported_by").await, None);
+
+    let (status, body) = app
+        .send_with_key(Method::POST, "/v1/finalize", Some(&body), &key_a, &secret_a)
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // A server cannot finalize a match no server was assigned to.
+    let unassigned = app.create_game(2, true);
+    let (status, _) = app
+        .send_with_key(
+            Method::POST,
+            "/v1/finalize",
+            Some(&winner_body(&unassigned, &unassigned.player1, "assigned-2")),
+            &key_a,
+            &secret_a,
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    assert_eq!(
+        audit_events(&app).await,
+        vec![
+            (
+                "finalize_not_assigned_server".to_string(),
+                Some("server-b".to_string()),
+                Some(1)
+            ),
+            ("finalize_shared_secret_denied".to_string(), None, Some(1)),
+            (
+                "finalize_not_assigned_server".to_string(),
+                Some("server-a".to_string()),
+                Some(2)
+            ),
+        ]
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn admin_override_needs_a_reason_and_is_audited() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (key_a, secret_a) = issue(&app, "server-a").await;
+    let game = app.create_game(1, true);
+    app.assign_server(&game, "server-a").await;
+    let mut body = winner_body(&game, &game.player1, "override-1");
+
+    let (status, _) = app.admin(Method::POST, "/v1/finalize", Some(&body)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(audit_events(&app).await.is_empty());
+
+    // Only the admin key may claim an override, even the assigned server's may not.
+    body["override_reason"] = "server-a crashed before reporting".into();
+    let (status, _) = app
+        .send_with_key(Method::POST, "/v1/finalize", Some(&body), &key_a, &secret_a)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, response) = app.admin(Method::POST, "/v1/finalize", Some(&body)).await;
+    assert_eq!(status, StatusCode::OK, "{response}");
+    assert_eq!(app.match_status(1).await, "result_pending_finalize");
+
+    let (event, caller, detail): (String, String, String) = sqlx::query_as(
+        "select event, caller, detail from security_audit_log order by id desc limit 1",
+    )
+    .fetch_one(app.pool())
+    .await
+    .unwrap();
+    assert_eq!(event, "finalize_admin_override");
+    assert_eq!(caller, "admin");
+    assert_eq!(detail, "server-a crashed before reporting");
+    assert_eq!(audit_events(&app).await.len(), 2);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn revoked_credentials_do_not_reopen_the_shared_secret() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (key_id, _) = issue(&app, "server-a").await;
+    let (status, body) = app
+        .admin(
+            Method::POST,
+            &format!("/v1/admin/credentials/{key_id}/revoke"),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let game = app.create_game(1, true);
+    app.assign_server(&game, "server-a").await;
+
+    let (status, _) = app
+        .finalize(&winner_body(&game, &game.player1, "revoked-1"))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(
+        audit_events(&app).await,
+        vec![("finalize_shared_secret_denied".to_string(), None, Some(1))]
+    );
+
+    app.cleanup().await;
+}
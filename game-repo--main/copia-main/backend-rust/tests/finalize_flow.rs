@@ -0,0 +1,373 @@
+//! End-to-end scenarios: signed `/v1/finalize` → `chain_jobs` → finalizer → fake chain.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use solana_sdk::{pubkey::Pubkey, signature::Signer, transaction::Transaction};
+
+use backend_rust::{
+    db::chain_jobs as chain_jobs_db,
+    solana::{
+        fake_chain::FakeGameParams,
+        game_account::{DecodedGameState, GameLayout},
+        gateway::ChainGateway,
+    },
+    worker::finalizer::{build_finalization_instruction, MAX_FINALIZER_ATTEMPTS},
+};
+use common::{broken_body, winner_body, TestApp, TestGame, ENTRY_LAMPORTS};
+
+#[tokio::test]
+async fn winner_finalize_settles_on_chain() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(1, true);
+
+    let (status, body) = app
+        .finalize(&winner_body(&game, &game.player1, "settle-1"))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["match_status"], "result_pending_finalize");
+    assert_eq!(body["finalization_action"], "settle");
+    assert_eq!(body["chain_job_status"], "pending");
+
+    assert!(app.run_finalizer_once().await);
+
+    let job = app.job(1).await;
+    assert_eq!(job.status, "confirmed");
+    assert!(job.last_tx_sig.is_some());
+    assert_eq!(app.match_status(1).await, "settled");
+    assert_eq!(
+        app.chain.game(&game.game_pda).unwrap().state,
+        DecodedGameState::Settled
+    );
+    assert_eq!(app.chain.balance(&game.player1), ENTRY_LAMPORTS * 2);
+    assert!(!app.run_finalizer_once().await, "queue should be idle");
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn tampered_body_and_replayed_nonce_are_rejected() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(2, true);
+    let body = winner_body(&game, &game.player1, "replay-1");
+
+    let (status, _) = app.post_signed("/v1/finalize", &body, "nonce-1").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = app.post_signed("/v1/finalize", &body, "nonce-1").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "replayed nonce must fail");
+
+    let raw = serde_json::to_vec(&body).unwrap();
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let signature = common::sign(&timestamp, "nonce-2", &raw);
+    let tampered = winner_body(&game, &game.player2.unwrap(), "replay-1");
+    let request = axum::http::Request::builder()
+        .method("POST")
+        .uri("/v1/finalize")
+        .header("X-Timestamp", timestamp)
+        .header("X-Nonce", "nonce-2")
+        .header("X-Signature", signature)
+        .body(axum::body::Body::from(
+            serde_json::to_vec(&tampered).unwrap(),
+        ))
+        .unwrap();
+    let (status, _) = app.send(request).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED, "tampered body must fail");
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn conflicting_idempotency_keys_are_rejected() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(3, true);
+
+    let (status, _) = app
+        .finalize(&winner_body(&game, &game.player1, "key-a"))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = app
+        .finalize(&winner_body(&game, &game.player1, "key-a"))
+        .await;
+    assert_eq!(status, StatusCode::OK, "same key is idempotent: {body}");
+
+    let (status, _) = app
+        .finalize(&winner_body(&game, &game.player1, "key-b"))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, _) = app
+        .finalize(&winner_body(&game, &game.player2.unwrap(), "key-a"))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT, "same key, different winner");
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn broken_outcome_force_refunds() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(4, false);
+
+    let (status, body) = app.finalize(&broken_body(&game, "refund-1")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["finalization_action"], "force_refund");
+
+    assert!(app.run_finalizer_once().await);
+
+    assert_eq!(app.job(4).await.status, "confirmed");
+    assert_eq!(app.match_status(4).await, "refunded");
+    assert_eq!(
+        app.chain.game(&game.game_pda).unwrap().state,
+        DecodedGameState::Refunded
+    );
+    assert_eq!(app.chain.balance(&game.player1), ENTRY_LAMPORTS);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn failed_transaction_is_retried() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(5, true);
+    let (status, _) = app
+        .finalize(&winner_body(&game, &game.player2.unwrap(), "retry-1"))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    app.chain.fail_next_landings(1);
+    assert!(app.run_finalizer_once().await);
+
+    let job = app.job(5).await;
+    assert_eq!(job.status, "retrying");
+    assert_eq!(job.attempt_count, 1);
+    assert!(job.last_error.unwrap().contains("failed on-chain"));
+    assert_eq!(app.match_status(5).await, "finalizing");
+
+    app.make_jobs_due().await;
+    assert!(app.run_finalizer_once().await);
+
+    assert_eq!(app.job(5).await.status, "confirmed");
+    assert_eq!(app.match_status(5).await, "settled");
+    assert_eq!(
+        app.chain.balance(&game.player2.unwrap()),
+        ENTRY_LAMPORTS * 2
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn crash_while_submitted_is_recovered() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(6, true);
+    let (status, _) = app
+        .finalize(&winner_body(&game, &game.player1, "crash-1"))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Replay the finalizer up to the point where it has sent and recorded the
+    // transaction, then "crash" before confirming.
+    let job = chain_jobs_db::claim_next_due_finalizer_job(app.pool(), &app.program_id.to_string())
+        .await
+        .unwrap()
+        .expect("job is due");
+    let decoded = app.chain.game(&game.game_pda).unwrap();
+    let (ix, _) = build_finalization_instruction(
+        &app.default_env().idl,
+        app.program_id,
+        app.authority.pubkey(),
+        &decoded,
+        &job,
+    )
+    .unwrap();
+    let blockhash = app.chain.get_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&app.authority.pubkey()),
+        &[&app.authority],
+        blockhash,
+    );
+    let signature = app.chain.send_transaction(&tx).await.unwrap();
+    chain_jobs_db::mark_job_submitted(
+        app.pool(),
+        job.match_id,
+        job.lock_token,
+        &signature.to_string(),
+    )
+    .await
+    .unwrap();
+
+    // The lock is only reclaimable once stale.
+    assert!(!app.run_finalizer_once().await);
+    sqlx::query("update chain_jobs set locked_at = now() - interval '1 minute'")
+        .execute(app.pool())
+        .await
+        .unwrap();
+
+    assert!(app.run_finalizer_once().await);
+
+    let recovered = app.job(6).await;
+    assert_eq!(recovered.status, "confirmed");
+    assert_eq!(recovered.attempt_count, 1, "recovery must not resend");
+    assert_eq!(recovered.last_tx_sig, Some(signature.to_string()));
+    assert_eq!(app.match_status(6).await, "settled");
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn job_fails_after_max_attempts() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(7, true);
+    let (status, _) = app
+        .finalize(&winner_body(&game, &game.player1, "max-1"))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    app.chain.fail_next_sends(u32::MAX);
+    for _ in 0..MAX_FINALIZER_ATTEMPTS {
+        assert!(app.run_finalizer_once().await);
+        app.make_jobs_due().await;
+    }
+
+    let job = app.job(7).await;
+    assert_eq!(job.status, "failed");
+    assert_eq!(job.attempt_count, MAX_FINALIZER_ATTEMPTS);
+    assert!(
+        !app.run_finalizer_once().await,
+        "failed jobs are not retried"
+    );
+    assert_eq!(
+        app.chain.game(&game.game_pda).unwrap().state,
+        DecodedGameState::Joined
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn pre_upgrade_game_settles_with_legacy_instruction() {
+    let Some(app) = TestApp::spawn_with(|c| {
+        c.environments[0].program_idl_path = Some(common::V2_IDL_PATH.into())
+    })
+    .await
+    else {
+        return;
+    };
+    let game = app.create_game_with_layout(8, true, GameLayout::V1, 0);
+    assert_eq!(
+        app.chain.game(&game.game_pda).unwrap().layout,
+        GameLayout::V1
+    );
+
+    let (status, body) = app
+        .finalize(&winner_body(&game, &game.player2.unwrap(), "settle-v1"))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(app.run_finalizer_once().await);
+
+    assert_eq!(app.job(8).await.status, "confirmed");
+    assert_eq!(app.match_status(8).await, "settled");
+    assert_eq!(
+        app.chain.balance(&game.player2.unwrap()),
+        ENTRY_LAMPORTS * 2
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn v2_game_settle_pays_protocol_fee() {
+    let Some(app) = TestApp::spawn_with(|c| {
+        c.environments[0].program_idl_path = Some(common::V2_IDL_PATH.into())
+    })
+    .await
+    else {
+        return;
+    };
+    let game = app.create_game_with_layout(9, true, GameLayout::V2, 500);
+
+    let (status, body) = app
+        .finalize(&winner_body(&game, &game.player1, "settle-v2"))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(app.run_finalizer_once().await);
+
+    assert_eq!(app.match_status(9).await, "settled");
+    let pot = ENTRY_LAMPORTS * 2;
+    let fee = pot * 500 / 10_000;
+    assert_eq!(app.chain.balance(&game.player1), pot - fee);
+    assert_eq!(app.chain.balance(&app.authority.pubkey()), fee);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn token_games_are_held_instead_of_enqueued() {
+    let Some(app) = TestApp::spawn_with(|c| {
+        c.environments[0].program_idl_path = Some(common::V2_IDL_PATH.into())
+    })
+    .await
+    else {
+        return;
+    };
+    let (player1, player2) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let game_pda = app.chain.insert_game(&FakeGameParams {
+        player1,
+        player2: Some(player2),
+        authority: app.authority.pubkey(),
+        entry_amount: ENTRY_LAMPORTS,
+        match_id: 10,
+        layout: GameLayout::V2,
+        fee_bps: 0,
+        token_mint: Some(Pubkey::new_unique()),
+    });
+    let game = TestGame {
+        game_pda,
+        match_id: 10,
+        player1,
+        player2: Some(player2),
+    };
+
+    let (status, body) = app.finalize(&winner_body(&game, &player1, "token-1")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["match_status"], "held_for_review");
+    assert!(body.get("finalization_action").is_none());
+    assert!(app
+        .match_column(10, "hold_reason_detail")
+        .await
+        .unwrap()
+        .contains("token mint"));
+    let jobs: i64 = sqlx::query_scalar("select count(*) from chain_jobs")
+        .fetch_one(app.pool())
+        .await
+        .unwrap();
+    assert_eq!(jobs, 0);
+
+    // An operator cannot force it through either, until token games are supported.
+    let mut resolved = winner_body(&game, &player1, "token-2");
+    resolved["override_reason"] = "pay the winner".into();
+    let (status, body) = app
+        .admin(Method::POST, "/v1/finalize", Some(&resolved))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+    assert_eq!(app.match_status(10).await, "held_for_review");
+
+    app.cleanup().await;
+}
@@ -0,0 +1,93 @@
+//! `/healthz`, `/readyz` and `/v1/finalizer/status`.
+
+mod common;
+
+use axum::http::StatusCode;
+
+use backend_rust::worker::liveness::FinalizerState;
+use common::{winner_body, TestApp};
+
+#[tokio::test]
+async fn probes_check_database_and_rpc() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+
+    let (status, body) = app.get("/healthz").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["status"], "ok");
+
+    let (status, body) = app.get("/readyz").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let names: Vec<&str> = body["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|check| check["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["database", "default: rpc"]);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn running_finalizer_without_ticks_fails_liveness() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let liveness = &app.default_env().finalizer;
+
+    liveness.set_state(FinalizerState::Running);
+    let (status, body) = app.get("/healthz").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{body}");
+    assert_eq!(body["checks"][0]["name"], "default: finalizer");
+    assert_eq!(body["checks"][0]["ok"], false);
+
+    liveness.tick();
+    let (status, _) = app.get("/healthz").await;
+    assert_eq!(status, StatusCode::OK);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn finalizer_status_reports_queue_and_last_error() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (status, _) = app.get("/v1/finalizer/status").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let game = app.create_game(1, true);
+    let (status, _) = app
+        .finalize(&winner_body(&game, &game.player1, "status-1"))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = app.get_signed("/v1/finalizer/status").await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let env = &body["environments"][0];
+    assert_eq!(env["state"], "starting");
+    assert!(env["last_tick_at"].is_null());
+    assert_eq!(env["queue"]["pending"], 1);
+    assert!(env["oldest_due_age_seconds"].as_i64().unwrap() >= 0);
+
+    app.chain.fail_next_landings(1);
+    assert!(app.run_finalizer_once().await);
+
+    let (_, body) = app.get_signed("/v1/finalizer/status").await;
+    let env = &body["environments"][0];
+    assert!(env["last_tick_at"].as_i64().is_some());
+    assert_eq!(env["queue"]["retrying"], 1);
+    assert!(env["queue"].get("pending").is_none());
+    assert!(
+        env["oldest_due_age_seconds"].is_null(),
+        "retry is backed off"
+    );
+    assert!(env["last_job_error"]
+        .as_str()
+        .unwrap()
+        .contains("failed on-chain"));
+
+    app.cleanup().await;
+}
@@ -0,0 +1,157 @@
+//! Bundled IDL compatibility checks; these run without a database.
+
+use backend_rust::solana::{
+    fake_chain::{FakeChain, FakeGameParams},
+    game_account::{
+        decode_game_account, encode_game_account, idl_game_layout, DecodedGameState, GameLayout,
+    },
+    idl::{Idl, BUNDLED_GAME_IDL},
+    instructions::{create_game_ix, verify_deployed_program, verify_idl},
+    pda::game_address,
+};
+use solana_sdk::pubkey::Pubkey;
+
+/// The upgraded program, not yet deployed; its IDL is used via `PROGRAM_IDL_PATH`.
+const V2_IDL: &str = include_str!("fixtures/game_program_v2.json");
+
+#[test]
+fn bundled_idl_matches_backend_expectations() {
+    let idl = Idl::bundled().unwrap();
+    let program_id = idl.address.expect("bundled IDL has an address");
+    verify_idl(&idl, &program_id).unwrap();
+    // The bundled IDL is the deployed program's, which predates V2.
+    assert_eq!(idl_game_layout(&idl), GameLayout::V1);
+    assert!(idl.instruction("settle_game_v2").is_err());
+
+    let err = verify_idl(&idl, &Pubkey::new_unique()).unwrap_err();
+    assert!(err.to_string().contains("does not match PROGRAM_ID"));
+}
+
+#[test]
+fn v2_idl_matches_backend_expectations() {
+    let idl = Idl::parse(V2_IDL).unwrap();
+    verify_idl(&idl, &idl.address.unwrap()).unwrap();
+    assert_eq!(idl_game_layout(&idl), GameLayout::V2);
+}
+
+#[test]
+fn drifted_idl_is_rejected() {
+    let drifted = BUNDLED_GAME_IDL.replacen("\"match_id\"", "\"match_number\"", 1);
+    let idl = Idl::parse(&drifted).unwrap();
+    let program_id = idl.address.unwrap();
+
+    let err = verify_idl(&idl, &program_id).unwrap_err();
+    assert!(
+        format!("{err:#}").contains("create_game"),
+        "unexpected error: {err:#}"
+    );
+}
+
+#[test]
+fn instructions_and_accounts_round_trip() {
+    let idl = Idl::bundled().unwrap();
+    let program_id = Pubkey::new_unique();
+    let (player1, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let ix = create_game_ix(&idl, program_id, player1, authority, 5_000, 42).unwrap();
+    let metas: Vec<_> = ix
+        .accounts
+        .iter()
+        .map(|m| (m.pubkey, m.is_signer))
+        .collect();
+    let decoded = idl.decode_instruction(&ix.data, &metas).unwrap();
+    assert_eq!(decoded.name, "create_game");
+    assert_eq!(
+        decoded.args.field("match_id").unwrap().as_u64().unwrap(),
+        42
+    );
+    let game = decoded
+        .accounts
+        .iter()
+        .find(|(name, _, _)| name == "game")
+        .unwrap();
+    assert_eq!(
+        game.1,
+        game_address(&program_id, &player1, &authority, 42).0
+    );
+
+    let chain = FakeChain::new(program_id);
+    let game_pda = chain.insert_game(&FakeGameParams {
+        player1,
+        player2: None,
+        authority,
+        entry_amount: 5_000,
+        match_id: 42,
+        layout: chain.layout(),
+        fee_bps: 0,
+        token_mint: None,
+    });
+    let account = chain.game(&game_pda).unwrap();
+    assert_eq!(account.state, DecodedGameState::Created);
+    let bytes = encode_game_account(&idl, &account).unwrap();
+    assert_eq!(decode_game_account(&idl, &bytes).unwrap().match_id, 42);
+}
+
+#[tokio::test]
+async fn deployed_program_check_requires_an_executable_program() {
+    let idl = Idl::bundled().unwrap();
+    let program_id = Pubkey::new_unique();
+    let chain = FakeChain::new(program_id);
+
+    verify_deployed_program(&chain, &idl, &program_id)
+        .await
+        .unwrap();
+    assert!(verify_deployed_program(&chain, &idl, &Pubkey::new_unique())
+        .await
+        .is_err());
+}
+
+#[test]
+fn game_layout_is_detected_per_account() {
+    let idl = Idl::parse(V2_IDL).unwrap();
+    let program_id = Pubkey::new_unique();
+    let chain = FakeChain::with_idl(program_id, idl.clone());
+    let params = |match_id, layout| FakeGameParams {
+        player1: Pubkey::new_unique(),
+        player2: Some(Pubkey::new_unique()),
+        authority: Pubkey::new_unique(),
+        entry_amount: 1_000,
+        match_id,
+        layout,
+        fee_bps: 100,
+        token_mint: None,
+    };
+
+    let v1 = chain
+        .game(&chain.insert_game(&params(1, GameLayout::V1)))
+        .unwrap();
+    let v1_bytes = encode_game_account(&idl, &v1).unwrap();
+    let v2 = chain
+        .game(&chain.insert_game(&params(2, GameLayout::V2)))
+        .unwrap();
+    let v2_bytes = encode_game_account(&idl, &v2).unwrap();
+    assert!(v1_bytes.len() < v2_bytes.len());
+
+    // Legacy accounts, with or without zeroed reserved space after them.
+    let decoded = decode_game_account(&idl, &v1_bytes).unwrap();
+    assert_eq!(decoded.layout, GameLayout::V1);
+    assert_eq!(decoded.fee_bps, 0);
+    let mut padded = v1_bytes.clone();
+    padded.resize(v1_bytes.len() + 64, 0);
+    assert_eq!(
+        decode_game_account(&idl, &padded).unwrap().layout,
+        GameLayout::V1
+    );
+
+    let decoded = decode_game_account(&idl, &v2_bytes).unwrap();
+    assert_eq!(decoded.layout, GameLayout::V2);
+    assert_eq!(decoded.fee_bps, 100);
+    assert_eq!(decoded.token_mint, None);
+
+    let mut unknown = v2_bytes;
+    unknown[v1_bytes.len()] = 3;
+    let err = decode_game_account(&idl, &unknown).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("unsupported Game layout version 3"));
+}
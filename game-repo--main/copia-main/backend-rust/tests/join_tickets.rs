@@ -0,0 +1,236 @@
+//! Signed join tickets from challenge registration, acceptance and refresh.
+
+mod common;
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use serde_json::{json, Value};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+use backend_rust::{
+    join_ticket::{verify_join_ticket, JoinTicketIssuer},
+    solana::fake_chain::FakeGameParams,
+};
+use common::{wallet_proof, TestApp, ENTRY_LAMPORTS};
+
+fn ticket_claims(app: &TestApp, ticket: &Value) -> backend_rust::join_ticket::JoinTicketClaims {
+    let key = app.state.join_tickets.public_key();
+    verify_join_ticket(ticket.as_str().unwrap(), &key, Utc::now().timestamp()).unwrap()
+}
+
+fn refresh_body(wallet: &Keypair, game_pda: &str) -> Value {
+    let mut body = json!({"wallet": wallet.pubkey().to_string()});
+    wallet_proof(&mut body, wallet, game_pda);
+    body
+}
+
+#[tokio::test]
+async fn players_get_tickets_for_their_match_and_server() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (status, _) = app
+        .post_signed(
+            "/v1/servers/register",
+            &json!({"server_id": "server-a", "ip": "10.0.0.1", "port": 7777, "status": "idle"}),
+            "tickets-1",
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, key) = app.get("/v1/join-tickets/public-key").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(key["algorithm"], "ed25519");
+    assert_eq!(
+        key["public_key"],
+        app.state.join_tickets.public_key().to_string()
+    );
+
+    let creator = Keypair::new();
+    let acceptor = Keypair::new();
+    let reserved = app.reserve_match(&creator.pubkey()).await;
+    let match_id = reserved["match_id"].as_i64().unwrap();
+    let game_pda = reserved["game_pda"].as_str().unwrap().to_string();
+    // As if the create watcher had seen the game land.
+    sqlx::query("update matches set match_status = 'created_on_chain' where match_id = $1")
+        .bind(match_id)
+        .execute(app.pool())
+        .await
+        .unwrap();
+    let register = |signer: &Keypair| {
+        let mut body = json!({
+            "game_pda": game_pda,
+            "creator_pubkey": creator.pubkey().to_string(),
+            "entry_amount": ENTRY_LAMPORTS,
+            "match_id": match_id,
+        });
+        wallet_proof(&mut body, signer, &game_pda);
+        body
+    };
+    // Naming the creator is not enough: the request must be signed by its wallet.
+    let (status, _) = app
+        .post_json("/v1/challenges", &register(&Keypair::new()))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, registered) = app.post_json("/v1/challenges", &register(&creator)).await;
+    assert_eq!(status, StatusCode::CREATED, "{registered}");
+    let claims = ticket_claims(&app, &registered["join_ticket"]);
+    assert_eq!(claims.wallet, creator.pubkey().to_string());
+    assert_eq!(claims.match_id, match_id);
+    assert_eq!(claims.server_id, "server-a");
+    assert_eq!(claims.expires_at, registered["join_ticket_expires_at"]);
+    assert_eq!(claims.expires_at - claims.issued_at, 120);
+
+    let accept = |signer: &Keypair| {
+        let mut body = json!({"acceptor_pubkey": acceptor.pubkey().to_string()});
+        wallet_proof(&mut body, signer, &game_pda);
+        body
+    };
+    let accept_path = format!("/v1/challenges/{game_pda}/accept");
+    let (status, _) = app.post_json(&accept_path, &accept(&Keypair::new())).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        app.match_column(match_id as u64, "player2_pubkey").await,
+        None
+    );
+    let (status, accepted) = app.post_json(&accept_path, &accept(&acceptor)).await;
+    assert_eq!(status, StatusCode::OK, "{accepted}");
+    let claims = ticket_claims(&app, &accepted["join_ticket"]);
+    assert_eq!(claims.wallet, acceptor.pubkey().to_string());
+    assert_eq!(claims.server_id, "server-a");
+
+    // A reconnecting player proves it holds its wallet to get a fresh ticket.
+    let path = format!("/v1/challenges/{game_pda}/join-ticket");
+    let refresh = refresh_body(&creator, &game_pda);
+    let (status, refreshed) = app.post_json(&path, &refresh).await;
+    assert_eq!(status, StatusCode::OK, "{refreshed}");
+    // A proof is good for one request, so a captured one gets nobody a ticket.
+    let (status, _) = app.post_json(&path, &refresh).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(refreshed["server_id"], "server-a");
+    assert_eq!(refreshed["server_port"], 7777);
+    let claims = ticket_claims(&app, &refreshed["join_ticket"]);
+    assert_eq!(claims.wallet, creator.pubkey().to_string());
+
+    // Not for a wallet outside the match, nor with a signature for another game.
+    let (status, _) = app
+        .post_json(&path, &refresh_body(&Keypair::new(), &game_pda))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let mut forged = refresh_body(&creator, "another-game");
+    forged["wallet"] = creator.pubkey().to_string().into();
+    let (status, _) = app.post_json(&path, &forged).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, polled) = app.get(&format!("/v1/challenges/{game_pda}/status")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(polled["status"], "joined_on_chain");
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn clients_without_a_wallet_proof_play_without_tickets() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (status, _) = app
+        .post_signed(
+            "/v1/servers/register",
+            &json!({"server_id": "server-a", "ip": "10.0.0.1", "port": 7777, "status": "idle"}),
+            "tickets-unproven-1",
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let creator = Keypair::new();
+    let acceptor = Keypair::new();
+    let reserved = app.reserve_match(&creator.pubkey()).await;
+    let match_id = reserved["match_id"].as_u64().unwrap();
+    let game = |player2| FakeGameParams {
+        player1: creator.pubkey(),
+        player2,
+        authority: app.authority.pubkey(),
+        entry_amount: ENTRY_LAMPORTS,
+        match_id,
+        layout: app.chain.layout(),
+        fee_bps: 0,
+        token_mint: None,
+    };
+    let game_pda = app.chain.insert_game(&game(None)).to_string();
+    assert_eq!(reserved["game_pda"], game_pda);
+
+    // What the Unity client sends: no timestamp, nonce or signature.
+    let register = json!({
+        "game_pda": game_pda,
+        "creator_pubkey": creator.pubkey().to_string(),
+        "entry_amount": ENTRY_LAMPORTS,
+        "match_id": match_id,
+    });
+    let mut partial = register.clone();
+    partial["timestamp"] = Utc::now().timestamp().into();
+    let (status, _) = app.post_json("/v1/challenges", &partial).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, registered) = app.post_json("/v1/challenges", &register).await;
+    assert_eq!(status, StatusCode::CREATED, "{registered}");
+    assert_eq!(registered["server_port"], 7777);
+    assert!(registered.get("join_ticket").is_none(), "{registered}");
+
+    // Nobody can be accepted before the chain shows who joined.
+    let accept_path = format!("/v1/challenges/{game_pda}/accept");
+    let accept = json!({"acceptor_pubkey": acceptor.pubkey().to_string()});
+    let (status, _) = app.post_json(&accept_path, &accept).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    app.chain.insert_game(&game(Some(acceptor.pubkey())));
+    let stranger = json!({"acceptor_pubkey": Keypair::new().pubkey().to_string()});
+    let (status, _) = app.post_json(&accept_path, &stranger).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(app.match_column(match_id, "player2_pubkey").await, None);
+
+    let (status, accepted) = app.post_json(&accept_path, &accept).await;
+    assert_eq!(status, StatusCode::OK, "{accepted}");
+    assert_eq!(accepted["status"], "matched");
+    assert!(accepted.get("join_ticket").is_none(), "{accepted}");
+    assert_eq!(
+        app.match_column(match_id, "player2_pubkey").await,
+        Some(acceptor.pubkey().to_string())
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn tickets_fail_verification_when_expired_tampered_or_foreign() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    // Only the in-memory chain may sign with a key generated at startup.
+    let config = &app.state.config;
+    assert!(config.join_ticket_keypair_path.is_none());
+    assert!(JoinTicketIssuer::from_config(config, true).is_ok());
+    let err = JoinTicketIssuer::from_config(config, false).err().unwrap();
+    assert!(
+        err.to_string().contains("JOIN_TICKET_KEYPAIR_PATH"),
+        "{err:#}"
+    );
+
+    let issuer = &app.state.join_tickets;
+    let key = issuer.public_key();
+    let issued = issuer.issue("wallet-1", 3, "server-a");
+    let now = Utc::now().timestamp();
+
+    assert!(verify_join_ticket(&issued.ticket, &key, now).is_ok());
+    assert!(verify_join_ticket(&issued.ticket, &key, issued.expires_at).is_err());
+    assert!(verify_join_ticket(&issued.ticket, &Pubkey::new_unique(), now).is_err());
+
+    let other = issuer.issue("wallet-2", 3, "server-a");
+    let (payload, _) = other.ticket.split_once('.').unwrap();
+    let (_, signature) = issued.ticket.split_once('.').unwrap();
+    assert!(verify_join_ticket(&format!("{payload}.{signature}"), &key, now).is_err());
+
+    app.cleanup().await;
+}
@@ -0,0 +1,149 @@
+//! Kickoff reports from the assigned server, moving matches to `in_progress`.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use common::{winner_body, TestApp, TestGame};
+
+async fn issue(app: &TestApp, server_id: &str) -> (String, String) {
+    let (status, body) = app
+        .admin(
+            Method::POST,
+            &format!("/v1/admin/servers/{server_id}/credentials"),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    (
+        body["key_id"].as_str().unwrap().to_string(),
+        body["secret"].as_str().unwrap().to_string(),
+    )
+}
+
+fn present(game: &TestGame) -> Value {
+    json!({"present_wallets": [game.player1.to_string(), game.player2.unwrap().to_string()]})
+}
+
+#[tokio::test]
+async fn assigned_server_kicks_off_a_joined_match_once() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (key_a, secret_a) = issue(&app, "server-a").await;
+    let (key_b, secret_b) = issue(&app, "server-b").await;
+    let game = app.create_game(1, true);
+    app.assign_server(&game, "server-a").await;
+    let path = "/v1/matches/1/kickoff";
+
+    let (status, _) = app
+        .send_with_key(Method::POST, path, Some(&present(&game)), &key_b, &secret_b)
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let stranger = json!({
+        "present_wallets": [game.player1.to_string(), solana_sdk::pubkey::Pubkey::new_unique().to_string()]
+    });
+    for body in [stranger, json!({"present_wallets": []})] {
+        let (status, _) = app
+            .send_with_key(Method::POST, path, Some(&body), &key_a, &secret_a)
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    // Players are recorded as they connect; the match starts once both have.
+    let player1 = json!({"present_wallets": [game.player1.to_string()]});
+    let (status, partial) = app
+        .send_with_key(Method::POST, path, Some(&player1), &key_a, &secret_a)
+        .await;
+    assert_eq!(status, StatusCode::OK, "{partial}");
+    assert_eq!(partial["match_status"], "joined_on_chain");
+    assert!(partial.get("kicked_off_at").is_none());
+    assert_eq!(partial["present_wallets"], player1["present_wallets"]);
+    assert_eq!(app.match_status(1).await, "joined_on_chain");
+
+    let player2 = json!({"present_wallets": [game.player2.unwrap().to_string()]});
+    let (status, first) = app
+        .send_with_key(Method::POST, path, Some(&player2), &key_a, &secret_a)
+        .await;
+    assert_eq!(status, StatusCode::OK, "{first}");
+    assert_eq!(first["match_status"], "in_progress");
+    assert_eq!(first["present_wallets"], present(&game)["present_wallets"]);
+    assert_eq!(app.match_status(1).await, "in_progress");
+
+    let (status, again) = app
+        .send_with_key(Method::POST, path, Some(&present(&game)), &key_a, &secret_a)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(again["kicked_off_at"], first["kicked_off_at"]);
+
+    let (_, polled) = app
+        .get(&format!("/v1/challenges/{}/status", game.game_pda))
+        .await;
+    assert_eq!(polled["status"], "in_progress");
+    assert_eq!(polled["kicked_off_at"], first["kicked_off_at"]);
+
+    // A started match still finalizes, and keeps its kickoff record.
+    let (status, body) = app
+        .send_with_key(
+            Method::POST,
+            "/v1/finalize",
+            Some(&winner_body(&game, &game.player2.unwrap(), "kickoff-1")),
+            &key_a,
+            &secret_a,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(app.match_status(1).await, "result_pending_finalize");
+    assert!(app.match_column(1, "kicked_off_at::text").await.is_some());
+
+    let (status, _) = app
+        .send_with_key(Method::POST, path, Some(&present(&game)), &key_a, &secret_a)
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn matches_kick_off_only_once_joined_and_assigned() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    // The creator may connect before anyone joins; that alone starts nothing.
+    let created = app.create_game(1, false);
+    app.assign_server(&created, "server-a").await;
+    let body = json!({"present_wallets": [created.player1.to_string()]});
+    let (status, reported) = app
+        .post_signed("/v1/matches/1/kickoff", &body, "kickoff-created")
+        .await;
+    assert_eq!(status, StatusCode::OK, "{reported}");
+    assert_eq!(reported["match_status"], "created_on_chain");
+    assert!(app
+        .match_column(1, "player1_connected_at::text")
+        .await
+        .is_some());
+
+    let (status, _) = app
+        .post_signed("/v1/matches/2/kickoff", &body, "kickoff-missing")
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Known to the backend through finalize only, without a server.
+    let unassigned = app.create_game(3, true);
+    let (status, _) = app
+        .finalize(&winner_body(&unassigned, &unassigned.player1, "kickoff-3"))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .post_signed(
+            "/v1/matches/3/kickoff",
+            &present(&unassigned),
+            "kickoff-unassigned",
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    app.cleanup().await;
+}
@@ -0,0 +1,89 @@
+//! `/metrics`: the Prometheus counters of HMAC auth and the finalizer.
+
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+
+use common::{winner_body, TestApp, ENTRY_LAMPORTS};
+
+/// The value of one series, e.g. `chain_job_transitions_total{to="pending"}`.
+async fn sample(app: &TestApp, series: &str) -> f64 {
+    let (status, text) = app.get_text("/metrics").await;
+    assert_eq!(status, StatusCode::OK);
+    text.lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map_or(0.0, |value| value.parse().unwrap())
+}
+
+#[tokio::test]
+async fn hmac_rejections_are_counted_by_reason() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(1, true);
+    let body = winner_body(&game, &game.player1, "metrics-1");
+
+    let (status, _) = app.post_signed("/v1/finalize", &body, "nonce-1").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.post_signed("/v1/finalize", &body, "nonce-1").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app.post_json("/v1/finalize", &body).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let replayed = r#"hmac_rejections_total{reason="replayed_nonce"}"#;
+    assert_eq!(sample(&app, replayed).await, 1.0);
+    let missing = r#"hmac_rejections_total{reason="missing_header"}"#;
+    assert_eq!(sample(&app, missing).await, 1.0);
+    let finalized = r#"http_requests_total{method="POST",route="/v1/finalize",status="200"}"#;
+    assert!(sample(&app, finalized).await >= 1.0);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn finalizer_transitions_failures_and_payouts_are_counted() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(2, true);
+    let (status, _) = app
+        .finalize(&winner_body(&game, &game.player1, "metrics-2"))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    app.chain.fail_next_landings(1);
+    assert!(app.run_finalizer_once().await);
+    app.make_jobs_due().await;
+    assert!(app.run_finalizer_once().await);
+    assert_eq!(app.match_status(2).await, "settled");
+
+    for (to, count) in [
+        ("pending", 1.0),
+        ("submitted", 2.0),
+        ("retrying", 1.0),
+        ("confirmed", 1.0),
+    ] {
+        let series = format!(r#"chain_job_transitions_total{{to="{to}"}}"#);
+        assert_eq!(sample(&app, &series).await, count, "{series}");
+    }
+    assert_eq!(sample(&app, "chain_job_attempts_total").await, 2.0);
+    let failures = r#"chain_job_failures_total{class="confirmation"}"#;
+    assert_eq!(sample(&app, failures).await, 1.0);
+    let confirmations = r#"chain_job_confirmation_seconds_count{job_type="settle"}"#;
+    assert_eq!(sample(&app, confirmations).await, 1.0);
+    assert_eq!(
+        sample(&app, "lamports_settled_total").await,
+        (ENTRY_LAMPORTS * 2) as f64
+    );
+
+    let register = json!({ "server_id": "gs-1", "ip": "10.0.0.1", "port": 7777, "status": "idle" });
+    let (status, _) = app
+        .post_signed("/v1/servers/register", &register, "register-1")
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let idle = r#"server_pool_servers{status="idle"}"#;
+    assert_eq!(sample(&app, idle).await, 1.0);
+
+    app.cleanup().await;
+}
@@ -0,0 +1,65 @@
+//! Expiry of HMAC nonces by the nonce pruner.
+
+mod common;
+
+use axum::http::StatusCode;
+
+use backend_rust::worker::nonce_pruner::{prune_expired_nonces, NONCE_RETENTION_SECONDS};
+use common::{winner_body, TestApp};
+
+async fn nonce_count(app: &TestApp) -> i64 {
+    sqlx::query_scalar("select count(*) from used_nonces")
+        .fetch_one(app.pool())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn pruner_deletes_only_nonces_past_the_replay_window() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    // More than one delete batch of expired nonces, and one just inside the window.
+    sqlx::query(
+        "insert into used_nonces (nonce, created_at)
+         select 'old-' || n, now() - make_interval(secs => $1 + 5)
+         from generate_series(1, 12000) as n",
+    )
+    .bind(NONCE_RETENTION_SECONDS as f64)
+    .execute(app.pool())
+    .await
+    .unwrap();
+    sqlx::query(
+        "insert into used_nonces (nonce, created_at)
+         values ('recent', now() - make_interval(secs => $1 - 30))",
+    )
+    .bind(NONCE_RETENTION_SECONDS as f64)
+    .execute(app.pool())
+    .await
+    .unwrap();
+
+    let game = app.create_game(1, true);
+    let body = winner_body(&game, &game.player1, "nonces-1");
+    let (status, _) = app.post_signed("/v1/finalize", &body, "live-nonce").await;
+    assert_eq!(status, StatusCode::OK);
+
+    assert_eq!(prune_expired_nonces(&app.state).await.unwrap(), 12_000);
+    assert_eq!(nonce_count(&app).await, 2);
+
+    // A nonce still inside its window keeps guarding against replay.
+    let (status, _) = app.post_signed("/v1/finalize", &body, "live-nonce").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (_, metrics) = app.get_text("/metrics").await;
+    assert!(metrics.lines().any(|line| line == "used_nonces_rows 2"));
+    assert!(metrics
+        .lines()
+        .any(|line| line == "used_nonces_pruned_total 12000"));
+    assert!(metrics
+        .lines()
+        .any(|line| line.starts_with("used_nonces_bytes ") && line != "used_nonces_bytes 0"));
+
+    assert_eq!(prune_expired_nonces(&app.state).await.unwrap(), 0);
+
+    app.cleanup().await;
+}
@@ -0,0 +1,95 @@
+//! Startup preflight: the checks `main` and `backend-rust check` run before serving.
+
+mod common;
+
+use solana_sdk::signature::Keypair;
+
+use backend_rust::{
+    preflight,
+    solana::{idl::Idl, signer::SignerConfig},
+};
+use common::{write_temp_keypair, TestApp};
+
+#[tokio::test]
+async fn healthy_setup_passes() {
+    // The bundled IDL is pinned to the deployed program id.
+    let bundled = Idl::bundled().unwrap().address.unwrap();
+    let Some(app) =
+        TestApp::spawn_with(|config| config.environments[0].program_id = bundled.to_string()).await
+    else {
+        return;
+    };
+
+    let report = preflight::run(&app.state).await;
+    assert!(report.passed(), "{}", report.render());
+    assert_eq!(
+        report.check("default: rpc").unwrap().outcome,
+        Ok("cluster mock".into())
+    );
+    assert!(report.check("default: program").unwrap().outcome.is_ok());
+    assert!(report.check("database migrations").unwrap().outcome.is_ok());
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn empty_secret_and_unfunded_authority_fail() {
+    let Some(app) = TestApp::spawn_with(|config| {
+        config.internal_hmac_secret = " ".into();
+        config.fee_payer_min_balance_lamports = 1;
+    })
+    .await
+    else {
+        return;
+    };
+
+    let report = preflight::run(&app.state).await;
+    assert!(!report.passed());
+    assert!(report.check("hmac secret").unwrap().outcome.is_err());
+    assert!(report.check("default: signers").unwrap().outcome.is_ok());
+    let balances = report.check("default: balances").unwrap();
+    assert!(balances
+        .outcome
+        .as_ref()
+        .unwrap_err()
+        .contains("holds 0 lamports"));
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn wrong_authority_key_and_pending_migration_fail() {
+    let Some(app) = TestApp::spawn_with(|config| {
+        config.environments[0].authorities[0].signer = SignerConfig::KeypairFile {
+            path: write_temp_keypair(&Keypair::new()),
+        };
+    })
+    .await
+    else {
+        return;
+    };
+    sqlx::query(
+        "delete from _sqlx_migrations where version = (select max(version) from _sqlx_migrations)",
+    )
+    .execute(app.pool())
+    .await
+    .unwrap();
+
+    let report = preflight::run(&app.state).await;
+    assert!(!report.passed());
+    let signers = report.check("default: signers").unwrap();
+    assert!(signers
+        .outcome
+        .as_ref()
+        .unwrap_err()
+        .contains("does not match"));
+    assert!(report.check("default: balances").is_none());
+    let migrations = report.check("database migrations").unwrap();
+    assert!(migrations
+        .outcome
+        .as_ref()
+        .unwrap_err()
+        .contains("not applied"));
+
+    app.cleanup().await;
+}
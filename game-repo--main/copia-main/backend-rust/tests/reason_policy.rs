@@ -0,0 +1,212 @@
+//! The `reason_code` registry deciding what `broken` results do.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use backend_rust::reason_policy::{ReasonAction, ReasonPolicy};
+use common::{broken_body, winner_body, TestApp, TestGame};
+
+fn broken_with(game: &TestGame, reason_code: &str, present: &[String], key: &str) -> Value {
+    let mut body = broken_body(game, key);
+    body["reason_code"] = reason_code.into();
+    body["present_wallets"] = json!(present);
+    body
+}
+
+#[test]
+fn overrides_change_and_extend_the_defaults() {
+    let policy = ReasonPolicy::with_overrides(" no_show=hold_for_review, lag_out=refund ").unwrap();
+    assert_eq!(policy.action("no_show"), ReasonAction::HoldForReview);
+    assert_eq!(policy.action("lag_out"), ReasonAction::Refund);
+    assert_eq!(policy.action("rage_quit"), ReasonAction::SettleToPresent);
+    assert_eq!(policy.action("server_crash"), ReasonAction::Refund);
+    assert!(!policy.is_registered("made_up"));
+    assert_eq!(policy.action("made_up"), ReasonAction::Refund);
+
+    let policy = ReasonPolicy::with_overrides("*=hold_for_review").unwrap();
+    assert_eq!(policy.action("made_up"), ReasonAction::HoldForReview);
+    assert_eq!(policy.action("server_crash"), ReasonAction::Refund);
+
+    assert!(ReasonPolicy::with_overrides("no_show").is_err());
+    assert!(ReasonPolicy::with_overrides("no_show=pay_everyone").is_err());
+    assert!(ReasonPolicy::with_overrides("*=settle_to_present").is_err());
+}
+
+#[tokio::test]
+async fn every_unity_broken_reason_refunds() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    // Sent by MatchManager.cs and GameTimer.cs, plus one this build does not know.
+    let reasons = [
+        "disconnect_inconsistent_metadata",
+        "disconnect_unknown_winner",
+        "invalid_winner_wallet",
+        "invalid_winner_metadata",
+        "unspecified",
+        "timer_end_draw",
+        "timer_end_missing_wallets",
+        "made_up",
+    ];
+    for (match_id, reason) in (1..).zip(reasons) {
+        let game = app.create_game(match_id, true);
+        let present = [game.player1.to_string()];
+        let (status, body) = app
+            .finalize(&broken_with(&game, reason, &present, reason))
+            .await;
+        assert_eq!(status, StatusCode::OK, "{reason}: {body}");
+        assert_eq!(body["finalization_action"], "force_refund", "{reason}");
+    }
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn settle_to_present_pays_the_one_player_who_showed_up() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(1, true);
+    app.kick_off(&game, "server-a").await;
+    let player1 = game.player1.to_string();
+    let player2 = game.player2.unwrap().to_string();
+
+    for present in [
+        vec![player1.clone(), player2.clone()],
+        vec![solana_sdk::pubkey::Pubkey::new_unique().to_string()],
+    ] {
+        let (status, _) = app
+            .finalize(&broken_with(&game, "no_show", &present, "policy-1"))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    let (status, body) = app
+        .finalize(&broken_with(
+            &game,
+            "no_show",
+            std::slice::from_ref(&player2),
+            "policy-1",
+        ))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["finalization_action"], "settle");
+    assert_eq!(app.match_column(1, "winner_pubkey").await, Some(player2));
+    assert_eq!(
+        app.match_column(1, "finalization_reason_code")
+            .await
+            .as_deref(),
+        Some("no_show")
+    );
+
+    // Nobody present, or nobody else paid in: a refund.
+    let empty = app.create_game(2, true);
+    let (_, body) = app
+        .finalize(&broken_with(&empty, "disconnect", &[], "policy-2"))
+        .await;
+    assert_eq!(body["finalization_action"], "force_refund");
+    let unjoined = app.create_game(3, false);
+    let present = [unjoined.player1.to_string()];
+    let (_, body) = app
+        .finalize(&broken_with(&unjoined, "no_show", &present, "policy-3"))
+        .await;
+    assert_eq!(body["finalization_action"], "force_refund");
+
+    // Nor is a player the server never reported connected paid.
+    let never_started = app.create_game(5, true);
+    app.assign_server(&never_started, "server-a").await;
+    let present = [never_started.player1.to_string()];
+    let (status, body) = app
+        .finalize(&broken_with(
+            &never_started,
+            "no_show",
+            &present,
+            "policy-5",
+        ))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["finalization_action"], "force_refund");
+    assert_eq!(app.match_column(5, "winner_pubkey").await, None);
+
+    // A no-show proper: only player1 ever connected, so the match never started.
+    let no_show = app.create_game(6, true);
+    app.assign_server(&no_show, "server-a").await;
+    let present = [no_show.player1.to_string()];
+    let (status, reported) = app
+        .post_signed(
+            "/v1/matches/6/kickoff",
+            &json!({ "present_wallets": present }),
+            "policy-6-connect",
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{reported}");
+    assert_eq!(app.match_status(6).await, "joined_on_chain");
+    let (status, body) = app
+        .finalize(&broken_with(&no_show, "no_show", &present, "policy-6"))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["finalization_action"], "settle");
+    assert_eq!(
+        app.match_column(6, "winner_pubkey").await.as_deref(),
+        Some(present[0].as_str())
+    );
+
+    let crashed = app.create_game(4, true);
+    let present = [crashed.player1.to_string()];
+    let (_, body) = app
+        .finalize(&broken_with(&crashed, "server_crash", &present, "policy-4"))
+        .await;
+    assert_eq!(body["finalization_action"], "force_refund");
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn held_results_wait_for_an_admin_override() {
+    let Some(app) = TestApp::spawn_with(|config| {
+        config
+            .reason_policy
+            .set("rage_quit", ReasonAction::HoldForReview)
+    })
+    .await
+    else {
+        return;
+    };
+    let game = app.create_game(1, true);
+    let body = broken_with(&game, "rage_quit", &[game.player1.to_string()], "held-1");
+
+    for _ in 0..2 {
+        let (status, held) = app.finalize(&body).await;
+        assert_eq!(status, StatusCode::OK, "{held}");
+        assert_eq!(held["match_status"], "held_for_review");
+        assert!(held.get("finalization_action").is_none());
+    }
+    let jobs: i64 = sqlx::query_scalar("select count(*) from chain_jobs")
+        .fetch_one(app.pool())
+        .await
+        .unwrap();
+    assert_eq!(jobs, 0);
+
+    // The server cannot change its mind; an operator decides.
+    let (status, _) = app
+        .finalize(&winner_body(&game, &game.player1, "held-2"))
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let mut resolved = winner_body(&game, &game.player1, "held-3");
+    resolved["override_reason"] = "reviewed the replay; player2 quit".into();
+    let (status, body) = app
+        .admin(Method::POST, "/v1/finalize", Some(&resolved))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["match_status"], "result_pending_finalize");
+    assert_eq!(body["finalization_action"], "settle");
+    assert_eq!(
+        app.match_column(1, "hold_reason_code").await.as_deref(),
+        Some("rage_quit")
+    );
+
+    app.cleanup().await;
+}
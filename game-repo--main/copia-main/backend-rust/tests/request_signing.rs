@@ -0,0 +1,119 @@
+//! Canonical (version 2) request signatures and the version 1 transition.
+
+mod common;
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+};
+use serde_json::json;
+
+use backend_rust::api::request_signing::{canonical_v2, RequestSigner};
+use common::{TestApp, HMAC_SECRET};
+
+fn request(method: Method, path: &str, headers: &[(&str, String)], body: &[u8]) -> Request<Body> {
+    let mut request = Request::builder().method(method).uri(path);
+    for (name, value) in headers {
+        request = request.header(*name, value);
+    }
+    request.body(Body::from(body.to_vec())).unwrap()
+}
+
+#[test]
+fn canonical_request_format_is_stable() {
+    let message = canonical_v2(
+        "1700000000",
+        "n1",
+        Some("sk_1"),
+        &Method::PUT,
+        "/v1/servers/a/heartbeat",
+        Some("b=2&a=1&"),
+        b"{}",
+    );
+    assert_eq!(
+        String::from_utf8(message).unwrap(),
+        "HMAC-SHA256-V2\n1700000000\nn1\nsk_1\nPUT\n/v1/servers/a/heartbeat\na=1&b=2\n\
+         44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+    );
+}
+
+#[tokio::test]
+async fn signatures_only_hold_for_their_endpoint() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let signer = RequestSigner::new(HMAC_SECRET, None);
+    let body = serde_json::to_vec(&json!({"status": "idle"})).unwrap();
+
+    // A heartbeat signature does not carry over to another server's heartbeat.
+    let headers = signer.sign(&Method::PUT, "/v1/servers/server-a/heartbeat", &body);
+    let (status, _) = app
+        .send(request(
+            Method::PUT,
+            "/v1/servers/server-b/heartbeat",
+            &headers,
+            &body,
+        ))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Nor to another method, nor to another query.
+    let headers = signer.sign(&Method::GET, "/v1/finalizer/status?env=a", b"");
+    let (status, _) = app
+        .send(request(
+            Method::GET,
+            "/v1/finalizer/status?env=b",
+            &headers,
+            b"",
+        ))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // The query may be reordered on the way.
+    let headers = signer.sign(&Method::GET, "/v1/finalizer/status?a=1&b=2", b"");
+    let (status, _) = app
+        .send(request(
+            Method::GET,
+            "/v1/finalizer/status?b=2&a=1",
+            &headers,
+            b"",
+        ))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let headers = signer.sign(&Method::PUT, "/v1/servers/server-a/heartbeat", &body);
+    let (status, _) = app
+        .send(request(
+            Method::PUT,
+            "/v1/servers/server-a/heartbeat",
+            &headers,
+            &body,
+        ))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn version_1_signatures_are_refused_once_disabled() {
+    let Some(app) = TestApp::spawn_with(|config| config.hmac_v1_signatures_enabled = false).await
+    else {
+        return;
+    };
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let v1 = [
+        ("X-Timestamp", timestamp.clone()),
+        ("X-Nonce", "v1-nonce".to_string()),
+        ("X-Signature", common::sign(&timestamp, "v1-nonce", b"")),
+    ];
+    let (status, _) = app
+        .send(request(Method::GET, "/v1/finalizer/status", &v1, b""))
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = app.get_signed("/v1/finalizer/status").await;
+    assert_eq!(status, StatusCode::OK);
+
+    app.cleanup().await;
+}
@@ -0,0 +1,147 @@
+//! Backend-issued match ids: reserve → `create_game` on-chain → watcher.
+
+mod common;
+
+use axum::http::StatusCode;
+use serde_json::json;
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+
+use backend_rust::{
+    solana::{gateway::ChainGateway, instructions::create_game_ix, pda::derive_match_pdas},
+    worker::create_watcher::process_waiting_matches,
+};
+use common::{wallet_proof, TestApp, ENTRY_LAMPORTS};
+
+async fn create_on_chain(app: &TestApp, creator: &Keypair, match_id: u64) {
+    app.chain.airdrop(&creator.pubkey(), ENTRY_LAMPORTS * 2);
+    let ix = create_game_ix(
+        &app.default_env().idl,
+        app.program_id,
+        creator.pubkey(),
+        app.authority.pubkey(),
+        ENTRY_LAMPORTS,
+        match_id,
+    )
+    .unwrap();
+    let blockhash = app.chain.get_latest_blockhash().await.unwrap();
+    let tx =
+        Transaction::new_signed_with_payer(&[ix], Some(&creator.pubkey()), &[creator], blockhash);
+    app.chain.send_transaction(&tx).await.unwrap();
+}
+
+#[tokio::test]
+async fn reserved_match_moves_to_created_once_the_game_lands() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let creator = Keypair::new();
+
+    let first = app.reserve_match(&creator.pubkey()).await;
+    let second = app.reserve_match(&creator.pubkey()).await;
+    assert_ne!(first["match_id"], second["match_id"]);
+    assert_eq!(first["match_status"], "waiting_create_tx");
+
+    let match_id = first["match_id"].as_i64().unwrap();
+    let pdas = derive_match_pdas(
+        &app.program_id.to_string(),
+        &app.authority.pubkey().to_string(),
+        &creator.pubkey().to_string(),
+        match_id,
+    )
+    .unwrap();
+    assert_eq!(first["game_pda"], pdas.game_pda);
+    assert_eq!(first["vault_pda"], pdas.vault_pda);
+
+    assert_eq!(
+        process_waiting_matches(&app.state, app.default_env())
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(app.match_status(match_id as u64).await, "waiting_create_tx");
+
+    create_on_chain(&app, &creator, match_id as u64).await;
+    assert_eq!(
+        process_waiting_matches(&app.state, app.default_env())
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(app.match_status(match_id as u64).await, "created_on_chain");
+    // The game's own creation time, not when the watcher noticed it.
+    let game_pda = pdas.game_pda.parse().unwrap();
+    let created_at = app.chain.game(&game_pda).unwrap().created_at;
+    assert_eq!(
+        app.match_column(
+            match_id as u64,
+            &format!("(extract(epoch from created_onchain_at) = {created_at})::text"),
+        )
+        .await
+        .as_deref(),
+        Some("true")
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn expired_reservation_without_a_game_is_dropped() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let creator = Keypair::new();
+    let reserved = app.reserve_match(&creator.pubkey()).await;
+    let match_id = reserved["match_id"].as_i64().unwrap();
+
+    sqlx::query(
+        "update matches set create_expires_at = now() - interval '1 second' where match_id = $1",
+    )
+    .bind(match_id)
+    .execute(app.pool())
+    .await
+    .unwrap();
+    assert_eq!(
+        process_waiting_matches(&app.state, app.default_env())
+            .await
+            .unwrap(),
+        1
+    );
+
+    let remaining: i64 = sqlx::query_scalar("select count(*) from matches where match_id = $1")
+        .bind(match_id)
+        .fetch_one(app.pool())
+        .await
+        .unwrap();
+    assert_eq!(remaining, 0);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn reserved_match_id_cannot_be_registered_by_someone_else() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let creator = Keypair::new();
+    let reserved = app.reserve_match(&creator.pubkey()).await;
+
+    let someone_else = Keypair::new();
+    let mut request = json!({
+        "game_pda": reserved["game_pda"],
+        "creator_pubkey": someone_else.pubkey().to_string(),
+        "entry_amount": ENTRY_LAMPORTS,
+        "match_id": reserved["match_id"],
+    });
+    wallet_proof(
+        &mut request,
+        &someone_else,
+        reserved["game_pda"].as_str().unwrap(),
+    );
+    let (status, body) = app.post_json("/v1/challenges", &request).await;
+    assert_eq!(status, StatusCode::CONFLICT, "{body}");
+
+    app.cleanup().await;
+}
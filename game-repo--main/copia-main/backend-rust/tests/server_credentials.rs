@@ -0,0 +1,203 @@
+//! Per-server HMAC credentials: issuing, rotation, revocation and server identity.
+
+mod common;
+
+use axum::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use common::{winner_body, TestApp};
+
+async fn issue(app: &TestApp, server_id: &str) -> (String, String) {
+    let (status, body) = app
+        .admin(
+            Method::POST,
+            &format!("/v1/admin/servers/{server_id}/credentials"),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["server_id"], server_id);
+    (
+        body["key_id"].as_str().unwrap().to_string(),
+        body["secret"].as_str().unwrap().to_string(),
+    )
+}
+
+fn register_body(server_id: &str) -> Value {
+    json!({"server_id": server_id, "ip": "10.0.0.1", "port": 7777, "status": "idle"})
+}
+
+#[tokio::test]
+async fn servers_can_only_act_as_themselves() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (key_id, secret) = issue(&app, "server-a").await;
+
+    let (status, body) = app
+        .send_with_key(
+            Method::POST,
+            "/v1/servers/register",
+            Some(&register_body("server-a")),
+            &key_id,
+            &secret,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // Nor register, nor heartbeat as another server.
+    let (status, _) = app
+        .send_with_key(
+            Method::POST,
+            "/v1/servers/register",
+            Some(&register_body("server-b")),
+            &key_id,
+            &secret,
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .send_with_key(
+            Method::PUT,
+            "/v1/servers/server-b/heartbeat",
+            Some(&json!({"status": "idle"})),
+            &key_id,
+            &secret,
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // The shared secret may no longer speak for a server with its own key...
+    let (status, _) = app
+        .post_signed(
+            "/v1/servers/register",
+            &register_body("server-a"),
+            "shared-1",
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    // ...but still for one without.
+    let (status, _) = app
+        .post_signed(
+            "/v1/servers/register",
+            &register_body("server-b"),
+            "shared-2",
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Results are attributed to the authenticated server.
+    let game = app.create_game(1, true);
+    app.assign_server(&game, "server-a").await;
+    let (status, body) = app
+        .send_with_key(
+            Method::POST,
+            "/v1/finalize",
+            Some(&winner_body(&game, &game.player1, "creds-1")),
+            &key_id,
+            &secret,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        app.match_column(1, "result_reported_by").await.as_deref(),
+        Some("server-a")
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn keys_overlap_while_rotating_and_stop_when_revoked() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (old_key, old_secret) = issue(&app, "server-a").await;
+    let (new_key, new_secret) = issue(&app, "server-a").await;
+    let heartbeat = json!({"status": "idle"});
+    let path = "/v1/servers/server-a/heartbeat";
+
+    for (key_id, secret) in [(&old_key, &old_secret), (&new_key, &new_secret)] {
+        let (status, _) = app
+            .send_with_key(Method::PUT, path, Some(&heartbeat), key_id, secret)
+            .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let (status, body) = app
+        .admin(
+            Method::POST,
+            &format!("/v1/admin/credentials/{old_key}/revoke"),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["active"], false);
+
+    let (status, _) = app
+        .send_with_key(Method::PUT, path, Some(&heartbeat), &old_key, &old_secret)
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app
+        .send_with_key(Method::PUT, path, Some(&heartbeat), &new_key, &new_secret)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = app
+        .admin(Method::GET, "/v1/admin/servers/server-a/credentials", None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let credentials = body["credentials"].as_array().unwrap();
+    assert_eq!(credentials.len(), 2);
+    assert!(credentials.iter().all(|c| c.get("secret").is_none()));
+    let active = |key: &str| {
+        credentials
+            .iter()
+            .find(|c| c["key_id"] == key)
+            .map(|c| c["active"].clone())
+    };
+    assert_eq!(active(&old_key), Some(json!(false)));
+    assert_eq!(active(&new_key), Some(json!(true)));
+
+    // Admin endpoints need the admin key, not a server's or the shared one.
+    let (status, _) = app
+        .send_with_key(
+            Method::POST,
+            "/v1/admin/servers/server-a/credentials",
+            None,
+            &new_key,
+            &new_secret,
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .send_with_key(
+            Method::POST,
+            "/v1/admin/servers/server-a/credentials",
+            None,
+            "admin",
+            "wrong-secret",
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Revoking every key does not let the shared secret act for the server again.
+    let (status, _) = app
+        .admin(
+            Method::POST,
+            &format!("/v1/admin/credentials/{new_key}/revoke"),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .post_signed(
+            "/v1/servers/register",
+            &register_body("server-a"),
+            "shared-revoked",
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    app.cleanup().await;
+}
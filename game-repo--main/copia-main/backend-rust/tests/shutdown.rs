@@ -0,0 +1,83 @@
+//! Shutdown: the finalizer stops claiming and never leaves a job locked.
+
+mod common;
+
+use std::time::Duration;
+
+use backend_rust::worker::finalizer;
+use common::{winner_body, TestApp};
+use uuid::Uuid;
+
+async fn lock_token(app: &TestApp, match_id: u64) -> Option<Uuid> {
+    sqlx::query_scalar("select lock_token from chain_jobs where match_id = $1")
+        .bind(match_id as i64)
+        .fetch_one(app.pool())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn finalizer_claims_nothing_once_shutdown_is_triggered() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(1, true);
+    let (status, body) = app
+        .finalize(&winner_body(&game, &game.player1, "shutdown-1"))
+        .await;
+    assert!(status.is_success(), "{body}");
+
+    app.state.shutdown.trigger(Duration::from_secs(5));
+    assert!(!app.run_finalizer_once().await);
+
+    let job = app.job(1).await;
+    assert_eq!(job.status, "pending");
+    assert_eq!(job.attempt_count, 0);
+    assert_eq!(lock_token(&app, 1).await, None);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn deadline_releases_a_job_waiting_for_confirmation() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(2, true);
+    let (status, body) = app
+        .finalize(&winner_body(&game, &game.player1, "shutdown-2"))
+        .await;
+    assert!(status.is_success(), "{body}");
+
+    // The transaction lands, but its status never shows: the finalizer keeps polling.
+    app.chain.hide_signature_statuses(true);
+    let worker = tokio::spawn({
+        let (state, env, signers) = (app.state.clone(), app.default_env().clone(), app.signers());
+        async move { finalizer::process_one_job(&state, &env, &signers).await }
+    });
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while app.job(2).await.status != "submitted" {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("job submitted");
+
+    app.state.shutdown.trigger(Duration::ZERO);
+    let processed = tokio::time::timeout(Duration::from_secs(5), worker)
+        .await
+        .expect("finalizer stops at the deadline")
+        .unwrap()
+        .unwrap();
+    assert!(processed);
+
+    // Left for the next start to recover from its signature, and not locked.
+    let job = app.job(2).await;
+    assert_eq!(job.status, "submitted");
+    assert!(job.last_tx_sig.is_some());
+    assert_eq!(job.last_error, None);
+    assert_eq!(lock_token(&app, 2).await, None);
+    assert_eq!(app.match_status(2).await, "finalizing");
+
+    app.cleanup().await;
+}
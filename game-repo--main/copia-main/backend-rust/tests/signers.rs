@@ -0,0 +1,156 @@
+//! Authority signing through the encrypted keystore and the remote signer.
+
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use axum::http::StatusCode;
+use solana_sdk::{
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use uuid::Uuid;
+
+use backend_rust::{
+    solana::{
+        idl::Idl,
+        instructions::{create_game_ix, force_refund_ix, settle_game_ix},
+        keystore, remote_signer,
+        signer::{load_signer, LocalSigner, SignerConfig},
+    },
+    worker::finalizer::FinalizerSigners,
+};
+use common::{winner_body, TestApp};
+
+/// Starts the reference signer for `keypair` and returns its endpoint.
+async fn start_signer(listen: String, keypair: &Keypair, program_id: Pubkey) -> String {
+    let signer = Arc::new(LocalSigner::new(keypair.insecure_clone()));
+    let endpoint = match listen.strip_prefix("unix:") {
+        Some(_) => listen.clone(),
+        None => format!("http://{listen}"),
+    };
+    tokio::spawn(async move {
+        remote_signer::serve(&listen, signer, program_id, Idl::bundled().unwrap())
+            .await
+            .unwrap();
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    endpoint
+}
+
+fn free_local_addr() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.local_addr().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn keystore_unlocks_only_with_its_passphrase() {
+    let keypair = Keypair::new();
+    let path = std::env::temp_dir().join(format!("keystore-{}.json", Uuid::new_v4()));
+    let path = path.to_string_lossy().into_owned();
+    let sealed = keystore::encrypt_keypair(&keypair, "correct horse").unwrap();
+    keystore::write_keystore(&path, &sealed).unwrap();
+
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert!(raw.contains(&keypair.pubkey().to_string()));
+    assert!(!raw.contains(&keypair.to_base58_string()));
+
+    let config = SignerConfig::Keystore {
+        path: path.clone(),
+        passphrase: "correct horse".into(),
+    };
+    let signer = load_signer(&config, &keypair.pubkey()).unwrap();
+    assert_eq!(signer.pubkey(), keypair.pubkey());
+    assert!(!format!("{config:?}").contains("correct horse"));
+
+    let wrong = SignerConfig::Keystore {
+        path: path.clone(),
+        passphrase: "battery staple".into(),
+    };
+    assert!(load_signer(&wrong, &keypair.pubkey()).is_err());
+    std::fs::remove_file(&path).ok();
+}
+
+#[tokio::test]
+async fn finalizer_settles_through_an_http_remote_signer() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let endpoint = start_signer(free_local_addr(), &app.authority, app.program_id).await;
+    let signers = FinalizerSigners {
+        authorities: vec![
+            load_signer(&SignerConfig::Remote { endpoint }, &app.authority.pubkey()).unwrap(),
+        ],
+        fee_payer: None,
+    };
+    let game = app.create_game(1, true);
+
+    let (status, body) = app
+        .finalize(&winner_body(&game, &game.player1, "remote-1"))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(app.run_finalizer_with(&signers).await);
+    assert_eq!(app.job(1).await.status, "confirmed");
+    assert_eq!(app.match_status(1).await, "settled");
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn unix_socket_signer_only_signs_settlements_it_is_the_authority_of() {
+    let keypair = Keypair::new();
+    let program_id = Pubkey::new_unique();
+    let socket = std::env::temp_dir().join(format!("signer-{}.sock", Uuid::new_v4()));
+    let endpoint = start_signer(format!("unix:{}", socket.display()), &keypair, program_id).await;
+    let signer = load_signer(&SignerConfig::Remote { endpoint }, &keypair.pubkey()).unwrap();
+    let idl = Idl::bundled().unwrap();
+    let authority = keypair.pubkey();
+    let key = Pubkey::new_unique;
+    let refused = |ix: Instruction| {
+        let signer = signer.clone();
+        async move {
+            let message = Message::new(&[ix], Some(&authority));
+            let err = signer.sign_message(&message.serialize()).await.unwrap_err();
+            format!("{err:#}")
+        }
+    };
+
+    for ix in [
+        settle_game_ix(&idl, program_id, key(), key(), key(), authority).unwrap(),
+        force_refund_ix(&idl, program_id, key(), key(), key(), key(), authority).unwrap(),
+    ] {
+        let bytes = Message::new(&[ix], Some(&authority)).serialize();
+        let signature = signer.sign_message(&bytes).await.unwrap();
+        assert!(signature.verify(authority.as_ref(), &bytes));
+    }
+
+    let err = refused(Instruction::new_with_bytes(key(), &[1], vec![])).await;
+    assert!(err.contains("does not sign for"), "{err}");
+    let err = refused(Instruction::new_with_bytes(program_id, &[1, 2, 3], vec![])).await;
+    assert!(err.contains("does not decode"), "{err}");
+    let create = create_game_ix(&idl, program_id, authority, authority, 1, 1).unwrap();
+    let err = refused(create).await;
+    assert!(err.contains("does not sign create_game"), "{err}");
+    // A settlement of another authority's game, with this key only paying fees.
+    let settle = settle_game_ix(&idl, program_id, key(), key(), key(), key()).unwrap();
+    let err = refused(settle).await;
+    assert!(err.contains("authority"), "{err}");
+
+    std::fs::remove_file(&socket).ok();
+}
+
+#[tokio::test]
+async fn http_signer_refuses_to_listen_beyond_loopback() {
+    let signer = Arc::new(LocalSigner::new(Keypair::new()));
+    let err = remote_signer::serve(
+        "0.0.0.0:0",
+        signer,
+        Pubkey::new_unique(),
+        Idl::bundled().unwrap(),
+    )
+    .await
+    .unwrap_err();
+    assert!(err.to_string().contains("loopback"), "{err:#}");
+}
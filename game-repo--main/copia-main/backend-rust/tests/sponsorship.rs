@@ -0,0 +1,141 @@
+//! Gasless play: the backend sponsor co-signs relayed player transactions as fee payer.
+
+mod common;
+
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde_json::{json, Value};
+use solana_sdk::{
+    instruction::Instruction,
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use solana_sdk_ids::compute_budget;
+
+use backend_rust::solana::{gateway::ChainGateway, instructions::create_game_ix};
+use common::{write_temp_keypair, TestApp, ENTRY_LAMPORTS};
+
+async fn spawn_sponsored(wallet_daily_limit: i64) -> Option<(TestApp, Pubkey)> {
+    let sponsor = Keypair::new();
+    let path = write_temp_keypair(&sponsor);
+    let app = TestApp::spawn_with(|config| {
+        config.sponsor_keypair_path = Some(path.clone());
+        config.sponsor_wallet_daily_limit = wallet_daily_limit;
+    })
+    .await;
+    std::fs::remove_file(&path).ok();
+    app.map(|app| (app, sponsor.pubkey()))
+}
+
+async fn sponsored_create(app: &TestApp, creator: &Keypair) -> Transaction {
+    let reserved = app.reserve_match(&creator.pubkey()).await;
+    let (status, body) = app
+        .post_json(
+            "/v1/transactions/create-game",
+            &json!({ "match_id": reserved["match_id"], "sponsored": true }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let bytes = BASE64
+        .decode(body["transaction"].as_str().unwrap())
+        .unwrap();
+    let mut tx: Transaction = bincode::deserialize(&bytes).unwrap();
+    let blockhash = tx.message.recent_blockhash;
+    tx.partial_sign(&[creator], blockhash);
+    tx
+}
+
+async fn relay(app: &TestApp, tx: &Transaction) -> (StatusCode, Value) {
+    let encoded = BASE64.encode(bincode::serialize(tx).unwrap());
+    app.post_json("/v1/transactions/relay", &json!({ "transaction": encoded }))
+        .await
+}
+
+async fn sponsored_count(app: &TestApp) -> i64 {
+    sqlx::query_scalar("select count(*) from sponsored_txs")
+        .fetch_one(app.pool())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn sponsor_pays_for_a_player_holding_only_the_stake() {
+    let Some((app, sponsor)) = spawn_sponsored(1).await else {
+        return;
+    };
+    let creator = Keypair::new();
+    app.chain.airdrop(&creator.pubkey(), ENTRY_LAMPORTS);
+
+    let tx = sponsored_create(&app, &creator).await;
+    assert_eq!(tx.message.account_keys[0], sponsor);
+    let (status, body) = relay(&app, &tx).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{body}");
+    assert_eq!(body["sponsored"], true);
+    assert_eq!(app.chain.balance(&creator.pubkey()), 0);
+    assert_eq!(sponsored_count(&app).await, 1);
+
+    // The wallet's quota of one sponsored transaction is used up.
+    app.chain.airdrop(&creator.pubkey(), ENTRY_LAMPORTS);
+    let tx = sponsored_create(&app, &creator).await;
+    let (status, _) = relay(&app, &tx).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(sponsored_count(&app).await, 1);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn sponsor_only_signs_a_bare_game_instruction() {
+    let Some((app, sponsor)) = spawn_sponsored(5).await else {
+        return;
+    };
+    let creator = Keypair::new();
+    app.chain.airdrop(&creator.pubkey(), ENTRY_LAMPORTS);
+
+    let reserved = app.reserve_match(&creator.pubkey()).await;
+    let create = create_game_ix(
+        &app.default_env().idl,
+        app.program_id,
+        creator.pubkey(),
+        app.authority.pubkey(),
+        ENTRY_LAMPORTS,
+        reserved["match_id"].as_u64().unwrap(),
+    )
+    .unwrap();
+    // SetComputeUnitPrice: a priority fee the sponsor would pay.
+    let mut price = vec![3];
+    price.extend_from_slice(&1_000_000u64.to_le_bytes());
+    let priority = Instruction::new_with_bytes(compute_budget::id(), &price, vec![]);
+
+    let blockhash = app.chain.get_latest_blockhash().await.unwrap();
+    let message = Message::new_with_blockhash(&[priority, create], Some(&sponsor), &blockhash);
+    let mut tx = Transaction::new_unsigned(message);
+    tx.partial_sign(&[&creator], blockhash);
+
+    let (status, _) = relay(&app, &tx).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(sponsored_count(&app).await, 0);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn sponsored_transactions_need_a_configured_sponsor() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let reserved = app.reserve_match(&Pubkey::new_unique()).await;
+
+    let (status, _) = app
+        .post_json(
+            "/v1/transactions/create-game",
+            &json!({ "match_id": reserved["match_id"], "sponsored": true }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    app.cleanup().await;
+}
@@ -0,0 +1,85 @@
+//! Request ids, incoming trace context, and the link from a chain job back to
+//! the `/v1/finalize` request that enqueued it.
+
+mod common;
+
+use axum::{body::Body, http::Request};
+use opentelemetry::trace::TraceId;
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+use tower::ServiceExt;
+use tracing_subscriber::layer::SubscriberExt;
+
+use backend_rust::telemetry;
+use common::{winner_body, TestApp};
+
+const CALLER_TRACE: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+
+#[tokio::test]
+async fn finalizer_span_links_back_to_the_finalize_request() {
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry().with(telemetry::layer(&provider));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let game = app.create_game(1, true);
+    let raw = serde_json::to_vec(&winner_body(&game, &game.player1, "trace-1")).unwrap();
+    let timestamp = chrono::Utc::now().timestamp().to_string();
+    let request = Request::post("/v1/finalize")
+        .header("X-Timestamp", &timestamp)
+        .header("X-Nonce", "trace-nonce")
+        .header("X-Signature", common::sign(&timestamp, "trace-nonce", &raw))
+        .header("x-request-id", "req-123")
+        .header(
+            "traceparent",
+            format!("00-{CALLER_TRACE}-00f067aa0ba902b7-01"),
+        )
+        .body(Body::from(raw))
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    assert!(response.status().is_success());
+    assert_eq!(response.headers()["x-request-id"], "req-123");
+    // The request span closes with the response body.
+    drop(response);
+
+    let stored: Option<String> =
+        sqlx::query_scalar("select trace_parent from chain_jobs where match_id = 1")
+            .fetch_one(app.pool())
+            .await
+            .unwrap();
+    assert!(stored.unwrap().starts_with(&format!("00-{CALLER_TRACE}-")));
+
+    assert!(app.run_finalizer_once().await);
+    provider.force_flush().unwrap();
+
+    let spans = exporter.get_finished_spans().unwrap();
+    let caller_trace = TraceId::from_hex(CALLER_TRACE).unwrap();
+    let request_span = spans.iter().find(|span| span.name == "request").unwrap();
+    assert_eq!(request_span.span_context.trace_id(), caller_trace);
+    let job_span = spans.iter().find(|span| span.name == "chain_job").unwrap();
+    assert_ne!(job_span.span_context.trace_id(), caller_trace);
+    assert!(job_span
+        .links
+        .iter()
+        .any(|link| link.span_context.trace_id() == caller_trace));
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn responses_carry_a_generated_request_id() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+
+    let request = Request::get("/healthz").body(Body::empty()).unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    let request_id = response.headers()["x-request-id"].to_str().unwrap();
+    assert!(uuid::Uuid::parse_str(request_id).is_ok(), "{request_id}");
+
+    app.cleanup().await;
+}
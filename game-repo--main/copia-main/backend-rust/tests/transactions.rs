@@ -0,0 +1,259 @@
+//! Server-built unsigned transactions, signed by the player and either sent to the
+//! fake chain directly or through the relay.
+
+mod common;
+
+use axum::http::StatusCode;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde_json::{json, Value};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+
+use backend_rust::{
+    solana::{game_account::DecodedGameState, gateway::ChainGateway, instructions::create_game_ix},
+    worker::{create_watcher::process_waiting_matches, relay_watcher::process_relayed_txs},
+};
+use common::{TestApp, ENTRY_LAMPORTS};
+
+fn sign(body: &Value, signer: &Keypair) -> Transaction {
+    let bytes = BASE64
+        .decode(body["transaction"].as_str().unwrap())
+        .unwrap();
+    let mut tx: Transaction = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(tx.message.account_keys[0], signer.pubkey(), "signer pays");
+    let blockhash = tx.message.recent_blockhash;
+    tx.sign(&[signer], blockhash);
+    tx
+}
+
+async fn sign_and_send(app: &TestApp, body: &Value, signer: &Keypair) {
+    app.chain
+        .send_transaction(&sign(body, signer))
+        .await
+        .unwrap();
+}
+
+async fn relay(app: &TestApp, tx: &Transaction) -> (StatusCode, Value) {
+    let encoded = BASE64.encode(bincode::serialize(tx).unwrap());
+    app.post_json("/v1/transactions/relay", &json!({ "transaction": encoded }))
+        .await
+}
+
+async fn unsigned(app: &TestApp, path: &str, body: Value) -> Value {
+    let (status, body) = app.post_json(path, &body).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    body
+}
+
+#[tokio::test]
+async fn wallet_signs_server_built_create_and_join() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (creator, joiner) = (Keypair::new(), Keypair::new());
+    app.chain.airdrop(&creator.pubkey(), ENTRY_LAMPORTS * 2);
+    app.chain.airdrop(&joiner.pubkey(), ENTRY_LAMPORTS * 2);
+
+    let reserved = app.reserve_match(&creator.pubkey()).await;
+    let (status, create) = app
+        .post_json(
+            "/v1/transactions/create-game",
+            &json!({ "match_id": reserved["match_id"] }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{create}");
+    assert_eq!(create["game_pda"], reserved["game_pda"]);
+    assert_eq!(create["vault_pda"], reserved["vault_pda"]);
+    sign_and_send(&app, &create, &creator).await;
+
+    let game_pda: Pubkey = create["game_pda"].as_str().unwrap().parse().unwrap();
+    assert_eq!(
+        app.chain.game(&game_pda).unwrap().state,
+        DecodedGameState::Created
+    );
+
+    let (status, join) = app
+        .post_json(
+            "/v1/transactions/join-game",
+            &json!({ "game_pda": game_pda.to_string(), "player_pubkey": joiner.pubkey().to_string() }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{join}");
+    sign_and_send(&app, &join, &joiner).await;
+
+    let game = app.chain.game(&game_pda).unwrap();
+    assert_eq!(game.state, DecodedGameState::Joined);
+    assert_eq!(game.player2, joiner.pubkey());
+
+    // The game is full now.
+    let (status, _) = app
+        .post_json(
+            "/v1/transactions/join-game",
+            &json!({ "game_pda": game_pda.to_string(), "player_pubkey": Pubkey::new_unique().to_string() }),
+        )
+        .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn create_tx_requires_an_open_reservation() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+
+    let (status, _) = app
+        .post_json("/v1/transactions/create-game", &json!({ "match_id": 999 }))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn relayed_create_and_join_move_the_match_forward() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (creator, joiner) = (Keypair::new(), Keypair::new());
+    app.chain.airdrop(&creator.pubkey(), ENTRY_LAMPORTS * 2);
+    app.chain.airdrop(&joiner.pubkey(), ENTRY_LAMPORTS * 2);
+
+    let reserved = app.reserve_match(&creator.pubkey()).await;
+    let match_id = reserved["match_id"].as_u64().unwrap();
+    let create = unsigned(
+        &app,
+        "/v1/transactions/create-game",
+        json!({ "match_id": match_id }),
+    )
+    .await;
+    let (status, relayed) = relay(&app, &sign(&create, &creator)).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{relayed}");
+    assert_eq!(relayed["instruction"], "create_game");
+    assert_eq!(
+        app.match_column(match_id, "create_tx_sig").await.as_deref(),
+        relayed["signature"].as_str()
+    );
+
+    process_waiting_matches(&app.state, app.default_env())
+        .await
+        .unwrap();
+    assert_eq!(app.match_status(match_id).await, "created_on_chain");
+
+    let join = unsigned(
+        &app,
+        "/v1/transactions/join-game",
+        json!({ "game_pda": create["game_pda"], "player_pubkey": joiner.pubkey().to_string() }),
+    )
+    .await;
+    let (status, relayed) = relay(&app, &sign(&join, &joiner)).await;
+    assert_eq!(status, StatusCode::ACCEPTED, "{relayed}");
+    assert_eq!(relayed["match_id"].as_u64(), Some(match_id));
+
+    let changed = process_relayed_txs(&app.state, app.default_env())
+        .await
+        .unwrap();
+    assert_eq!(changed, 1);
+    assert_eq!(app.match_status(match_id).await, "joined_on_chain");
+    assert_eq!(
+        app.match_column(match_id, "player2_pubkey").await,
+        Some(joiner.pubkey().to_string())
+    );
+    assert_eq!(
+        app.match_column(match_id, "join_tx_sig").await.as_deref(),
+        relayed["signature"].as_str()
+    );
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn relay_rejects_transactions_for_someone_elses_reservation() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let (creator, intruder) = (Keypair::new(), Keypair::new());
+    app.chain.airdrop(&intruder.pubkey(), ENTRY_LAMPORTS * 2);
+
+    let reserved = app.reserve_match(&creator.pubkey()).await;
+    let match_id = reserved["match_id"].as_u64().unwrap();
+    let ix = create_game_ix(
+        &app.default_env().idl,
+        app.program_id,
+        intruder.pubkey(),
+        app.authority.pubkey(),
+        ENTRY_LAMPORTS,
+        match_id,
+    )
+    .unwrap();
+    let blockhash = app.chain.get_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&intruder.pubkey()),
+        &[&intruder],
+        blockhash,
+    );
+
+    let (status, _) = relay(&app, &tx).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert!(app
+        .chain
+        .game(
+            &app.chain
+                .game_pda(&intruder.pubkey(), &app.authority.pubkey(), match_id)
+                .0
+        )
+        .is_none());
+    assert_eq!(app.match_column(match_id, "create_tx_sig").await, None);
+
+    app.cleanup().await;
+}
+
+#[tokio::test]
+async fn failed_relayed_transaction_is_cleared_for_a_retry() {
+    let Some(app) = TestApp::spawn().await else {
+        return;
+    };
+    let creator = Keypair::new();
+    app.chain.airdrop(&creator.pubkey(), ENTRY_LAMPORTS * 2);
+
+    let reserved = app.reserve_match(&creator.pubkey()).await;
+    let match_id = reserved["match_id"].as_u64().unwrap();
+    let create = unsigned(
+        &app,
+        "/v1/transactions/create-game",
+        json!({ "match_id": match_id }),
+    )
+    .await;
+
+    app.chain.fail_next_landings(1);
+    let (status, _) = relay(&app, &sign(&create, &creator)).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    process_relayed_txs(&app.state, app.default_env())
+        .await
+        .unwrap();
+    assert_eq!(app.match_status(match_id).await, "waiting_create_tx");
+    assert_eq!(app.match_column(match_id, "create_tx_sig").await, None);
+    assert!(app
+        .match_column(match_id, "last_error")
+        .await
+        .unwrap()
+        .contains("failed on-chain"));
+
+    // A freshly signed transaction goes through.
+    let create = unsigned(
+        &app,
+        "/v1/transactions/create-game",
+        json!({ "match_id": match_id }),
+    )
+    .await;
+    let (status, _) = relay(&app, &sign(&create, &creator)).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+
+    app.cleanup().await;
+}